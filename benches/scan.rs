@@ -0,0 +1,100 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use disk_cleaner::app::ScanUpdate;
+use disk_cleaner::scanner::{self, ScanConfig, SizeOptions};
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+// Projects in each generated tree; override with DISK_CLEANER_BENCH_PROJECTS=10,500
+const DEFAULT_PROJECTS: &[usize] = &[10, 100];
+// Files in each project's `target` and `node_modules`
+const FILES_PER_TARGET: usize = 20;
+
+fn project_counts() -> Vec<usize> {
+    std::env::var("DISK_CLEANER_BENCH_PROJECTS")
+        .ok()
+        .map(|counts| {
+            counts
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_else(|| DEFAULT_PROJECTS.to_vec())
+}
+
+// Lay out `projects` projects under `root`, the same way every time: a couple of source
+// files that are walked past, plus a `target` and a `node_modules` to find and size.
+// Returns the total bytes in the matches.
+fn build_fixture(root: &Path, projects: usize) -> u64 {
+    let mut matched = 0;
+    for project in 0..projects {
+        let dir = root.join(format!("group-{}/project-{}", project % 10, project));
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("src/nested/lib.rs"), "pub fn lib() {}\n").unwrap();
+
+        for target in ["target/debug/deps", "node_modules/pkg/dist"] {
+            let target = dir.join(target);
+            fs::create_dir_all(&target).unwrap();
+            for file in 0..FILES_PER_TARGET {
+                let size = 512 * (1 + (project + file) % 8);
+                fs::write(target.join(format!("file-{}", file)), vec![0u8; size]).unwrap();
+                matched += size as u64;
+            }
+        }
+    }
+    matched
+}
+
+fn bench_directory_size(c: &mut Criterion) {
+    let mut group = c.benchmark_group("calculate_directory_size");
+    for projects in project_counts() {
+        let root = tempfile::tempdir().unwrap();
+        let bytes = build_fixture(root.path(), projects);
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(projects),
+            root.path(),
+            |b, root| {
+                b.iter(|| {
+                    scanner::calculate_directory_size(black_box(root), SizeOptions::default())
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan");
+    for projects in project_counts() {
+        let root = tempfile::tempdir().unwrap();
+        build_fixture(root.path(), projects);
+        let config = ScanConfig::builder().root(root.path()).build();
+        group.throughput(Throughput::Elements(projects as u64 * 2));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(projects),
+            &config,
+            |b, config| {
+                b.iter(|| {
+                    let mut found = 0;
+                    scanner::scan(
+                        config,
+                        |update| {
+                            if let ScanUpdate::Result(_) = update {
+                                found += 1;
+                            }
+                        },
+                        &AtomicBool::new(false),
+                    );
+                    assert_eq!(found, projects * 2);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_directory_size, bench_scan);
+criterion_main!(benches);
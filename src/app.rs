@@ -1,18 +1,40 @@
-use crate::scanner;
+use crate::browser::DirBrowser;
+use crate::checkpoint;
+use crate::cli::Cli;
+use crate::clipboard;
+use crate::delete::{self, DeleteMode, DeletionReport, TrashFailure};
+use crate::export;
+use crate::git;
+use crate::groups::{Expanded, GroupMode, Layout, Row};
+use crate::headless::ListFormat;
+use crate::history;
+use crate::ignored;
+use crate::in_use;
+use crate::keymap::{Action, KEYMAP, KeyBindings};
+use crate::mounts;
+use crate::owners::Owner;
+use crate::scanner::{self, DirStats, RootConcurrency, ScanConfig};
+use crate::session;
+use crate::stats;
+use crate::tooling::{self, CleanCommand};
+use crate::units::{SizeUnits, human_size};
+use crate::watch::TreeWatcher;
 use crossterm::event::{KeyCode, KeyEvent};
-use glob::Pattern;
-use ratatui::widgets::ListState;
+use ratatui::layout::Rect;
+use ratatui::widgets::TableState;
+use serde::{Deserialize, Serialize};
 use std::{
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
         mpsc,
     },
     thread,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime},
 };
-use walkdir::WalkDir;
 
 // App state enum
 #[derive(PartialEq, Eq)]
@@ -20,294 +42,3869 @@ pub enum AppState {
     Scanning,
     Stopping,
     ScanComplete,
+    ConfirmPreview,
+    Deleting,
     DeletionComplete,
 }
 
+// Spinner glyph sets shown while scanning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpinnerStyle {
+    Braille,
+    Ascii,
+}
+
+impl SpinnerStyle {
+    pub fn chars(self) -> &'static [char] {
+        match self {
+            SpinnerStyle::Braille => &['⠁', '⠂', '⠄', '⡀', '⢀', '⠠', '⠐', '⠈'],
+            SpinnerStyle::Ascii => &['|', '/', '-', '\\'],
+        }
+    }
+
+    // Pick the ASCII spinner when forced or when the terminal likely can't render braille
+    pub fn detect(force_ascii: bool) -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        if force_ascii || !terminal_supports_unicode(var) {
+            SpinnerStyle::Ascii
+        } else {
+            SpinnerStyle::Braille
+        }
+    }
+}
+
+// Best-effort check of the locale and terminal type for unicode support
+fn terminal_supports_unicode(var: impl Fn(&str) -> Option<String>) -> bool {
+    if var("TERM").as_deref() == Some("linux") {
+        // The Linux virtual console has no braille glyphs
+        return false;
+    }
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty());
+    match locale {
+        Some(value) => {
+            let value = value.to_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        }
+        None => true,
+    }
+}
+
+// Whether the terminal wants coloured output: honours NO_COLOR and TERM=dumb
+pub fn colors_supported(var: impl Fn(&str) -> Option<String>) -> bool {
+    if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    var("TERM").as_deref() != Some("dumb")
+}
+
+// How each row of the results list is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewMode {
+    Compact,
+    Detailed,
+}
+
+impl ViewMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            ViewMode::Compact => ViewMode::Detailed,
+            ViewMode::Detailed => ViewMode::Compact,
+        }
+    }
+}
+
+// How paths are written in the detailed rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathDisplay {
+    #[default]
+    Absolute,
+    // Relative to the scan root, which every row shares
+    Relative,
+}
+
+impl PathDisplay {
+    pub fn toggle(self) -> Self {
+        match self {
+            PathDisplay::Absolute => PathDisplay::Relative,
+            PathDisplay::Relative => PathDisplay::Absolute,
+        }
+    }
+
+    // `path` as this mode shows it. Paths outside `root` stay absolute either way.
+    pub fn render(self, path: &Path, root: &Path) -> String {
+        let path = scanner::absolute_path(path);
+        if self == PathDisplay::Relative
+            && let Ok(relative) = path.strip_prefix(scanner::absolute_path(root))
+        {
+            return match relative.as_os_str().is_empty() {
+                true => ".".to_string(),
+                false => relative.display().to_string(),
+            };
+        }
+        path.display().to_string()
+    }
+}
+
+// A column of the results table the list can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Selected,
+    Size,
+    Age,
+    Path,
+    // Name of the project a match is in; only `--sort name` uses it, there's no column
+    Name,
+}
+
+// Column the results are sorted by, and which way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub column: SortColumn,
+    pub descending: bool,
+}
+
+impl Default for SortOrder {
+    // Most recently modified first, as the list has always been ordered
+    fn default() -> Self {
+        SortOrder {
+            column: SortColumn::Age,
+            descending: false,
+        }
+    }
+}
+
+impl SortOrder {
+    // Sorting by the current column again reverses it; a new column starts with the
+    // order that's most useful there: selected, biggest and oldest first, paths A-Z
+    pub fn toggled(self, column: SortColumn) -> Self {
+        if column == self.column {
+            return SortOrder {
+                column,
+                descending: !self.descending,
+            };
+        }
+        SortOrder::starting(column)
+    }
+
+    // A column's most useful order, used when it's first picked
+    pub fn starting(column: SortColumn) -> Self {
+        let descending = match column {
+            SortColumn::Selected | SortColumn::Size | SortColumn::Age => true,
+            SortColumn::Path | SortColumn::Name => false,
+        };
+        SortOrder { column, descending }
+    }
+
+    // The same column, the other way round
+    pub fn reversed(self) -> Self {
+        SortOrder {
+            descending: !self.descending,
+            ..self
+        }
+    }
+}
+
+// Sort matches by a column; ties keep path order so the list doesn't jump around
+pub fn sort_dirs(dirs: &mut [DirInfo], order: SortOrder) {
+    dirs.sort_by(|a, b| {
+        let by_column = match order.column {
+            SortColumn::Selected => a.selected.cmp(&b.selected),
+            SortColumn::Size => a.size_bytes.cmp(&b.size_bytes),
+            SortColumn::Age => a.modified_days_ago.cmp(&b.modified_days_ago),
+            SortColumn::Path => a.path.cmp(&b.path),
+            SortColumn::Name => project_name(&a.path).cmp(&project_name(&b.path)),
+        };
+        let by_column = if order.descending {
+            by_column.reverse()
+        } else {
+            by_column
+        };
+        by_column.then_with(|| a.path.cmp(&b.path))
+    });
+}
+
+fn project_name(path: &Path) -> Option<&std::ffi::OsStr> {
+    path.parent().and_then(Path::file_name)
+}
+
 // Messages from scan thread
 pub enum ScanUpdate {
     Path(PathBuf),
     Result(DirInfo),
+    // The scan stopped early after reporting this many matches
+    LimitReached(usize),
+    // Part of the tree couldn't be read and was skipped
+    Skipped(PathBuf, String),
+    // The size and file count of a match reported before it was measured
+    SizeReady(PathBuf, DirStats),
     Done,
 }
 
+// Messages from deletion thread
+pub enum DeletionUpdate {
+    Progress(usize, usize),
+    Done(DeletionReport),
+}
+
+// How far `+`/`-` move the auto-select age threshold
+pub const AGE_STEP_DAYS: u32 = 5;
+
+// How long a toast stays on screen
+pub const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+// Above this many selected directories, deletion must be confirmed by typing the count
+pub const DEFAULT_CONFIRM_THRESHOLD: usize = 500;
+
+// Input for a confirmation that requires typing an exact value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedConfirmation {
+    pub expected: String,
+    pub input: String,
+}
+
 // Struct to represent directory information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirInfo {
     pub path: PathBuf,
+    // The target name this directory matched, e.g. `node_modules`
+    pub matched_name: String,
     pub modified_days_ago: u32,
+    pub modified: SystemTime,
     pub selected: bool,
     pub size_bytes: u64,
+    // Files under the directory, counted in the same walk as its size
+    pub file_count: u64,
+    // Part of it couldn't be read, so the size may be short; a size of 0 without this
+    // means it's really empty
+    #[serde(default)]
+    pub unreadable: bool,
+    // Owned by root or under a system prefix, so never auto-selected
+    pub system: bool,
+    // Who owns the directory; `None` where the platform has no uids
+    #[serde(default)]
+    pub owner: Option<Owner>,
+}
+
+#[cfg(test)]
+impl DirInfo {
+    // A match at `path` named after its last component, found just now: unselected, empty
+    // and readable. Tests fill in what they care about with `..DirInfo::for_test(path)`.
+    pub fn for_test(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        DirInfo {
+            matched_name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path,
+            modified_days_ago: 0,
+            modified: SystemTime::now(),
+            selected: false,
+            size_bytes: 0,
+            file_count: 0,
+            unreadable: false,
+            system: false,
+            owner: None,
+        }
+    }
+}
+
+// Largest children of one matched directory, computed when the user asks
+#[derive(Debug, Clone)]
+pub struct ContentsPopup {
+    pub path: PathBuf,
+    pub children: Vec<(PathBuf, u64)>,
+    // Bytes per file extension, largest first
+    pub extensions: Vec<(String, u64)>,
+    pub scroll: u16,
+}
+
+// How many extensions the contents popup breaks the space down into
+pub const CONTENTS_EXTENSIONS: usize = 8;
+
+// How far the walk has got through the scan root's immediate subdirectories, which is
+// the basis for the scan's ETA
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    pub started: Instant,
+    pub top_level_total: usize,
+    pub top_level_seen: usize,
+}
+
+// Too little of the tree has been walked before these for a stable estimate
+const ETA_MIN_ELAPSED: Duration = Duration::from_secs(2);
+const ETA_MIN_DONE: usize = 2;
+
+// Remaining time if the rest of the top-level directories take as long as the finished ones
+pub fn estimate_remaining(elapsed: Duration, done: usize, total: usize) -> Option<Duration> {
+    if elapsed < ETA_MIN_ELAPSED || done < ETA_MIN_DONE || done >= total {
+        return None;
+    }
+    Some(elapsed.mul_f64((total - done) as f64 / done as f64))
 }
 
 // Struct to hold scan results
 #[derive(Debug, Clone, Default)]
 pub struct ScanResults {
+    // Directories visited by the walk
     pub total_folders: usize,
+    // Matching directories found
     pub found_folders: usize,
-    pub total_size_gb: f64,
-    pub selected_size_gb: f64,
+    // Matching directories currently selected for deletion
+    pub selected_folders_count: usize,
+    pub total_size_bytes: u64,
+    pub selected_size_bytes: u64,
+    // Set when the scan stopped at `--limit` matches
+    pub limit_reached: Option<usize>,
+    // Set when the user stopped the scan, so the results are partial
+    pub stopped: bool,
+    // Matches left out by `--since-last-run` because the last run already reported them
+    pub seen_before: usize,
 }
 
 // App state
 pub struct App {
     pub state: AppState,
     pub spinner_index: usize,
+    pub spinner_style: SpinnerStyle,
     pub current_scan_path: Option<PathBuf>,
     pub scan_receiver: Option<mpsc::Receiver<ScanUpdate>>,
     pub scan_stop_signal: Arc<AtomicBool>,
-    pub deletion_summary: Option<(usize, u64)>,
-    pub folders_to_clean: Vec<String>,
+    pub deletion_receiver: Option<mpsc::Receiver<DeletionUpdate>>,
+    pub deletion_progress: (usize, usize),
+    pub deletion_report: Option<DeletionReport>,
+    // Matches found again under the parents of folders a cleanup left behind, per parent
+    pub subtree_receiver: Option<mpsc::Receiver<(PathBuf, Vec<ScanUpdate>)>>,
+    // Asked whether to empty the trash after the cleanup
+    pub pending_empty_trash: bool,
+    // How emptying the trash went, shown in the summary
+    pub empty_trash_result: Option<Result<(), String>>,
+    pub report_file: Option<PathBuf>,
+    pub report_error: Option<String>,
+    pub scan_config: ScanConfig,
+    // Checked state of each entry in `scan_config.folders_to_clean`, by index
     pub selected_folders: Vec<bool>,
-    pub ignore_patterns: Vec<String>,
-    pub current_directory: PathBuf,
     pub dirs_to_clean: Vec<DirInfo>,
-    pub dir_list_state: ListState,
+    pub dir_list_state: TableState,
+    pub sort_order: SortOrder,
+    // Where each sortable column header was last drawn, for mouse clicks
+    pub sort_headers: Vec<(Rect, SortColumn)>,
+    pub view_mode: ViewMode,
+    pub path_display: PathDisplay,
+    pub group_mode: GroupMode,
+    // Only list what will be cleaned
+    pub hide_unselected: bool,
+    // Row where a `V` range started; Space then toggles every folder up to the highlight
+    pub visual_anchor: Option<usize>,
+    // Projects whose matches are folded away in grouped mode
+    pub collapsed_groups: HashSet<PathBuf>,
+    // Matches opened inline with Right; their subdirectories are sized when first opened
+    pub expanded_dirs: Expanded,
+    // Total size of each match's parent directory, filled in one at a time once the scan is done
+    pub parent_sizes: HashMap<PathBuf, u64>,
+    // Matches still being measured in the background, with concurrent sizing
+    pub sizes_pending: HashSet<PathBuf>,
+    // Size of the filesystem that was scanned, looked up once the scan is done
+    pub disk_total: Option<u64>,
+    // What the system trash held at startup, measured in the background
+    pub trash_size: Option<u64>,
+    pub trash_size_receiver: Option<mpsc::Receiver<u64>>,
+    // Why moving to the trash doesn't work here, found by probing it before the first
+    // cleanup
+    pub trash_unavailable: Option<String>,
+    pub trash_checked: bool,
+    pub use_color: bool,
+    pub size_units: SizeUnits,
     pub confirm_action: Option<String>,
+    // Audit sessions: nothing can be cleaned up, and the session file isn't written
+    pub read_only: bool,
+    // `--require-targets` was given without any targets, so the defaults are only looked at
+    pub targets_unconfirmed: bool,
+    // Start each line of headless output with the time, for `--timestamps`
+    pub timestamps: bool,
+    // Build tools' own clean commands, tried before removal; empty without `--use-tooling`
+    pub clean_tools: Vec<CleanCommand>,
+    // Refuse to clean while the scan root's git working tree has uncommitted changes
+    pub require_clean_git: bool,
+    // Show how many files each match holds next to its size
+    pub show_file_counts: bool,
+    // Show who owns each match after its path
+    pub show_owners: bool,
+    // Matches a running process has files open in, looked up when the scan finishes
+    pub in_use: HashSet<PathBuf>,
+    // Never leave those selected
+    pub skip_in_use: bool,
+    // How headless modes print the matches
+    pub list_format: ListFormat,
+    pub key_bindings: KeyBindings,
+    pub delete_mode: DeleteMode,
+    pub confirm_threshold: usize,
+    // Cancel a confirmation left open this long without input; off unless configured
+    pub confirm_timeout: Option<Duration>,
+    // When the open confirmation was first seen, or last got input
+    pub confirm_opened: Option<Instant>,
+    // Select all but this many of the newest matches per target name once a scan ends
+    pub keep_recent: Option<usize>,
+    // Measure the selected directories again right before deleting them
+    pub refresh_sizes_before_delete: bool,
+    pub typed_confirmation: Option<TypedConfirmation>,
+    pub preview_scroll: u16,
+    pub show_help: bool,
+    pub help_scroll: u16,
+    pub show_stats: bool,
+    pub contents: Option<ContentsPopup>,
+    pub browser: Option<DirBrowser>,
+    pub session_file: Option<PathBuf>,
+    // Where each run's matches are recorded for `--since-last-run`
+    pub history_file: Option<PathBuf>,
+    // Paths ignored with `I`, excluded from every scan
+    pub ignored_file: Option<PathBuf>,
+    // Only list matches the last run of this root didn't report, or that changed since
+    pub since_last_run: bool,
+    pub last_run: Option<history::LastRun>,
+    pub pending_restore: Option<Vec<PathBuf>>,
+    // Index of the directory awaiting a single-item delete confirmation
+    pub pending_single_delete: Option<usize>,
     pub scan_results: ScanResults,
+    pub scan_progress: Option<ScanProgress>,
+    pub scan_eta: Option<Duration>,
+    // Parts of the tree the last scan couldn't read, with the reason
+    pub scan_warnings: Vec<(PathBuf, String)>,
+    // Set in watch mode; rescans when the tree changes
+    pub watcher: Option<TreeWatcher>,
+    pub watch_error: Option<String>,
+    // Selection to carry over while a watch-triggered rescan refills the list
+    pub rescan_selection: Option<HashMap<PathBuf, bool>>,
+    // Short-lived note shown over the bottom of the screen, e.g. after copying a path
+    pub toast: Option<(String, Instant)>,
     pub should_exit: bool,
 }
 
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl App {
     pub fn new() -> Self {
         App {
             state: AppState::Scanning,
             spinner_index: 0,
+            spinner_style: SpinnerStyle::Braille,
             current_scan_path: None,
             scan_receiver: None,
             scan_stop_signal: Arc::new(AtomicBool::new(false)),
-            deletion_summary: None,
-            folders_to_clean: vec!["node_modules".to_string(), "target".to_string()],
+            deletion_receiver: None,
+            subtree_receiver: None,
+            deletion_progress: (0, 0),
+            deletion_report: None,
+            pending_empty_trash: false,
+            empty_trash_result: None,
+            report_file: None,
+            report_error: None,
+            scan_config: ScanConfig::default(),
             selected_folders: vec![true, true],
-            ignore_patterns: vec![".*".to_string()],
-            current_directory: PathBuf::from("."),
             dirs_to_clean: Vec::new(),
-            dir_list_state: ListState::default(),
+            dir_list_state: TableState::default(),
+            sort_order: SortOrder::default(),
+            sort_headers: Vec::new(),
+            view_mode: ViewMode::Detailed,
+            path_display: PathDisplay::default(),
+            group_mode: GroupMode::Flat,
+            hide_unselected: false,
+            visual_anchor: None,
+            collapsed_groups: HashSet::new(),
+            expanded_dirs: Expanded::new(),
+            parent_sizes: HashMap::new(),
+            sizes_pending: HashSet::new(),
+            disk_total: None,
+            trash_size: None,
+            trash_unavailable: None,
+            trash_checked: false,
+            trash_size_receiver: None,
+            use_color: true,
+            size_units: SizeUnits::Binary,
             confirm_action: None,
+            read_only: false,
+            targets_unconfirmed: false,
+            timestamps: false,
+            clean_tools: Vec::new(),
+            require_clean_git: false,
+            show_file_counts: false,
+            show_owners: false,
+            in_use: HashSet::new(),
+            skip_in_use: false,
+            list_format: ListFormat::default(),
+            key_bindings: KeyBindings::default(),
+            delete_mode: DeleteMode::default(),
+            confirm_threshold: DEFAULT_CONFIRM_THRESHOLD,
+            confirm_timeout: None,
+            confirm_opened: None,
+            keep_recent: None,
+            refresh_sizes_before_delete: false,
+            typed_confirmation: None,
+            preview_scroll: 0,
+            show_help: false,
+            help_scroll: 0,
+            show_stats: false,
+            contents: None,
+            browser: None,
+            session_file: session::default_path(),
+            history_file: history::default_path(),
+            ignored_file: ignored::default_path(),
+            since_last_run: false,
+            last_run: None,
+            pending_restore: None,
+            pending_single_delete: None,
             scan_results: ScanResults::default(),
+            scan_progress: None,
+            scan_eta: None,
+            scan_warnings: Vec::new(),
+            watcher: None,
+            watch_error: None,
+            rescan_selection: None,
+            toast: None,
             should_exit: false,
         }
     }
 
+    pub fn from_cli(cli: &Cli) -> Self {
+        let mut builder = ScanConfig::builder()
+            .exclude(&cli.exclude)
+            .protect(&cli.protect)
+            .include_hidden(cli.include_hidden)
+            .ignore(&cli.ignore)
+            .match_full_path(cli.match_full_path)
+            .min_size_bytes(cli.min_size.unwrap_or(0))
+            .min_files(cli.min_files.unwrap_or(0))
+            .hide_empty(cli.hide_empty)
+            .min_age_days(cli.min_age.unwrap_or(0))
+            .modified_before(cli.before)
+            .owner_only(cli.owner_only)
+            .min_depth(cli.min_depth.unwrap_or(0))
+            .max_depth(cli.max_depth)
+            .follow_root_symlink(!cli.no_follow_root_symlink)
+            .local_only(cli.local_only)
+            .auto_select(!cli.no_auto_select)
+            .select_older_than_days(
+                cli.select_older_than
+                    .unwrap_or(scanner::DEFAULT_SELECT_AGE_DAYS),
+            )
+            .max_matches(cli.limit)
+            .concurrent_sizes(cli.size_in_background)
+            .size_options(scanner::SizeOptions {
+                count_hard_links: cli.count_links,
+                disk_usage: cli.disk_usage,
+            });
+        if let Some(path) = &cli.directory
+            && path.is_dir()
+        {
+            builder = builder.root(path.clone());
+        }
+        builder = builder
+            .extra_roots(cli.more_directories.clone())
+            .root_concurrency(
+                cli.roots_parallel
+                    .map_or(RootConcurrency::Sequential, RootConcurrency::Parallel),
+            );
+        if let Some(threads) = cli.max_threads {
+            builder = builder.max_threads(threads);
+        }
+        if !cli.targets.is_empty() {
+            builder = builder.targets(cli.targets.clone());
+        }
+        if !cli.target_globs.is_empty() {
+            builder = builder.target_globs(cli.target_globs.clone());
+        }
+
+        let mut app = App::new();
+        app.scan_config = builder.build();
+        app.load_ignored();
+        // Progress is only saved for a run that asked to resume, which also picks it up
+        if cli.resume {
+            app.scan_config.checkpoint = checkpoint::default_path(&app.scan_config.root);
+            app.scan_config.resume = true;
+        }
+        app.selected_folders = vec![true; app.scan_config.folders_to_clean.len()];
+        app.spinner_style = SpinnerStyle::detect(cli.ascii);
+        app.use_color = colors_supported(|name| std::env::var(name).ok());
+        app.report_file = cli.report.clone();
+        app.since_last_run = cli.since_last_run;
+        app.keep_recent = cli.keep_recent;
+        app.confirm_timeout = cli.confirm_timeout.map(Duration::from_secs);
+        app.refresh_sizes_before_delete = cli.refresh_sizes_before_delete;
+        app.targets_unconfirmed =
+            cli.require_targets && cli.targets.is_empty() && cli.target_globs.is_empty();
+        app.read_only = cli.read_only || app.targets_unconfirmed;
+        app.require_clean_git = cli.require_clean_git;
+        app.timestamps = cli.timestamps;
+        if cli.use_tooling {
+            app.clean_tools = [cli.tooling.clone(), tooling::default_commands()].concat();
+        }
+        app.show_file_counts = cli.file_counts;
+        app.show_owners = cli.show_owners;
+        app.skip_in_use = cli.skip_in_use;
+        app.list_format = cli.format;
+        if let Some(order) = cli.sort_order(SortOrder::default()) {
+            app.sort_order = order;
+        }
+        app.key_bindings = cli.key_bindings.clone();
+        app.delete_mode = if let Some(dir) = &cli.archive {
+            DeleteMode::Archive(scanner::absolute_path(dir))
+        } else if cli.permanent {
+            DeleteMode::Permanent
+        } else if cli.trash_fallback_permanent {
+            DeleteMode::Trash(TrashFailure::DeletePermanently)
+        } else {
+            DeleteMode::Trash(TrashFailure::Report)
+        };
+        if cli.si {
+            app.size_units = SizeUnits::Si;
+        }
+        if let Some(threshold) = cli.confirm_threshold {
+            app.confirm_threshold = threshold;
+        }
+        app
+    }
+
+    // Refresh the scan's ETA from the time spent on the finished top-level directories
+    pub fn update_scan_eta(&mut self) {
+        self.scan_eta = self.scan_progress.as_ref().and_then(|progress| {
+            // The directory being walked isn't done yet
+            let done = progress.top_level_seen.saturating_sub(1);
+            estimate_remaining(progress.started.elapsed(), done, progress.top_level_total)
+        });
+    }
+
+    pub fn advance_spinner(&mut self) {
+        self.spinner_index = (self.spinner_index + 1) % self.spinner_style.chars().len();
+    }
+
     pub fn start_scan(&mut self) {
         let (tx, rx) = mpsc::channel();
         self.scan_receiver = Some(rx);
         self.state = AppState::Scanning;
         self.dirs_to_clean.clear(); // Clear previous results
+        self.expanded_dirs.clear();
+        self.parent_sizes.clear();
+        self.sizes_pending.clear();
+        self.visual_anchor = None;
+        self.in_use.clear();
+        self.scan_results = ScanResults::default();
+        self.scan_warnings.clear();
+        self.last_run = match (&self.history_file, self.since_last_run) {
+            (Some(file), true) => history::load(file, &self.scan_config.root),
+            _ => None,
+        };
         self.scan_stop_signal.store(false, Ordering::SeqCst);
+        self.scan_eta = None;
+        self.scan_progress = Some(ScanProgress {
+            started: Instant::now(),
+            top_level_total: std::iter::once(&self.scan_config.root)
+                .chain(&self.scan_config.extra_roots)
+                .map(|root| {
+                    std::fs::read_dir(root)
+                        .map(|entries| {
+                            entries
+                                .flatten()
+                                .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+                                .count()
+                        })
+                        .unwrap_or(0)
+                })
+                .sum(),
+            top_level_seen: 0,
+        });
 
         let stop_signal = self.scan_stop_signal.clone();
-        let current_directory = self.current_directory.clone();
-        let folders_to_clean = self.folders_to_clean.clone();
-        let ignore_patterns = self.ignore_patterns.clone();
+        let config = self.scan_config.clone();
+        // Only the first scan picks up a checkpoint; rescans start over
+        self.scan_config.resume = false;
 
         thread::spawn(move || {
-            let ignore_patterns: Vec<Pattern> = ignore_patterns
-                .iter()
-                .map(|p| Pattern::new(p).expect("Failed to compile glob pattern"))
-                .collect();
-            let mut it = WalkDir::new(&current_directory).into_iter();
+            scanner::scan(&config, |update| drop(tx.send(update)), &stop_signal);
+        });
+    }
 
-            loop {
-                if stop_signal.load(Ordering::SeqCst) {
-                    break;
+    // Scan and wait for the results, for headless modes without an event loop
+    pub fn run_scan_blocking(&mut self) {
+        self.start_scan();
+        while let Some(receiver) = &self.scan_receiver {
+            match receiver.recv() {
+                Ok(update) => self.handle_scan_update(update),
+                Err(_) => break,
+            }
+        }
+    }
+
+    // Apply a message from the scan thread
+    pub fn handle_scan_update(&mut self, update: ScanUpdate) {
+        match update {
+            ScanUpdate::Path(path) => {
+                self.scan_results.total_folders += 1;
+                if let Some(progress) = &mut self.scan_progress
+                    && let Some(parent) = path.parent()
+                    && (parent == self.scan_config.root
+                        || self
+                            .scan_config
+                            .extra_roots
+                            .iter()
+                            .any(|root| parent == root))
+                {
+                    progress.top_level_seen += 1;
+                }
+                self.current_scan_path = Some(path);
+            }
+            ScanUpdate::Result(mut dir_info) => {
+                if let Some(last_run) = &self.last_run
+                    && !last_run.is_new(&dir_info)
+                {
+                    self.scan_results.seen_before += 1;
+                    return;
+                }
+                if let Some(selected) = self
+                    .rescan_selection
+                    .as_ref()
+                    .and_then(|previous| previous.get(&dir_info.path))
+                {
+                    dir_info.selected = *selected;
+                }
+                if self.scan_config.concurrent_sizes {
+                    self.sizes_pending.insert(dir_info.path.clone());
                 }
-                let entry = match it.next() {
-                    Some(Ok(entry)) => entry,
-                    Some(Err(_)) => continue, // or handle error
-                    None => break,
+                self.dirs_to_clean.push(dir_info);
+                sort_dirs(&mut self.dirs_to_clean, self.sort_order);
+                self.recompute_totals();
+
+                if self.dir_list_state.selected().is_none() {
+                    self.dir_list_state.select(Some(0));
+                }
+            }
+            ScanUpdate::SizeReady(path, stats) => {
+                self.sizes_pending.remove(&path);
+                let Some(index) = self.dirs_to_clean.iter().position(|d| d.path == path) else {
+                    return;
                 };
+                // `--min-size` and `--min-files` can only be checked once it's measured
+                if !self.scan_config.keeps(stats) {
+                    self.remove_dir_at(index);
+                } else {
+                    self.dirs_to_clean[index].size_bytes = stats.size_bytes;
+                    self.dirs_to_clean[index].file_count = stats.file_count;
+                    self.dirs_to_clean[index].unreadable = stats.unreadable;
+                    sort_dirs(&mut self.dirs_to_clean, self.sort_order);
+                    self.recompute_totals();
+                }
+            }
+            ScanUpdate::Skipped(path, reason) => {
+                self.scan_warnings.push((path, reason));
+            }
+            ScanUpdate::LimitReached(count) => {
+                self.scan_results.limit_reached = Some(count);
+            }
+            ScanUpdate::Done => {
+                self.query_disk_total(mounts::disk_total_bytes);
+                self.scan_progress = None;
+                self.scan_eta = None;
+                if let Some(keep) = self.keep_recent {
+                    self.select_all_but_recent(keep);
+                }
+                self.mark_in_use(in_use::open_paths);
+                self.state = AppState::ScanComplete;
+                self.scan_receiver = None;
+                self.current_scan_path = None;
+                // A watch rescan already kept the selection
+                if self.rescan_selection.take().is_none() {
+                    self.offer_session_restore();
+                }
+            }
+        }
+    }
+
+    // Flag matches other processes are using, unselecting them with `--skip-in-use`
+    fn mark_in_use(&mut self, open_paths: impl FnOnce() -> Vec<PathBuf>) {
+        let open = open_paths();
+        self.in_use =
+            in_use::dirs_in_use(self.dirs_to_clean.iter().map(|d| d.path.as_path()), &open);
+        if self.skip_in_use && !self.in_use.is_empty() {
+            for dir in &mut self.dirs_to_clean {
+                if self.in_use.contains(&dir.path) {
+                    dir.selected = false;
+                }
+            }
+            self.recompute_totals();
+        }
+    }
 
-                let path = entry.path();
-                if entry.file_type().is_dir() {
-                    let _ = tx.send(ScanUpdate::Path(path.to_path_buf()));
+    // Look up the scanned filesystem's size, unless a previous scan of the same root already did
+    fn query_disk_total(&mut self, query: impl FnOnce(&Path) -> Option<u64>) {
+        if self.disk_total.is_none() {
+            self.disk_total = query(&self.scan_config.root);
+        }
+    }
 
-                    // Check against ignore patterns
-                    let filename = path.file_name().unwrap_or_default().to_string_lossy();
-                    let should_ignore = ignore_patterns.iter().any(|p| p.matches(&filename));
+    // Watch the scan root for changes, noting why if that isn't possible
+    pub fn start_watching(&mut self) {
+        match TreeWatcher::start(&self.scan_config.root) {
+            Ok(watcher) => {
+                self.watcher = Some(watcher);
+                self.watch_error = None;
+            }
+            Err(err) => {
+                self.watcher = None;
+                self.watch_error = Some(err.to_string());
+            }
+        }
+    }
 
-                    if should_ignore {
-                        it.skip_current_dir();
-                        continue;
-                    }
-                }
+    // Collect filesystem changes and rescan once they settle, unless the user is busy
+    pub fn poll_watch(&mut self, now: Instant) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        if let Err(err) = watcher.poll(now) {
+            self.watcher = None;
+            self.watch_error = Some(err);
+            return;
+        }
+        let idle = self.state == AppState::ScanComplete
+            && self.confirm_action.is_none()
+            && self.browser.is_none()
+            && self.contents.is_none();
+        if idle && watcher.debouncer.take_due(now) {
+            self.rescan_selection = Some(
+                self.dirs_to_clean
+                    .iter()
+                    .map(|d| (d.path.clone(), d.selected))
+                    .collect(),
+            );
+            self.start_scan();
+        }
+    }
 
-                let is_dir = entry.file_type().is_dir();
-                let dir_name = entry.file_name().to_string_lossy();
-
-                if is_dir && folders_to_clean.contains(&dir_name.to_string()) {
-                    if let Ok(metadata) = entry.metadata() {
-                        let modified_time = match metadata.modified() {
-                            Ok(t) => t,
-                            Err(_) => UNIX_EPOCH,
-                        }
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs();
-
-                        let days_ago = (SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs()
-                            - modified_time)
-                            / (24 * 60 * 60);
-
-                        let dir_size = scanner::calculate_directory_size(&path.to_path_buf());
-
-                        let dir_info = DirInfo {
-                            path: path.to_path_buf(),
-                            modified_days_ago: days_ago as u32,
-                            selected: days_ago > 30, // Auto-select directories older than 30 days
-                            size_bytes: dir_size,
-                        };
-                        let _ = tx.send(ScanUpdate::Result(dir_info));
-                    }
-                    it.skip_current_dir();
+    // Offer to restore the selection saved by a previous session of this root
+    fn offer_session_restore(&mut self) {
+        let Some(file) = &self.session_file else {
+            return;
+        };
+        let saved = session::load(file, &self.scan_config.root);
+        let restorable = session::reconcile(&saved, &self.dirs_to_clean);
+        if !restorable.is_empty() && self.confirm_action.is_none() {
+            self.pending_restore = Some(restorable);
+            self.confirm_action = Some("Restore previous selection".to_string());
+        }
+    }
+
+    // Select exactly the given paths
+    pub fn restore_selection(&mut self, paths: &[PathBuf]) {
+        for dir in &mut self.dirs_to_clean {
+            dir.selected = paths.contains(&scanner::absolute_path(&dir.path));
+        }
+        self.recompute_totals();
+    }
+
+    // Scan just `root`, a directory under the scan root, and swap what it holds now into
+    // the list. Much quicker than a full rescan after cleaning up part of a big tree;
+    // matches elsewhere, and the selection of ones that are still there, are kept.
+    pub fn rescan_subtree(&mut self, root: PathBuf) {
+        let found = scan_subtree(&self.subtree_config(&root));
+        self.merge_subtree(&root, found);
+    }
+
+    // The scan settings for rescanning just `root`
+    fn subtree_config(&self, root: &Path) -> ScanConfig {
+        ScanConfig {
+            root: root.to_path_buf(),
+            extra_roots: Vec::new(),
+            max_matches: None,
+            concurrent_sizes: false,
+            checkpoint: None,
+            resume: false,
+            ..self.scan_config.clone()
+        }
+    }
+
+    // Rescan the parents of folders a cleanup couldn't remove, or only removed part of, on
+    // a worker thread; `poll_subtree_rescans` merges each one in as it finishes
+    fn start_subtree_rescans(&mut self, failed: &[PathBuf]) {
+        let roots = failed_parents(failed, &self.scan_config.root);
+        if roots.is_empty() {
+            return;
+        }
+        let configs: Vec<ScanConfig> = roots.iter().map(|root| self.subtree_config(root)).collect();
+        let (tx, rx) = mpsc::channel();
+        self.subtree_receiver = Some(rx);
+        thread::spawn(move || {
+            for config in configs {
+                let found = scan_subtree(&config);
+                if tx.send((config.root, found)).is_err() {
+                    break;
                 }
             }
-            let _ = tx.send(ScanUpdate::Done);
         });
     }
 
-    pub fn move_dirs_to_trash(&self) -> (usize, u64) {
-        let mut deleted_count = 0;
-        let mut deleted_size = 0;
+    // Merge subtree rescans that have finished into the list
+    pub fn poll_subtree_rescans(&mut self) {
+        let Some(receiver) = &self.subtree_receiver else {
+            return;
+        };
+        let mut finished = Vec::new();
+        loop {
+            match receiver.try_recv() {
+                Ok(subtree) => finished.push(subtree),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.subtree_receiver = None;
+                    break;
+                }
+            }
+        }
+        for (root, found) in finished {
+            self.merge_subtree(&root, found);
+        }
+    }
+
+    // Swap what a rescan of `root` found for what the list held under it
+    fn merge_subtree(&mut self, root: &Path, found: Vec<ScanUpdate>) {
+        let previous = self.rescan_selection.replace(
+            self.dirs_to_clean
+                .iter()
+                .filter(|d| d.path.starts_with(root))
+                .map(|d| (d.path.clone(), d.selected))
+                .collect(),
+        );
+        self.dirs_to_clean.retain(|d| !d.path.starts_with(root));
+        self.expanded_dirs.retain(|path, _| !path.starts_with(root));
+        self.scan_warnings
+            .retain(|(path, _)| !path.starts_with(root));
+        self.parent_sizes.clear();
+        for update in found {
+            self.handle_scan_update(update);
+        }
+        self.rescan_selection = previous;
+        self.recompute_totals();
+
+        let selected = match self.layout().rows.len() {
+            0 => None,
+            len => Some(self.dir_list_state.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.dir_list_state.select(selected);
+    }
 
-        for dir in &self.dirs_to_clean {
-            if dir.selected && trash::delete(&dir.path).is_ok() {
-                deleted_count += 1;
-                deleted_size += dir.size_bytes;
+    // Rescan from a different root, remembering the selection made under the old one
+    pub fn change_root(&mut self, root: PathBuf) {
+        let _ = self.save_session();
+        self.scan_config.root = root;
+        self.scan_config.resolve_root();
+        // Possibly on another filesystem
+        self.disk_total = None;
+        self.dir_list_state = TableState::default();
+        self.pending_restore = None;
+        self.rescan_selection = None;
+        self.start_scan();
+        // Follow the new root in watch mode
+        if self.watcher.is_some() {
+            self.start_watching();
+        }
+    }
+
+    fn handle_browser_key(&mut self, key: KeyEvent) {
+        let Some(browser) = &mut self.browser else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => browser.move_up(),
+            KeyCode::Down => browser.move_down(),
+            KeyCode::Enter => browser.enter(),
+            KeyCode::Backspace => browser.leave(),
+            KeyCode::Char('s') => {
+                let root = browser.path.clone();
+                self.browser = None;
+                self.change_root(root);
             }
+            KeyCode::Char('b') | KeyCode::Char('q') | KeyCode::Esc => self.browser = None,
+            _ => {}
         }
-        (deleted_count, deleted_size)
     }
 
-    pub fn update_selection_scan_results(&mut self) {
-        let (count, size) = self
+    // Remember what this run found, for `--since-last-run` next time. Only a complete scan
+    // is recorded, so folders a stopped scan never reached aren't taken as seen.
+    pub fn record_run(&self) -> std::io::Result<()> {
+        let Some(file) = &self.history_file else {
+            return Ok(());
+        };
+        let complete = matches!(
+            self.state,
+            AppState::ScanComplete | AppState::ConfirmPreview | AppState::DeletionComplete
+        ) && !self.scan_results.stopped
+            && self.scan_results.limit_reached.is_none();
+        if self.read_only || !complete {
+            return Ok(());
+        }
+        history::record(
+            file,
+            &self.scan_config.root,
+            &self.dirs_to_clean,
+            SystemTime::now(),
+        )
+    }
+
+    // Persist the current selection so it can be restored next time
+    pub fn save_session(&self) -> std::io::Result<()> {
+        let Some(file) = &self.session_file else {
+            return Ok(());
+        };
+        if self.read_only {
+            return Ok(());
+        }
+        let selected: Vec<PathBuf> = match self.state {
+            // An unanswered restore prompt keeps the previous session intact
+            AppState::ScanComplete | AppState::ConfirmPreview if self.pending_restore.is_none() => {
+                self.dirs_to_clean
+                    .iter()
+                    .filter(|d| d.selected)
+                    .map(|d| scanner::absolute_path(&d.path))
+                    .collect()
+            }
+            // Nothing left to remember once the selection has been cleaned
+            AppState::DeletionComplete => Vec::new(),
+            _ => return Ok(()),
+        };
+        session::save(file, &self.scan_config.root, &selected)
+    }
+
+    // Trash the selected directories on a worker thread, reporting progress over a channel
+    pub fn start_deletion(&mut self) {
+        if self.refresh_sizes_before_delete {
+            self.refresh_selected_sizes();
+        }
+        let (tx, rx) = mpsc::channel();
+        let dirs: Vec<DirInfo> = self
             .dirs_to_clean
             .iter()
             .filter(|d| d.selected)
-            .fold((0, 0), |(count, size), dir| {
-                (count + 1, size + dir.size_bytes)
+            .cloned()
+            .collect();
+        self.deletion_receiver = Some(rx);
+        self.deletion_progress = (0, dirs.len());
+        self.state = AppState::Deleting;
+
+        let mode = self.delete_mode.clone();
+        let tools = self.clean_tools.clone();
+        let root = self.scan_config.root.clone();
+        thread::spawn(move || {
+            let report = delete::remove_dirs(&dirs, &mode, &tools, &root, |done, total| {
+                let _ = tx.send(DeletionUpdate::Progress(done, total));
             });
-        self.scan_results.found_folders = count;
-        self.scan_results.selected_size_gb = size as f64 / (1024.0 * 1024.0 * 1024.0);
+            let _ = tx.send(DeletionUpdate::Done(report));
+        });
     }
 
-    pub fn handle_key_event(&mut self, key: KeyEvent) {
-        if let AppState::DeletionComplete = self.state {
-            match key.code {
-                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.should_exit = true,
-                _ => {}
-            }
-            return;
+    // Move the age threshold by `AGE_STEP_DAYS` and select by age again, system
+    // directories excepted
+    pub fn adjust_age_threshold(&mut self, older: bool) {
+        let days = &mut self.scan_config.select_older_than_days;
+        *days = if older {
+            days.saturating_add(AGE_STEP_DAYS)
+        } else {
+            days.saturating_sub(AGE_STEP_DAYS)
+        };
+        let threshold = *days;
+        for dir in &mut self.dirs_to_clean {
+            dir.selected = dir.modified_days_ago > threshold && !dir.system;
         }
+        self.recompute_totals();
+    }
 
-        if let Some(ref action) = self.confirm_action.clone() {
-            match key.code {
-                KeyCode::Char('y') | KeyCode::Char('Y') => {
-                    if action.starts_with("Move") {
-                        let (count, size) = self.move_dirs_to_trash();
-                        self.deletion_summary = Some((count, size));
-                        self.state = AppState::DeletionComplete;
-                    } else if action == "Stop the current scan" {
-                        self.scan_stop_signal.store(true, Ordering::SeqCst);
-                        self.state = AppState::Stopping;
-                    }
-                    self.confirm_action = None;
+    // Keep one more or one fewer of the newest matches per target, selecting the rest, as
+    // `--keep-recent` does when the scan finishes. Keeping fewer than none does nothing.
+    pub fn adjust_keep_recent(&mut self, more: bool) {
+        let keep = match (self.keep_recent, more) {
+            (None, true) => 1,
+            (None, false) => return,
+            (Some(keep), true) => keep.saturating_add(1),
+            (Some(keep), false) => keep.saturating_sub(1),
+        };
+        self.keep_recent = Some(keep);
+        self.select_all_but_recent(keep);
+    }
+
+    // Select all but the `keep` newest per target, leaving out matches in use when
+    // `--skip-in-use` is on
+    fn select_all_but_recent(&mut self, keep: usize) {
+        let none = HashSet::new();
+        let skip = if self.skip_in_use {
+            &self.in_use
+        } else {
+            &none
+        };
+        stats::keep_recent(&mut self.dirs_to_clean, keep, skip);
+        self.recompute_totals();
+    }
+
+    // Recalculate the size of every selected directory, in case it grew since the scan
+    pub fn refresh_selected_sizes(&mut self) {
+        for dir in self.dirs_to_clean.iter_mut().filter(|d| d.selected) {
+            let stats = scanner::directory_stats(&dir.path, self.scan_config.size_options);
+            dir.size_bytes = stats.size_bytes;
+            dir.file_count = stats.file_count;
+        }
+        self.recompute_totals();
+    }
+
+    // Append the finished cleanup to the CSV report, if one was requested
+    pub fn write_report(&mut self, count: usize, size: u64) {
+        let Some(file) = &self.report_file else {
+            return;
+        };
+        let row = export::ReportRow::now(&self.scan_config.root, count, size);
+        self.report_error = export::append_report_row(file, &row)
+            .err()
+            .map(|err| format!("Could not write report {}: {}", file.display(), err));
+    }
+
+    // Drain pending messages from the deletion thread
+    pub fn process_deletion_updates(&mut self) {
+        let Some(receiver) = &self.deletion_receiver else {
+            return;
+        };
+        let updates: Vec<DeletionUpdate> = receiver.try_iter().collect();
+        for update in updates {
+            match update {
+                DeletionUpdate::Progress(done, total) => {
+                    self.deletion_progress = (done, total);
                 }
-                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                    self.confirm_action = None;
+                DeletionUpdate::Done(report) => {
+                    self.write_report(report.succeeded.len(), report.bytes_freed);
+                    self.parent_sizes.clear();
+                    let failed: Vec<PathBuf> =
+                        report.failed.iter().map(|(path, _)| path.clone()).collect();
+                    self.deletion_report = Some(report);
+                    self.deletion_receiver = None;
+                    self.state = AppState::DeletionComplete;
+                    self.start_subtree_rescans(&failed);
                 }
-                _ => {}
             }
-            return;
         }
+    }
 
-        match self.state {
-            AppState::Scanning => match key.code {
-                KeyCode::Char('q') => self.should_exit = true,
-                KeyCode::Esc => {
-                    self.confirm_action = Some("Stop the current scan".to_string());
-                }
-                _ => {}
-            },
-            AppState::Stopping => {
-                // Ignore key events while stopping
+    // Size up the highlighted directory's children; only done on request since it walks the tree
+    pub fn open_contents(&mut self) {
+        let Some(dir) = self
+            .highlighted_dir()
+            .and_then(|i| self.dirs_to_clean.get(i))
+        else {
+            return;
+        };
+        self.contents = Some(ContentsPopup {
+            path: dir.path.clone(),
+            children: scanner::child_sizes(&dir.path, self.scan_config.size_options),
+            extensions: scanner::extension_sizes(
+                &dir.path,
+                self.scan_config.size_options,
+                CONTENTS_EXTENSIONS,
+            ),
+            scroll: 0,
+        });
+    }
+
+    // Exclude the paths ignored with `I` in earlier sessions
+    pub fn load_ignored(&mut self) {
+        let Some(file) = &self.ignored_file else {
+            return;
+        };
+        for path in ignored::load(file) {
+            if !self.scan_config.exclude_paths.contains(&path) {
+                self.scan_config.exclude_paths.push(path);
             }
-            AppState::ScanComplete | AppState::DeletionComplete => match key.code {
-                KeyCode::Char('q') | KeyCode::Esc => self.should_exit = true,
-                // Handle list navigation with clamped indices
-                KeyCode::Down => {
-                    // Handle list navigation down with proper bounds checking
-                    if !self.dirs_to_clean.is_empty() {
-                        let current_selection = self.dir_list_state.selected().unwrap_or(0);
-                        // Make sure we don't go beyond the list length
-                        if current_selection + 1 < self.dirs_to_clean.len() {
-                            self.dir_list_state.select(Some(current_selection + 1));
-                        }
-                    }
-                }
-                // Handle list navigation with clamped indices
-                KeyCode::Up => {
-                    // Handle list navigation up with proper bounds checking
-                    if !self.dirs_to_clean.is_empty() {
-                        let current_selection = self.dir_list_state.selected().unwrap_or(0);
-                        // Make sure we don't go below 0
-                        if current_selection > 0 {
-                            self.dir_list_state.select(Some(current_selection - 1));
-                        }
-                    }
-                }
-                KeyCode::Enter => {
-                    if !self.dirs_to_clean.is_empty() {
-                        // Proceed to confirmation when Enter is pressed in list
-                        let selected_count =
-                            self.dirs_to_clean.iter().filter(|d| d.selected).count();
-                        if selected_count > 0 {
-                            self.confirm_action =
-                                Some(format!("Move {} selected items to trash", selected_count));
-                        }
-                    }
-                }
-                KeyCode::Char(' ') => {
-                    // Toggle selection of current directory
-                    if !self.dirs_to_clean.is_empty() {
-                        if let Some(selected) = self.dir_list_state.selected() {
-                            if selected < self.dirs_to_clean.len() {
-                                self.dirs_to_clean[selected].selected =
-                                    !self.dirs_to_clean[selected].selected;
-                            }
-                        }
-                    }
-                    self.update_selection_scan_results();
-                }
-                KeyCode::Char('a') => {
-                    // Select all directories
-                    for dir in &mut self.dirs_to_clean {
-                        dir.selected = true;
-                    }
-                    self.update_selection_scan_results();
-                }
-                KeyCode::Char('d') => {
-                    // Deselect all directories
-                    for dir in &mut self.dirs_to_clean {
-                        dir.selected = false;
-                    }
-                    self.update_selection_scan_results();
-                }
-                KeyCode::Char('c') => {
-                    // Confirm deletion
-                    if !self.dirs_to_clean.is_empty() {
-                        let selected_count =
-                            self.dirs_to_clean.iter().filter(|d| d.selected).count();
-                        if selected_count > 0 {
-                            self.confirm_action =
-                                Some(format!("Move {} selected items to trash", selected_count));
-                        }
-                    }
-                }
-                _ => {}
-            },
         }
     }
+
+    // Drop the highlighted match and exclude it from this and every later scan
+    fn ignore_highlighted(&mut self) {
+        let Some(index) = self.highlighted_dir() else {
+            return;
+        };
+        let path = scanner::absolute_path(&self.dirs_to_clean[index].path);
+        // A read-only session leaves the ignored list alone, as it does the session file
+        let file = self.ignored_file.as_ref().filter(|_| !self.read_only);
+        if let Some(file) = file
+            && let Err(err) = ignored::add(file, &path)
+        {
+            self.set_toast(format!("Could not save {}: {}", file.display(), err));
+            return;
+        }
+        let message = match file {
+            Some(file) => format!(
+                "Ignoring {} from now on ({})",
+                path.display(),
+                file.display()
+            ),
+            None if self.read_only => format!(
+                "Ignoring {} until you quit; read-only, so it isn't saved",
+                path.display()
+            ),
+            None => format!("Ignoring {} until you quit", path.display()),
+        };
+        if !self.scan_config.exclude_paths.contains(&path) {
+            self.scan_config.exclude_paths.push(path);
+        }
+        self.remove_dir_at(index);
+        self.set_toast(message);
+    }
+
+    // Copy the highlighted directory's path to the clipboard
+    fn yank_highlighted(&mut self) {
+        self.yank_with(clipboard::copy);
+    }
+
+    fn yank_with(&mut self, copy: impl FnOnce(&str) -> Result<(), String>) {
+        let Some(dir) = self
+            .highlighted_dir()
+            .and_then(|i| self.dirs_to_clean.get(i))
+        else {
+            return;
+        };
+        let path = dir.path.display().to_string();
+        let message = match copy(&path) {
+            Ok(()) => format!("Copied {}", path),
+            Err(err) => format!("Could not copy: {}", err),
+        };
+        self.set_toast(message);
+    }
+
+    // Show a short-lived note; replaces any toast still on screen
+    pub fn set_toast(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), Instant::now()));
+    }
+
+    // The toast, while it's still fresh
+    pub fn current_toast(&self, now: Instant) -> Option<&str> {
+        self.toast
+            .as_ref()
+            .filter(|(_, shown)| now.duration_since(*shown) < TOAST_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    // Drop the toast once it has been up for `TOAST_DURATION`
+    pub fn expire_toast(&mut self, now: Instant) {
+        if self.current_toast(now).is_none() {
+            self.toast = None;
+        }
+    }
+
+    // Title of the deletion preview, worded for the active delete mode
+    pub fn preview_question(&self) -> String {
+        let selected = self.dirs_to_clean.iter().filter(|dir| dir.selected).count();
+        let question = self.delete_mode.confirm_question(
+            &format!(
+                "{} selected items ({})",
+                selected,
+                human_size(self.scan_results.selected_size_bytes, self.size_units)
+            ),
+            !self.clean_tools.is_empty(),
+        );
+        if self.delete_mode.is_permanent() {
+            format!("{}? This cannot be undone", question)
+        } else {
+            format!("{}?", question)
+        }
+    }
+
+    // How long cleaning up the selection should take, judging by how many files it holds
+    pub fn deletion_estimate(&self) -> Duration {
+        let (folders, files) = self
+            .dirs_to_clean
+            .iter()
+            .filter(|dir| dir.selected)
+            .fold((0, 0), |(folders, files), dir| {
+                (folders + 1, files + dir.file_count)
+            });
+        delete::estimate_duration(folders, files)
+    }
+
+    // Whether the finished cleanup put anything in a trash we know how to empty
+    pub fn can_empty_trash(&self) -> bool {
+        delete::CAN_EMPTY_TRASH
+            && matches!(self.delete_mode, DeleteMode::Trash(_))
+            && self.empty_trash_result.is_none()
+            && self
+                .deletion_report
+                .as_ref()
+                .is_some_and(|report| !report.succeeded.is_empty())
+    }
+
+    // Ask before emptying the trash; never done without an explicit yes
+    fn request_empty_trash(&mut self) {
+        let Some(report) = &self.deletion_report else {
+            return;
+        };
+        if !self.can_empty_trash() {
+            return;
+        }
+        self.confirm_action = Some(format!(
+            "Empty the trash to reclaim {} now? Everything in it is deleted for good",
+            human_size(report.bytes_freed, self.size_units)
+        ));
+        self.pending_empty_trash = true;
+    }
+
+    fn empty_trash_with(&mut self, empty: impl FnOnce() -> Result<(), String>) {
+        self.pending_empty_trash = false;
+        self.confirm_action = None;
+        let result = empty();
+        if result.is_ok() {
+            self.trash_size = None;
+        }
+        self.empty_trash_result = Some(result);
+    }
+
+    // Measure the system trash off the main thread; a big trash takes a while to add up
+    pub fn start_trash_size_query(&mut self) {
+        if !delete::CAN_EMPTY_TRASH {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            if let Ok(size) = delete::trash_size() {
+                let _ = tx.send(size);
+            }
+        });
+        self.trash_size_receiver = Some(rx);
+    }
+
+    pub fn poll_trash_size(&mut self) {
+        if let Some(receiver) = &self.trash_size_receiver
+            && let Ok(size) = receiver.try_recv()
+        {
+            self.trash_size = Some(size);
+            self.trash_size_receiver = None;
+        }
+    }
+
+    // Footer reminder that earlier cleanups are still sitting in the trash
+    pub fn trash_reminder(&self) -> Option<String> {
+        match self.trash_size {
+            Some(size) if size > 0 => Some(format!(
+                "Trash holds ~{} from prior cleanups",
+                human_size(size, self.size_units)
+            )),
+            _ => None,
+        }
+    }
+
+    // Ask before trashing just the highlighted directory
+    fn request_single_delete(&mut self) {
+        let Some(index) = self.highlighted_dir() else {
+            return;
+        };
+        let Some(dir) = self.dirs_to_clean.get(index) else {
+            return;
+        };
+        let what = dir.path.display().to_string();
+        if self.cleanup_blocked() {
+            return;
+        }
+        self.confirm_action = Some(
+            self.delete_mode
+                .confirm_question(&what, !self.clean_tools.is_empty()),
+        );
+        self.pending_single_delete = Some(index);
+    }
+
+    // Trash one directory right away, dropping it from the list if it's gone
+    fn trash_single(&mut self, index: usize) {
+        let Some(dir) = self.dirs_to_clean.get(index).cloned() else {
+            return;
+        };
+        let report = delete::remove_dirs(
+            std::slice::from_ref(&dir),
+            &self.delete_mode,
+            &self.clean_tools,
+            &self.scan_config.root,
+            |_, _| {},
+        );
+        if let Some(failure) = report.failure_lines().into_iter().next() {
+            self.set_toast(format!("Could not remove {}", failure));
+        } else {
+            self.remove_dir_at(index);
+            self.write_report(report.succeeded.len(), report.bytes_freed);
+        }
+    }
+
+    // Drop a directory from the results, keeping the highlight on a valid row
+    pub fn remove_dir_at(&mut self, index: usize) {
+        if index >= self.dirs_to_clean.len() {
+            return;
+        }
+        self.dirs_to_clean.remove(index);
+        // Its parent just got smaller
+        self.parent_sizes.clear();
+        self.recompute_totals();
+        let row = self.dir_list_state.selected().unwrap_or(index);
+        let selected = match self.layout().rows.len() {
+            0 => None,
+            len => Some(row.min(len - 1)),
+        };
+        self.dir_list_state.select(selected);
+    }
+
+    // Size the parent of the next match that doesn't have one yet. Only done while idle
+    // after a scan, so sizing parents never slows the scan itself; returns whether
+    // there was one to do
+    pub fn size_next_parent(&mut self) -> bool {
+        if self.state != AppState::ScanComplete {
+            return false;
+        }
+        let Some(parent) = self
+            .dirs_to_clean
+            .iter()
+            .filter_map(|dir| dir.path.parent())
+            .find(|parent| !self.parent_sizes.contains_key(*parent))
+            .map(Path::to_path_buf)
+        else {
+            return false;
+        };
+        let size = scanner::calculate_directory_size(&parent, self.scan_config.size_options);
+        self.parent_sizes.insert(parent, size);
+        true
+    }
+
+    // How much of its parent directory a match takes up, once the parent has been sized
+    pub fn percent_of_parent(&self, dir: &DirInfo) -> Option<u64> {
+        let parent_size = *self.parent_sizes.get(dir.path.parent()?)?;
+        match parent_size {
+            0 => None,
+            total => Some((dir.size_bytes.min(total) * 100 + total / 2) / total),
+        }
+    }
+
+    // Rows of the results list for the current grouping
+    pub fn layout(&self) -> Layout {
+        Layout::build(
+            &self.dirs_to_clean,
+            self.group_mode,
+            &self.collapsed_groups,
+            &self.expanded_dirs,
+            self.hide_unselected,
+        )
+    }
+
+    // Whether target folder `index` is checked. Every configured target is scanned, so
+    // one the flags don't cover (the two lists got out of step) shows as checked.
+    pub fn folder_selected(&self, index: usize) -> bool {
+        self.selected_folders.get(index).copied().unwrap_or(true)
+    }
+
+    // Re-sort by `column`, reversing if it's already the sort column, and keep the
+    // highlight on the same match
+    pub fn sort_by(&mut self, column: SortColumn) {
+        let highlighted = self
+            .highlighted_dir()
+            .map(|index| self.dirs_to_clean[index].path.clone());
+        self.sort_order = self.sort_order.toggled(column);
+        sort_dirs(&mut self.dirs_to_clean, self.sort_order);
+        if let Some(path) = highlighted
+            && let Some(index) = self.dirs_to_clean.iter().position(|d| d.path == path)
+        {
+            let row = self.layout().dir_row(index);
+            self.dir_list_state.select(row);
+        }
+    }
+
+    // A left click; on a column header it sorts by that column
+    pub fn handle_click(&mut self, column: u16, row: u16) {
+        let covered = self.show_help
+            || self.show_stats
+            || self.contents.is_some()
+            || self.browser.is_some()
+            || self.confirm_action.is_some();
+        if self.state != AppState::ScanComplete || covered {
+            return;
+        }
+        let clicked = self
+            .sort_headers
+            .iter()
+            .find(|(area, _)| area.contains(ratatui::layout::Position::new(column, row)))
+            .map(|(_, column)| *column);
+        if let Some(column) = clicked {
+            self.sort_by(column);
+        }
+    }
+
+    // Index into `dirs_to_clean` of the highlighted row, unless it's a group header
+    fn highlighted_dir(&self) -> Option<usize> {
+        let row = self.dir_list_state.selected()?;
+        match self.layout().rows.get(row) {
+            Some(Row::Dir(index)) => Some(*index),
+            _ => None,
+        }
+    }
+
+    // Toggle the highlighted directory, or every directory in the highlighted group
+    fn toggle_highlighted(&mut self) {
+        let Some(row) = self.dir_list_state.selected() else {
+            return;
+        };
+        let layout = self.layout();
+        match layout.rows.get(row) {
+            Some(Row::Dir(index)) => {
+                let dir = &mut self.dirs_to_clean[*index];
+                dir.selected = !dir.selected;
+            }
+            Some(Row::Group(group)) => {
+                let group = &layout.groups[*group];
+                let select = !group.all_selected(&self.dirs_to_clean);
+                for &index in &group.members {
+                    self.dirs_to_clean[index].selected = select;
+                }
+            }
+            // Subdirectories are only shown for inspection
+            Some(Row::Child { .. }) | None => {}
+        }
+        self.recompute_totals();
+    }
+
+    // Rows between the `V` anchor and the highlight, both included
+    pub fn visual_range(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let cursor = self.dir_list_state.selected()?;
+        Some(anchor.min(cursor)..=anchor.max(cursor))
+    }
+
+    // Flip every folder row in `rows`; group headers and subdirectories are left alone
+    fn toggle_rows(&mut self, rows: RangeInclusive<usize>) {
+        let layout = self.layout();
+        for row in rows {
+            if let Some(Row::Dir(index)) = layout.rows.get(row) {
+                let dir = &mut self.dirs_to_clean[*index];
+                dir.selected = !dir.selected;
+            }
+        }
+        self.recompute_totals();
+    }
+
+    // Fold or unfold the group the highlight is in, moving the highlight to its header
+    fn set_group_collapsed(&mut self, collapse: bool) {
+        let Some(row) = self.dir_list_state.selected() else {
+            return;
+        };
+        let layout = self.layout();
+        let group = match layout.rows.get(row) {
+            Some(Row::Group(group)) => *group,
+            Some(Row::Dir(index) | Row::Child { dir: index, .. }) => {
+                match layout.group_of(*index) {
+                    Some(group) => group,
+                    None => return,
+                }
+            }
+            None => return,
+        };
+        let project = layout.groups[group].project.clone();
+        if collapse {
+            self.collapsed_groups.insert(project);
+        } else {
+            self.collapsed_groups.remove(&project);
+        }
+        let header = self.layout().group_row(group);
+        self.dir_list_state.select(header);
+    }
+
+    // Right: open the highlighted match inline, or unfold the highlighted group
+    fn expand_highlighted(&mut self) {
+        let Some(index) = self.highlighted_dir() else {
+            self.set_group_collapsed(false);
+            return;
+        };
+        let path = self.dirs_to_clean[index].path.clone();
+        if !self.expanded_dirs.contains_key(&path) {
+            let subdirs = scanner::child_sizes(&path, self.scan_config.size_options)
+                .into_iter()
+                .filter(|(child, _)| child.is_dir())
+                .collect();
+            self.expanded_dirs.insert(path, subdirs);
+        }
+    }
+
+    // Left: close the match the highlight is in or on, or else fold its group
+    fn collapse_highlighted(&mut self) {
+        let Some(row) = self.dir_list_state.selected() else {
+            return;
+        };
+        let layout = self.layout();
+        let index = match layout.rows.get(row) {
+            Some(Row::Dir(index) | Row::Child { dir: index, .. }) => *index,
+            _ => return self.set_group_collapsed(true),
+        };
+        if self
+            .expanded_dirs
+            .remove(&self.dirs_to_clean[index].path)
+            .is_none()
+        {
+            return self.set_group_collapsed(true);
+        }
+        let dir_row = self.layout().dir_row(index);
+        self.dir_list_state.select(dir_row);
+    }
+
+    // Switch between the flat list and per-project groups
+    fn toggle_group_mode(&mut self) {
+        self.group_mode = self.group_mode.toggle();
+        let selected = (!self.dirs_to_clean.is_empty()).then_some(0);
+        self.dir_list_state.select(selected);
+    }
+
+    // Derive the found and selected totals from the list. Call after anything that adds,
+    // removes, resizes or (de)selects matches, so the totals can't go stale.
+    // `total_folders` counts what the walk visited rather than list entries, so it's
+    // left to the scan.
+    pub fn recompute_totals(&mut self) {
+        self.scan_results.found_folders = self.dirs_to_clean.len();
+        self.scan_results.total_size_bytes = stats::reclaimable_bytes(&self.dirs_to_clean);
+        self.scan_results.selected_folders_count =
+            self.dirs_to_clean.iter().filter(|d| d.selected).count();
+        self.scan_results.selected_size_bytes =
+            stats::reclaimable_bytes(self.dirs_to_clean.iter().filter(|d| d.selected));
+        // Rows come and go with the selection while unselected ones are hidden
+        if self.hide_unselected {
+            self.clamp_highlight();
+        }
+    }
+
+    // Keep the highlight on an existing row
+    fn clamp_highlight(&mut self) {
+        let selected = match self.layout().rows.len() {
+            0 => None,
+            len => Some(self.dir_list_state.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.dir_list_state.select(selected);
+    }
+
+    // With `--require-clean-git`, why cleaning has to wait; checked each time, since the
+    // developer may have committed in the meantime
+    pub fn cleanup_blocker(&self) -> Option<String> {
+        if self.targets_unconfirmed {
+            return Some(format!(
+                "Not cleaning: no targets were given, so only the defaults ({}) were looked for; \
+                 confirm the list with --target or a profile's targets",
+                self.scan_config.folders_to_clean.join(", ")
+            ));
+        }
+        if let Some(reason) = self.trash_unavailable_banner() {
+            return Some(format!("Not cleaning: {}", reason));
+        }
+        if !self.require_clean_git {
+            return None;
+        }
+        git::cleanup_blocker(&self.scan_config.root)
+    }
+
+    // Find out whether moving to the trash works, when that's how folders are removed.
+    // Falling back to permanent deletion, or the archive, work without it.
+    pub fn check_trash_support(&mut self) {
+        self.trash_unavailable = match self.delete_mode {
+            DeleteMode::Trash(TrashFailure::Report) if !self.read_only => {
+                delete::probe_trash(&self.scan_config.root).err()
+            }
+            _ => None,
+        };
+        self.trash_checked = true;
+    }
+
+    // Warning for when cleanups would fail for want of a trash
+    pub fn trash_unavailable_banner(&self) -> Option<String> {
+        self.trash_unavailable.as_ref().map(|reason| {
+            format!(
+                "the trash isn't available ({}); use --permanent or --archive <dir>",
+                reason
+            )
+        })
+    }
+
+    // Show why cleaning is blocked, if it is. The trash is only probed once a cleanup is
+    // asked for, so browsing never writes into the scan root.
+    fn cleanup_blocked(&mut self) -> bool {
+        if !self.trash_checked {
+            self.check_trash_support();
+        }
+        match self.cleanup_blocker() {
+            Some(reason) => {
+                self.set_toast(reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Show the selected paths for review before trashing them; unusually large
+    // selections must be confirmed by typing the count instead of a single key
+    fn request_deletion_confirm(&mut self) {
+        let selected_count = self.dirs_to_clean.iter().filter(|d| d.selected).count();
+        if selected_count == 0 || self.cleanup_blocked() {
+            return;
+        }
+        self.state = AppState::ConfirmPreview;
+        self.preview_scroll = 0;
+        self.typed_confirmation = if selected_count > self.confirm_threshold {
+            Some(TypedConfirmation {
+                expected: selected_count.to_string(),
+                input: String::new(),
+            })
+        } else {
+            None
+        };
+    }
+
+    fn handle_preview_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Up => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(1);
+                return;
+            }
+            KeyCode::Down => {
+                let max_scroll = self.scan_results.selected_folders_count.saturating_sub(1) as u16;
+                self.preview_scroll = (self.preview_scroll + 1).min(max_scroll);
+                return;
+            }
+            KeyCode::Esc => {
+                self.typed_confirmation = None;
+                self.state = AppState::ScanComplete;
+                return;
+            }
+            _ => {}
+        }
+
+        if let Some(typed) = &mut self.typed_confirmation {
+            match key.code {
+                KeyCode::Char(c) if c.is_ascii_digit() => typed.input.push(c),
+                KeyCode::Backspace => {
+                    typed.input.pop();
+                }
+                KeyCode::Enter => {
+                    if typed.input == typed.expected {
+                        self.typed_confirmation = None;
+                        self.start_deletion();
+                    } else {
+                        typed.input.clear();
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.start_deletion(),
+                KeyCode::Char('n') | KeyCode::Char('N') => self.state = AppState::ScanComplete,
+                _ => {}
+            }
+        }
+    }
+
+    // Whether a yes/no question or the deletion preview is waiting for an answer
+    fn confirm_open(&self) -> bool {
+        self.confirm_action.is_some() || self.state == AppState::ConfirmPreview
+    }
+
+    // Dismiss any open confirmation without acting on it
+    fn cancel_confirm(&mut self) {
+        self.confirm_action = None;
+        self.pending_restore = None;
+        self.pending_single_delete = None;
+        self.pending_empty_trash = false;
+        self.typed_confirmation = None;
+        if self.state == AppState::ConfirmPreview {
+            self.state = AppState::ScanComplete;
+        }
+    }
+
+    // Cancel a confirmation that has sat unanswered past the timeout, so a stray key
+    // press much later can't confirm it
+    pub fn expire_confirm(&mut self, now: Instant) {
+        if !self.confirm_open() {
+            self.confirm_opened = None;
+            return;
+        }
+        let Some(timeout) = self.confirm_timeout else {
+            return;
+        };
+        let opened = *self.confirm_opened.get_or_insert(now);
+        if now.duration_since(opened) >= timeout {
+            self.cancel_confirm();
+            self.confirm_opened = None;
+            self.toast = Some(("Confirmation timed out".to_string(), now));
+        }
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyEvent) {
+        // Any input restarts the confirmation timeout
+        self.confirm_opened = None;
+
+        if let AppState::DeletionComplete = self.state {
+            if self.pending_empty_trash {
+                match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        self.empty_trash_with(delete::empty_trash)
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.cancel_confirm(),
+                    _ => {}
+                }
+                return;
+            }
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.should_exit = true,
+                KeyCode::Char('e') => self.request_empty_trash(),
+                _ => {}
+            }
+            return;
+        }
+
+        if self.show_help {
+            match key.code {
+                KeyCode::Up => self.help_scroll = self.help_scroll.saturating_sub(1),
+                KeyCode::Down => {
+                    let max_scroll = KEYMAP.len().saturating_sub(1) as u16;
+                    self.help_scroll = (self.help_scroll + 1).min(max_scroll);
+                }
+                KeyCode::Esc => self.show_help = false,
+                code if matches!(
+                    self.key_bindings.action_for(code),
+                    Some(Action::Help | Action::Quit)
+                ) =>
+                {
+                    self.show_help = false;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if self.show_stats {
+            if key.code == KeyCode::Esc
+                || matches!(
+                    self.key_bindings.action_for(key.code),
+                    Some(Action::Stats | Action::Quit)
+                )
+            {
+                self.show_stats = false;
+            }
+            return;
+        }
+
+        if let Some(contents) = &mut self.contents {
+            match key.code {
+                KeyCode::Up => contents.scroll = contents.scroll.saturating_sub(1),
+                KeyCode::Down => {
+                    // The children, then a blank line, a heading and any extensions
+                    let lines = match contents.extensions.len() {
+                        0 => contents.children.len(),
+                        extensions => contents.children.len() + 2 + extensions,
+                    };
+                    let max_scroll = lines.saturating_sub(1) as u16;
+                    contents.scroll = (contents.scroll + 1).min(max_scroll);
+                }
+                KeyCode::Esc => self.contents = None,
+                code if matches!(
+                    self.key_bindings.action_for(code),
+                    Some(Action::Peek | Action::Quit)
+                ) =>
+                {
+                    self.contents = None
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if self.browser.is_some() {
+            self.handle_browser_key(key);
+            return;
+        }
+
+        if let AppState::ConfirmPreview = self.state {
+            self.handle_preview_key(key);
+            return;
+        }
+
+        if let Some(ref action) = self.confirm_action.clone() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    if action == "Stop the current scan" {
+                        self.scan_stop_signal.store(true, Ordering::SeqCst);
+                        self.scan_results.stopped = true;
+                        self.state = AppState::Stopping;
+                    } else if let Some(paths) = self.pending_restore.take() {
+                        self.restore_selection(&paths);
+                    } else if let Some(index) = self.pending_single_delete.take() {
+                        self.trash_single(index);
+                    }
+                    self.confirm_action = None;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.confirm_action = None;
+                    self.pending_restore = None;
+                    self.pending_single_delete = None;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let action = self.key_bindings.action_for(key.code);
+
+        if action == Some(Action::Help) {
+            self.show_help = true;
+            self.help_scroll = 0;
+            return;
+        }
+
+        // Read-only sessions never get as far as asking to clean anything up
+        if self.read_only && matches!(action, Some(Action::Clean | Action::TrashOne)) {
+            return;
+        }
+
+        match self.state {
+            AppState::Scanning => match (key.code, action) {
+                (_, Some(Action::Quit)) => self.should_exit = true,
+                (KeyCode::Esc, _) => {
+                    self.confirm_action = Some("Stop the current scan".to_string());
+                }
+                _ => {}
+            },
+            AppState::Stopping | AppState::Deleting | AppState::ConfirmPreview => {
+                // Ignore key events while stopping or deleting; the preview is handled above
+            }
+            AppState::ScanComplete | AppState::DeletionComplete => {
+                // Esc drops an open range before it quits
+                if key.code == KeyCode::Esc && self.visual_anchor.take().is_none() {
+                    self.should_exit = true;
+                    return;
+                }
+                let Some(action) = action else {
+                    return;
+                };
+                self.run_action(action);
+            }
+        }
+    }
+
+    // Carry out a bound action in the results list
+    fn run_action(&mut self, action: Action) {
+        // Anything that isn't moving through a range or toggling it may reorder the rows
+        if !matches!(
+            action,
+            Action::MoveUp | Action::MoveDown | Action::Toggle | Action::VisualRange
+        ) {
+            self.visual_anchor = None;
+        }
+        match action {
+            Action::Quit => self.should_exit = true,
+            // Handle list navigation with clamped indices
+            Action::MoveDown => {
+                // Handle list navigation down with proper bounds checking
+                let current_selection = self.dir_list_state.selected().unwrap_or(0);
+                // Make sure we don't go beyond the list length
+                if current_selection + 1 < self.layout().rows.len() {
+                    self.dir_list_state.select(Some(current_selection + 1));
+                }
+            }
+            // Handle list navigation with clamped indices
+            Action::MoveUp => {
+                // Handle list navigation up with proper bounds checking
+                let current_selection = self.dir_list_state.selected().unwrap_or(0);
+                // Make sure we don't go below 0
+                if current_selection > 0 {
+                    self.dir_list_state.select(Some(current_selection - 1));
+                }
+            }
+            Action::Clean => {
+                // Proceed to confirmation
+                self.request_deletion_confirm();
+            }
+            Action::Toggle => match self.visual_range() {
+                Some(rows) => {
+                    self.toggle_rows(rows);
+                    self.visual_anchor = None;
+                }
+                // Toggle selection of current directory, or of a whole group
+                None => self.toggle_highlighted(),
+            },
+            Action::VisualRange => {
+                self.visual_anchor = match self.visual_anchor {
+                    Some(_) => None,
+                    None => self.dir_list_state.selected(),
+                };
+            }
+            Action::SelectAll => {
+                // Select all directories
+                for dir in &mut self.dirs_to_clean {
+                    dir.selected = true;
+                }
+                self.recompute_totals();
+            }
+            Action::DeselectAll => {
+                // Deselect all directories
+                for dir in &mut self.dirs_to_clean {
+                    dir.selected = false;
+                }
+                self.recompute_totals();
+            }
+            Action::View => {
+                // Switch between compact and detailed rows
+                self.view_mode = self.view_mode.toggle();
+            }
+            Action::PathDisplay => {
+                // Drop the scan root from every path, or show it again
+                self.path_display = self.path_display.toggle();
+            }
+            Action::Group => {
+                // Group matches by the project containing them
+                self.toggle_group_mode();
+            }
+            Action::AgeUp => {
+                // Only select older directories
+                self.adjust_age_threshold(true);
+            }
+            Action::AgeDown => {
+                // Select younger directories too
+                self.adjust_age_threshold(false);
+            }
+            Action::KeepMore => self.adjust_keep_recent(true),
+            Action::KeepFewer => self.adjust_keep_recent(false),
+            Action::HideUnselected => {
+                // Show only the rows that will be cleaned, or everything again
+                self.hide_unselected = !self.hide_unselected;
+                self.clamp_highlight();
+            }
+            Action::Collapse => self.collapse_highlighted(),
+            Action::Expand => self.expand_highlighted(),
+            Action::Invert => {
+                // Invert the selection of the rows on screen; matches hidden by a filter or
+                // a collapsed group keep theirs
+                for row in self.layout().rows {
+                    if let Row::Dir(index) = row {
+                        let dir = &mut self.dirs_to_clean[index];
+                        dir.selected = !dir.selected;
+                    }
+                }
+                self.recompute_totals();
+            }
+            Action::CopyPath => {
+                // Copy the highlighted path
+                self.yank_highlighted();
+            }
+            Action::IgnoreForever => self.ignore_highlighted(),
+            Action::Peek => {
+                // Show what takes up the space in the highlighted directory
+                self.open_contents();
+            }
+            Action::Stats => {
+                // Break the matches down by age
+                self.show_stats = true;
+            }
+            Action::TrashOne => {
+                // Trash only the highlighted directory
+                self.request_single_delete();
+            }
+            Action::Browse => {
+                // Pick a different directory to scan
+                self.browser = Some(DirBrowser::open(&self.scan_config.root));
+            }
+            Action::SortSelected => self.sort_by(SortColumn::Selected),
+            Action::SortSize => self.sort_by(SortColumn::Size),
+            Action::SortAge => self.sort_by(SortColumn::Age),
+            Action::SortPath => self.sort_by(SortColumn::Path),
+            Action::Help => {}
+        }
+    }
+}
+
+// Order directories largest first
+pub fn sort_by_size_desc(dirs: &mut [DirInfo]) {
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.size_bytes));
+}
+
+// Walk `config.root`, keeping what a merge into the list needs
+fn scan_subtree(config: &ScanConfig) -> Vec<ScanUpdate> {
+    let mut found = Vec::new();
+    scanner::scan(
+        config,
+        |update| match update {
+            ScanUpdate::Result(_) | ScanUpdate::Skipped(..) => found.push(update),
+            _ => {}
+        },
+        &AtomicBool::new(false),
+    );
+    found
+}
+
+// The parents of the `failed` folders that lie under `root`, leaving out any inside
+// another, so each part of the tree is rescanned once
+fn failed_parents(failed: &[PathBuf], root: &Path) -> Vec<PathBuf> {
+    let mut parents: Vec<PathBuf> = failed
+        .iter()
+        .filter_map(|path| path.parent())
+        .filter(|parent| parent.starts_with(root))
+        .map(Path::to_path_buf)
+        .collect();
+    parents.sort();
+    parents.dedup();
+    let mut outermost: Vec<PathBuf> = Vec::new();
+    for parent in parents {
+        if !outermost.iter().any(|kept| parent.starts_with(kept)) {
+            outermost.push(parent);
+        }
+    }
+    outermost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::parse_args;
+
+    #[test]
+    fn ascii_flag_selects_ascii_spinner() {
+        let cli = parse_args(["--ascii".to_string()]).unwrap();
+        let app = App::from_cli(&cli);
+        assert_eq!(app.spinner_style, SpinnerStyle::Ascii);
+        assert_eq!(app.spinner_style.chars(), &['|', '/', '-', '\\']);
+    }
+
+    fn dir(path: &str, days_ago: u32, size_bytes: u64, selected: bool) -> DirInfo {
+        DirInfo {
+            modified_days_ago: days_ago,
+            modified: SystemTime::now() - Duration::from_secs(days_ago as u64 * 24 * 60 * 60),
+            selected,
+            size_bytes,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    #[test]
+    fn scan_results_track_found_and_selected_separately() {
+        let gib = 1024 * 1024 * 1024;
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Path(PathBuf::from("a")));
+        app.handle_scan_update(ScanUpdate::Path(PathBuf::from("a/target")));
+        app.handle_scan_update(ScanUpdate::Result(dir("a/target", 40, 2 * gib, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("b/target", 5, gib, false)));
+
+        assert_eq!(app.scan_results.total_folders, 2);
+        assert_eq!(app.scan_results.found_folders, 2);
+        assert_eq!(app.scan_results.selected_folders_count, 1);
+        assert_eq!(app.scan_results.total_size_bytes, 3 * gib);
+        assert_eq!(app.scan_results.selected_size_bytes, 2 * gib);
+
+        app.handle_scan_update(ScanUpdate::Done);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('d')));
+        assert_eq!(app.scan_results.found_folders, 2);
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+    }
+
+    #[test]
+    fn nested_matches_are_not_counted_twice() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("web/node_modules", 40, 1000, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir(
+            "web/node_modules/pkg/node_modules",
+            40,
+            300,
+            true,
+        )));
+        app.handle_scan_update(ScanUpdate::Result(dir(
+            "web-old/node_modules",
+            40,
+            50,
+            false,
+        )));
+
+        assert_eq!(app.scan_results.found_folders, 3);
+        assert_eq!(app.scan_results.total_size_bytes, 1050);
+        assert_eq!(app.scan_results.selected_folders_count, 2);
+        assert_eq!(app.scan_results.selected_size_bytes, 1000);
+
+        // With only the inner one selected, it counts on its own
+        app.dirs_to_clean
+            .iter_mut()
+            .find(|d| d.path == Path::new("web/node_modules"))
+            .unwrap()
+            .selected = false;
+        app.recompute_totals();
+        assert_eq!(app.scan_results.selected_size_bytes, 300);
+    }
+
+    #[test]
+    fn restores_previous_selection_after_scan() {
+        let root = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let old = root.path().join("old/target");
+        let new = root.path().join("new/target");
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::create_dir_all(&new).unwrap();
+
+        let mut first = App::new();
+        first.session_file = Some(state.path().join("session.json"));
+        first.scan_config.root = root.path().to_path_buf();
+        first.handle_scan_update(ScanUpdate::Result(dir(old.to_str().unwrap(), 90, 1, true)));
+        first.handle_scan_update(ScanUpdate::Result(dir(new.to_str().unwrap(), 1, 1, false)));
+        first.handle_scan_update(ScanUpdate::Done);
+        first.handle_key_event(KeyEvent::from(KeyCode::Char('a')));
+        first.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        first.save_session().unwrap();
+
+        let mut second = App::new();
+        second.session_file = first.session_file.clone();
+        second.scan_config.root = root.path().to_path_buf();
+        second.handle_scan_update(ScanUpdate::Result(dir(old.to_str().unwrap(), 90, 1, true)));
+        second.handle_scan_update(ScanUpdate::Result(dir(new.to_str().unwrap(), 1, 1, false)));
+        second.handle_scan_update(ScanUpdate::Done);
+        assert_eq!(
+            second.confirm_action.as_deref(),
+            Some("Restore previous selection")
+        );
+
+        second.handle_key_event(KeyEvent::from(KeyCode::Char('y')));
+        let selected: Vec<bool> = second.dirs_to_clean.iter().map(|d| d.selected).collect();
+        assert_eq!(selected, vec![false, true]);
+        assert!(second.pending_restore.is_none());
+    }
+
+    #[test]
+    fn invert_flips_every_selection() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("a/target", 1, 10, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("b/target", 2, 20, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("c/target", 3, 30, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('i')));
+        let selected: Vec<bool> = app.dirs_to_clean.iter().map(|d| d.selected).collect();
+        assert_eq!(selected, vec![false, true, false]);
+        assert_eq!(app.scan_results.selected_folders_count, 1);
+    }
+
+    #[test]
+    fn help_popup_captures_keys_until_closed() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('?')));
+        assert!(app.show_help);
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        assert_eq!(app.help_scroll, 1);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(!app.show_help);
+        assert!(!app.should_exit);
+    }
+
+    #[test]
+    fn view_toggle_keeps_selection_and_cursor() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("a/target", 1, 10, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("b/target", 2, 20, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('v')));
+        assert_eq!(app.view_mode, ViewMode::Compact);
+        assert_eq!(app.dir_list_state.selected(), Some(1));
+        let selected: Vec<bool> = app.dirs_to_clean.iter().map(|d| d.selected).collect();
+        assert_eq!(selected, vec![true, false]);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Up));
+        assert_eq!(app.dir_list_state.selected(), Some(0));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('v')));
+        assert_eq!(app.view_mode, ViewMode::Detailed);
+    }
+
+    #[test]
+    fn before_cutoff_skips_newer_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let old = root.path().join("old/target");
+        let new = root.path().join("new/target");
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::create_dir_all(&new).unwrap();
+        let set_date = |path: &std::path::Path, date: &str| {
+            let time = crate::cli::parse_date(date).unwrap();
+            filetime::set_file_mtime(path, filetime::FileTime::from_system_time(time)).unwrap();
+        };
+        set_date(&old, "2023-06-01");
+        set_date(&new, "2024-06-01");
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.scan_config.modified_before = Some(crate::cli::parse_date("2024-01-01").unwrap());
+        app.run_scan_blocking();
+
+        let found: Vec<&PathBuf> = app.dirs_to_clean.iter().map(|d| &d.path).collect();
+        assert_eq!(found, vec![&old]);
+    }
+
+    #[test]
+    fn pre_epoch_timestamps_do_not_stop_the_scan() {
+        let root = tempfile::tempdir().unwrap();
+        let ancient = root.path().join("ancient/target");
+        let recent = root.path().join("recent/target");
+        std::fs::create_dir_all(&ancient).unwrap();
+        std::fs::create_dir_all(&recent).unwrap();
+        filetime::set_file_mtime(&ancient, filetime::FileTime::from_unix_time(-86_400, 0)).unwrap();
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.run_scan_blocking();
+
+        assert!(app.state == AppState::ScanComplete);
+        assert_eq!(app.dirs_to_clean.len(), 2);
+        let ancient_info = app
+            .dirs_to_clean
+            .iter()
+            .find(|d| d.path == ancient)
+            .unwrap();
+        assert!(ancient_info.selected);
+    }
+
+    #[test]
+    fn browser_rescans_the_chosen_directory() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("other/target")).unwrap();
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('b')));
+        assert!(app.browser.is_some());
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        app.handle_key_event(KeyEvent::from(KeyCode::Enter));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('s')));
+
+        assert!(app.browser.is_none());
+        assert!(app.state == AppState::Scanning);
+        assert_eq!(app.scan_config.root, root.path().join("other"));
+        app.scan_stop_signal.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn removing_a_single_directory_keeps_the_cursor_valid() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 10, 100, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 20, 200, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 40, 400, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        // The prompt names the highlighted directory; declining keeps the list intact
+        app.dir_list_state.select(Some(2));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('x')));
+        assert_eq!(
+            app.confirm_action.as_deref(),
+            Some("Move /missing/c/target to trash")
+        );
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(app.dirs_to_clean.len(), 3);
+        assert!(app.pending_single_delete.is_none());
+
+        app.remove_dir_at(2);
+        assert_eq!(app.dirs_to_clean.len(), 2);
+        assert_eq!(app.dir_list_state.selected(), Some(1));
+        assert_eq!(app.scan_results.found_folders, 2);
+        assert_eq!(app.scan_results.total_size_bytes, 300);
+        assert_eq!(app.scan_results.selected_size_bytes, 200);
+
+        app.remove_dir_at(0);
+        app.remove_dir_at(0);
+        assert!(app.dirs_to_clean.is_empty());
+        assert_eq!(app.dir_list_state.selected(), None);
+    }
+
+    #[test]
+    fn grouped_mode_selects_and_collapses_whole_projects() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.handle_scan_update(ScanUpdate::Result(dir(
+            "/missing/app/target",
+            10,
+            100,
+            false,
+        )));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/web/dist", 20, 200, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/app/dist", 30, 300, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('g')));
+        assert_eq!(app.group_mode, GroupMode::ByProject);
+        assert_eq!(
+            app.layout().rows,
+            vec![
+                Row::Group(0),
+                Row::Dir(0),
+                Row::Dir(2),
+                Row::Group(1),
+                Row::Dir(1)
+            ]
+        );
+
+        // Space on a header selects every member, then deselects them again
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(app.scan_results.selected_size_bytes, 400);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+
+        // Collapsing from a member row folds the group and highlights its header
+        app.dir_list_state.select(Some(4));
+        app.handle_key_event(KeyEvent::from(KeyCode::Left));
+        assert_eq!(app.dir_list_state.selected(), Some(3));
+        assert_eq!(
+            app.layout().rows,
+            vec![Row::Group(0), Row::Dir(0), Row::Dir(2), Row::Group(1)]
+        );
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        assert_eq!(app.dir_list_state.selected(), Some(3));
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Right));
+        assert_eq!(app.layout().rows.len(), 5);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('g')));
+        assert_eq!(app.group_mode, GroupMode::Flat);
+        assert_eq!(app.dir_list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn no_auto_select_leaves_old_directories_unselected() {
+        let root = tempfile::tempdir().unwrap();
+        let old = root.path().join("old/target");
+        std::fs::create_dir_all(&old).unwrap();
+        filetime::set_file_mtime(&old, filetime::FileTime::from_unix_time(1_000_000_000, 0))
+            .unwrap();
+
+        let cli = parse_args([
+            "--no-auto-select".to_string(),
+            root.path().display().to_string(),
+        ])
+        .unwrap();
+        let mut app = App::from_cli(&cli);
+        app.session_file = None;
+        app.run_scan_blocking();
+
+        assert_eq!(app.dirs_to_clean.len(), 1);
+        assert!(!app.dirs_to_clean[0].selected);
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+    }
+
+    #[test]
+    fn watched_change_triggers_a_debounced_rescan_keeping_the_selection() {
+        let root = tempfile::tempdir().unwrap();
+        let old = root.path().join("old/target");
+        std::fs::create_dir_all(&old).unwrap();
+        filetime::set_file_mtime(&old, filetime::FileTime::from_unix_time(1_000_000_000, 0))
+            .unwrap();
+
+        let cli = parse_args([root.path().display().to_string()]).unwrap();
+        let mut app = App::from_cli(&cli);
+        app.session_file = None;
+        app.run_scan_blocking();
+        assert!(app.dirs_to_clean[0].selected);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+
+        let (tx, rx) = mpsc::channel();
+        app.watcher = Some(TreeWatcher::from_channel(rx, Duration::from_secs(2)));
+        std::fs::create_dir_all(root.path().join("new/target")).unwrap();
+        tx.send(Ok(notify::Event::new(notify::EventKind::Create(
+            notify::event::CreateKind::Folder,
+        ))))
+        .unwrap();
+
+        let now = Instant::now();
+        app.poll_watch(now);
+        assert!(app.state == AppState::ScanComplete);
+        app.poll_watch(now + Duration::from_secs(2));
+        assert!(app.state == AppState::Scanning);
+
+        while let Some(receiver) = &app.scan_receiver {
+            match receiver.recv() {
+                Ok(update) => app.handle_scan_update(update),
+                Err(_) => break,
+            }
+        }
+        assert_eq!(app.dirs_to_clean.len(), 2);
+        // The deselected folder stays deselected; the new one follows the usual rule
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+        assert!(app.rescan_selection.is_none());
+
+        drop(tx);
+        app.poll_watch(now + Duration::from_secs(3));
+        assert!(app.watcher.is_none());
+        assert!(app.watch_error.is_some());
+    }
+
+    #[test]
+    fn yank_reports_copied_path_or_clipboard_error() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 10, 100, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        let mut copied = String::new();
+        app.yank_with(|text| {
+            copied = text.to_string();
+            Ok(())
+        });
+        assert_eq!(copied, "/missing/a/target");
+        let now = Instant::now();
+        assert_eq!(app.current_toast(now), Some("Copied /missing/a/target"));
+        assert_eq!(app.current_toast(now + TOAST_DURATION), None);
+
+        app.yank_with(|_| Err("no display".to_string()));
+        assert_eq!(
+            app.current_toast(Instant::now()),
+            Some("Could not copy: no display")
+        );
+    }
+
+    #[test]
+    fn since_last_run_lists_only_new_or_changed_matches() {
+        let root = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let old = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        for project in ["a", "b"] {
+            let target = root.path().join(project).join("target");
+            std::fs::create_dir_all(&target).unwrap();
+            filetime::set_file_mtime(&target, old).unwrap();
+        }
+        let scan = |since_last_run: bool| {
+            let mut app = App::new();
+            app.session_file = None;
+            app.history_file = Some(data.path().join("history.json"));
+            app.since_last_run = since_last_run;
+            app.scan_config.root = root.path().to_path_buf();
+            app.run_scan_blocking();
+            app
+        };
+        let found = |app: &App| -> Vec<PathBuf> {
+            let mut paths: Vec<PathBuf> = app
+                .dirs_to_clean
+                .iter()
+                .map(|d| d.path.strip_prefix(root.path()).unwrap().to_path_buf())
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        // No recorded run yet: everything is new
+        let first = scan(true);
+        assert_eq!(
+            found(&first),
+            [Path::new("a/target"), Path::new("b/target")]
+        );
+        first.record_run().unwrap();
+
+        // A new project, and one of the old ones rebuilt
+        let fresh = root.path().join("c/target");
+        std::fs::create_dir_all(&fresh).unwrap();
+        filetime::set_file_mtime(&fresh, old).unwrap();
+        filetime::set_file_mtime(root.path().join("b/target"), filetime::FileTime::now()).unwrap();
+
+        let since = scan(true);
+        assert_eq!(
+            found(&since),
+            [Path::new("b/target"), Path::new("c/target")]
+        );
+        assert_eq!(since.scan_results.seen_before, 1);
+
+        let everything = scan(false);
+        assert_eq!(everything.dirs_to_clean.len(), 3);
+        assert_eq!(everything.scan_results.seen_before, 0);
+    }
+
+    #[test]
+    fn totals_follow_every_change_to_the_list() {
+        let mut app = App::new();
+        app.session_file = None;
+        let totals = |app: &App| {
+            let r = &app.scan_results;
+            (
+                r.found_folders,
+                r.total_size_bytes,
+                r.selected_folders_count,
+                r.selected_size_bytes,
+            )
+        };
+
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 5, 100, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 50, 200, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 90, 400, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+        assert_eq!(totals(&app), (3, 700, 2, 600));
+
+        // Selection changes
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('i')));
+        assert_eq!(totals(&app), (3, 700, 1, 100));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('a')));
+        assert_eq!(totals(&app), (3, 700, 3, 700));
+
+        // A size measured later
+        let b = app
+            .dirs_to_clean
+            .iter()
+            .position(|d| d.path.ends_with("b/target"))
+            .unwrap();
+        app.dirs_to_clean[b].size_bytes = 1000;
+        app.recompute_totals();
+        assert_eq!(totals(&app), (3, 1500, 3, 1500));
+
+        // A match leaving the list
+        app.remove_dir_at(b);
+        assert_eq!(totals(&app), (2, 500, 2, 500));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('d')));
+        assert_eq!(totals(&app), (2, 500, 0, 0));
+    }
+
+    #[test]
+    fn sorting_by_column_and_reversing() {
+        let mut dirs = vec![
+            dir("/p/b/target", 10, 300, true),
+            dir("/p/a/target", 90, 100, false),
+            dir("/p/c/target", 40, 300, false),
+        ];
+        let paths = |dirs: &[DirInfo]| -> Vec<String> {
+            dirs.iter()
+                .map(|d| d.path.parent().unwrap().display().to_string())
+                .collect()
+        };
+
+        sort_dirs(&mut dirs, SortOrder::default());
+        assert_eq!(paths(&dirs), ["/p/b", "/p/c", "/p/a"]);
+
+        // Biggest first, equal sizes in path order
+        let by_size = SortOrder::default().toggled(SortColumn::Size);
+        assert!(by_size.descending);
+        sort_dirs(&mut dirs, by_size);
+        assert_eq!(paths(&dirs), ["/p/b", "/p/c", "/p/a"]);
+        sort_dirs(&mut dirs, by_size.toggled(SortColumn::Size));
+        assert_eq!(paths(&dirs), ["/p/a", "/p/b", "/p/c"]);
+
+        sort_dirs(&mut dirs, by_size.toggled(SortColumn::Path));
+        assert_eq!(paths(&dirs), ["/p/a", "/p/b", "/p/c"]);
+        sort_dirs(&mut dirs, by_size.toggled(SortColumn::Selected));
+        assert_eq!(paths(&dirs), ["/p/b", "/p/a", "/p/c"]);
+
+        // Switching to age starts with the oldest; the default order is the reverse
+        let oldest_first = by_size.toggled(SortColumn::Age);
+        sort_dirs(&mut dirs, oldest_first);
+        assert_eq!(paths(&dirs), ["/p/a", "/p/c", "/p/b"]);
+        assert_eq!(oldest_first.toggled(SortColumn::Age), SortOrder::default());
+    }
+
+    #[test]
+    fn number_keys_sort_and_keep_the_highlighted_match() {
+        let mut app = App::new();
+        app.session_file = None;
+        for (path, age, size) in [("/p/a/target", 5, 100), ("/p/b/target", 50, 900)] {
+            app.handle_scan_update(ScanUpdate::Result(dir(path, age, size, true)));
+        }
+        app.handle_scan_update(ScanUpdate::Done);
+        app.dir_list_state.select(Some(0));
+        assert_eq!(app.dirs_to_clean[0].path, PathBuf::from("/p/a/target"));
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('2')));
+        assert_eq!(app.dirs_to_clean[0].path, PathBuf::from("/p/b/target"));
+        assert_eq!(app.dir_list_state.selected(), Some(1));
+
+        // Later matches slot into the chosen order
+        app.handle_scan_update(ScanUpdate::Result(dir("/p/c/target", 1, 500, true)));
+        assert_eq!(app.dirs_to_clean[1].path, PathBuf::from("/p/c/target"));
+
+        // Reversed, with the highlight still on the row it was on
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('2')));
+        assert_eq!(app.dirs_to_clean[0].path, PathBuf::from("/p/a/target"));
+        assert_eq!(app.dirs_to_clean[2].path, PathBuf::from("/p/b/target"));
+        assert_eq!(app.dir_list_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn expired_toast_is_cleared() {
+        let mut app = App::new();
+        app.set_toast("Exported 3 folders");
+        let shown = app.toast.as_ref().unwrap().1;
+
+        app.expire_toast(shown + TOAST_DURATION / 2);
+        assert_eq!(
+            app.current_toast(shown + TOAST_DURATION / 2),
+            Some("Exported 3 folders")
+        );
+
+        app.expire_toast(shown + TOAST_DURATION);
+        assert!(app.toast.is_none());
+
+        // A new toast replaces the old one and restarts the clock
+        app.set_toast("first");
+        app.set_toast("second");
+        assert_eq!(app.current_toast(Instant::now()), Some("second"));
+    }
+
+    #[test]
+    fn right_and_left_expand_and_collapse_a_match_inline() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("app/target");
+        std::fs::create_dir_all(target.join("debug")).unwrap();
+        std::fs::create_dir_all(target.join("release")).unwrap();
+        std::fs::write(target.join("debug/app"), vec![0; 3000]).unwrap();
+        std::fs::write(target.join("CACHEDIR.TAG"), b"tag").unwrap();
+
+        let mut app = App::new();
+        app.state = AppState::ScanComplete;
+        app.handle_scan_update(ScanUpdate::Result(DirInfo {
+            size_bytes: 3003,
+            ..DirInfo::for_test(&target)
+        }));
+        app.dir_list_state.select(Some(0));
+
+        // Only subdirectories are listed, largest first
+        app.handle_key_event(KeyEvent::from(KeyCode::Right));
+        let children = &app.expanded_dirs[&target];
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].0, target.join("debug"));
+        assert_eq!(
+            app.layout().rows,
+            vec![
+                Row::Dir(0),
+                Row::Child { dir: 0, child: 0 },
+                Row::Child { dir: 0, child: 1 }
+            ]
+        );
+
+        // Left from a child closes its match and moves back onto it
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        assert_eq!(app.highlighted_dir(), None);
+        app.handle_key_event(KeyEvent::from(KeyCode::Left));
+        assert!(app.expanded_dirs.is_empty());
+        assert_eq!(app.layout().rows, vec![Row::Dir(0)]);
+        assert_eq!(app.dir_list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn size_updates_patch_matches_reported_before_measuring() {
+        let mut app = App::new();
+        app.scan_config.concurrent_sizes = true;
+        app.scan_config.min_size_bytes = 50;
+        app.state = AppState::Scanning;
+        for path in ["/missing/a/target", "/missing/b/target"] {
+            app.handle_scan_update(ScanUpdate::Result(DirInfo {
+                modified_days_ago: 40,
+                selected: true,
+                ..DirInfo::for_test(path)
+            }));
+        }
+        assert_eq!(app.sizes_pending.len(), 2);
+        assert_eq!(app.scan_results.total_size_bytes, 0);
+
+        app.handle_scan_update(ScanUpdate::SizeReady(
+            PathBuf::from("/missing/a/target"),
+            DirStats {
+                size_bytes: 300,
+                file_count: 3,
+                unreadable: false,
+            },
+        ));
+        assert_eq!(app.dirs_to_clean[0].size_bytes, 300);
+        assert_eq!(app.dirs_to_clean[0].file_count, 3);
+        assert_eq!(app.scan_results.total_size_bytes, 300);
+        assert_eq!(app.scan_results.selected_size_bytes, 300);
+        assert!(!app.sizes_pending.contains(Path::new("/missing/a/target")));
+
+        // Too small once measured: dropped, as if it had never been reported
+        app.handle_scan_update(ScanUpdate::SizeReady(
+            PathBuf::from("/missing/b/target"),
+            DirStats {
+                size_bytes: 10,
+                file_count: 3,
+                unreadable: false,
+            },
+        ));
+        assert_eq!(app.dirs_to_clean.len(), 1);
+        assert_eq!(app.scan_results.found_folders, 1);
+        assert!(app.sizes_pending.is_empty());
+    }
+
+    #[test]
+    fn concurrent_sizing_measures_every_match_before_done() {
+        let root = tempfile::tempdir().unwrap();
+        for project in ["a", "b", "c"] {
+            let target = root.path().join(project).join("target");
+            std::fs::create_dir_all(&target).unwrap();
+            std::fs::write(target.join("bin"), vec![0; 1000]).unwrap();
+        }
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.scan_config.concurrent_sizes = true;
+        app.run_scan_blocking();
+        assert_eq!(app.dirs_to_clean.len(), 3);
+        assert!(app.dirs_to_clean.iter().all(|d| d.size_bytes >= 1000));
+        assert!(app.sizes_pending.is_empty());
+    }
+
+    #[test]
+    fn disk_size_is_looked_up_once_per_root() {
+        let mut app = App::new();
+        let mut queries = 0;
+        let mut query = |_: &Path| {
+            queries += 1;
+            Some(1000)
+        };
+        app.query_disk_total(&mut query);
+        app.query_disk_total(&mut query);
+        assert_eq!(queries, 1);
+        assert_eq!(app.disk_total, Some(1000));
+    }
+
+    #[test]
+    fn remapped_keys_trigger_their_action() {
+        let mut app = App::new();
+        app.state = AppState::ScanComplete;
+        app.key_bindings = KeyBindings::default()
+            .with_overrides(&std::collections::BTreeMap::from([(
+                "select_all".to_string(),
+                vec!["r".to_string()],
+            )]))
+            .unwrap();
+        app.handle_scan_update(ScanUpdate::Result(DirInfo {
+            size_bytes: 100,
+            ..DirInfo::for_test("/missing/app/target")
+        }));
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('a')));
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('r')));
+        assert_eq!(app.scan_results.selected_folders_count, 1);
+    }
+
+    #[test]
+    fn dirty_git_root_blocks_the_cleanup_keys() {
+        let root = tempfile::tempdir().unwrap();
+        let init = std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(root.path())
+            .status()
+            .unwrap();
+        assert!(init.success());
+        std::fs::write(root.path().join("wip.rs"), "").unwrap();
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.require_clean_git = true;
+        app.scan_config.root = root.path().to_path_buf();
+        app.state = AppState::ScanComplete;
+        app.handle_scan_update(ScanUpdate::Result(DirInfo {
+            modified_days_ago: 90,
+            selected: true,
+            size_bytes: 100,
+            ..DirInfo::for_test(root.path().join("target"))
+        }));
+        app.dir_list_state.select(Some(0));
+
+        for code in [KeyCode::Char('c'), KeyCode::Char('x')] {
+            app.handle_key_event(KeyEvent::from(code));
+            assert!(app.state == AppState::ScanComplete);
+            assert!(app.confirm_action.is_none());
+            let (toast, _) = app.toast.clone().unwrap();
+            assert!(toast.contains("1 uncommitted change "));
+        }
+
+        // Once the work is committed away, cleaning goes ahead
+        std::fs::remove_file(root.path().join("wip.rs")).unwrap();
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        assert!(app.state == AppState::ConfirmPreview);
+    }
+
+    #[test]
+    fn require_targets_without_any_disables_cleaning() {
+        let cli = Cli {
+            require_targets: true,
+            ..Cli::default()
+        };
+        let mut app = App::from_cli(&cli);
+        assert!(app.read_only);
+        assert_eq!(
+            app.cleanup_blocker().unwrap(),
+            "Not cleaning: no targets were given, so only the defaults (node_modules, target) \
+             were looked for; confirm the list with --target or a profile's targets"
+        );
+        app.session_file = None;
+        app.state = AppState::ScanComplete;
+        app.handle_scan_update(ScanUpdate::Result(dir(
+            "/missing/app/target",
+            90,
+            100,
+            true,
+        )));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        assert!(app.state == AppState::ScanComplete);
+
+        let cli = Cli {
+            require_targets: true,
+            targets: vec!["target".to_string()],
+            ..Cli::default()
+        };
+        let app = App::from_cli(&cli);
+        assert!(!app.read_only);
+        assert_eq!(app.cleanup_blocker(), None);
+    }
+
+    #[test]
+    fn only_resumed_scans_save_a_checkpoint() {
+        let app = App::from_cli(&Cli::default());
+        assert_eq!(app.scan_config.checkpoint, None);
+        assert!(!app.scan_config.resume);
+
+        let cli = Cli {
+            resume: true,
+            ..Cli::default()
+        };
+        let app = App::from_cli(&cli);
+        assert_eq!(
+            app.scan_config.checkpoint,
+            checkpoint::default_path(&app.scan_config.root)
+        );
+        assert!(app.scan_config.resume);
+    }
+
+    #[test]
+    fn read_only_ignores_the_cleanup_keys() {
+        let mut app = App::new();
+        app.read_only = true;
+        app.session_file = None;
+        app.state = AppState::ScanComplete;
+        app.handle_scan_update(ScanUpdate::Result(DirInfo {
+            modified_days_ago: 90,
+            selected: true,
+            size_bytes: 100,
+            ..DirInfo::for_test("/missing/app/target")
+        }));
+        app.dir_list_state.select(Some(0));
+
+        for code in [
+            KeyCode::Char('c'),
+            KeyCode::Enter,
+            KeyCode::Char('x'),
+            KeyCode::Delete,
+        ] {
+            app.handle_key_event(KeyEvent::from(code));
+            assert!(app.state == AppState::ScanComplete);
+            assert!(app.confirm_action.is_none());
+            assert_eq!(app.dirs_to_clean.len(), 1);
+        }
+
+        // Browsing and selecting still work
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+    }
+
+    #[test]
+    fn matches_show_their_share_of_the_parent_once_sized() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("app");
+        let target = project.join("target");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(target.join("bin"), vec![0; 3000]).unwrap();
+        std::fs::write(project.join("src/main.rs"), vec![0; 1000]).unwrap();
+
+        let mut app = App::new();
+        app.state = AppState::Scanning;
+        app.handle_scan_update(ScanUpdate::Result(DirInfo {
+            size_bytes: 3000,
+            ..DirInfo::for_test(&target)
+        }));
+        let dir = app.dirs_to_clean[0].clone();
+
+        // Nothing is sized while the scan is running
+        assert!(!app.size_next_parent());
+        assert_eq!(app.percent_of_parent(&dir), None);
+
+        app.state = AppState::ScanComplete;
+        assert!(app.size_next_parent());
+        assert_eq!(app.parent_sizes[&project], 4000);
+        assert_eq!(app.percent_of_parent(&dir), Some(75));
+        assert!(!app.size_next_parent());
+    }
+
+    #[test]
+    fn confirming_stop_marks_the_results_partial() {
+        let mut app = App::new();
+        app.state = AppState::Scanning;
+        app.handle_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(!app.scan_results.stopped);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('y')));
+        assert!(app.state == AppState::Stopping);
+        assert!(app.scan_results.stopped);
+        assert!(app.scan_stop_signal.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn limit_stops_the_scan_after_that_many_matches() {
+        let root = tempfile::tempdir().unwrap();
+        for project in ["a", "b", "c"] {
+            std::fs::create_dir_all(root.path().join(project).join("target")).unwrap();
+        }
+
+        let cli = parse_args([
+            "--limit".to_string(),
+            "2".to_string(),
+            root.path().display().to_string(),
+        ])
+        .unwrap();
+        let mut app = App::from_cli(&cli);
+        app.session_file = None;
+        app.run_scan_blocking();
+
+        assert_eq!(app.dirs_to_clean.len(), 2);
+        assert_eq!(app.scan_results.limit_reached, Some(2));
+        assert!(app.state == AppState::ScanComplete);
+
+        // Without a limit every match is reported
+        app.scan_config.max_matches = None;
+        app.run_scan_blocking();
+        assert_eq!(app.dirs_to_clean.len(), 3);
+        assert_eq!(app.scan_results.limit_reached, None);
+    }
+
+    #[test]
+    fn hidden_unselected_rows_are_skipped_by_navigation() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 10, 100, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 20, 200, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 30, 300, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('h')));
+        assert_eq!(app.layout().rows, vec![Row::Dir(0), Row::Dir(2)]);
+        assert_eq!(app.dirs_to_clean.len(), 3);
+
+        // Down goes straight from a to c, and no further
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        assert_eq!(app.highlighted_dir(), Some(2));
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        assert_eq!(app.highlighted_dir(), Some(2));
+
+        // Deselecting c hides it and moves the highlight back onto a
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(app.highlighted_dir(), Some(0));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(app.dir_list_state.selected(), None);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('h')));
+        assert_eq!(app.layout().rows.len(), 3);
+        assert_eq!(app.dir_list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn age_threshold_keys_reselect_by_age() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 10, 100, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 33, 200, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 40, 400, true)));
+        let mut system = dir("/missing/usr/target", 90, 800, false);
+        system.system = true;
+        app.handle_scan_update(ScanUpdate::Result(system));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        let selected =
+            |app: &App| -> Vec<bool> { app.dirs_to_clean.iter().map(|d| d.selected).collect() };
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('+')));
+        assert_eq!(app.scan_config.select_older_than_days, 35);
+        assert_eq!(selected(&app), vec![false, false, true, false]);
+        assert_eq!(app.scan_results.selected_size_bytes, 400);
+
+        for _ in 0..6 {
+            app.handle_key_event(KeyEvent::from(KeyCode::Char('-')));
+        }
+        assert_eq!(app.scan_config.select_older_than_days, 5);
+        assert_eq!(selected(&app), vec![true, true, true, false]);
+        assert_eq!(app.scan_results.selected_size_bytes, 700);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('-')));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('-')));
+        assert_eq!(app.scan_config.select_older_than_days, 0);
+    }
+
+    #[test]
+    fn unanswered_confirmation_is_cancelled_after_the_timeout() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 100, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+        let start = Instant::now();
+
+        // Off by default: the dialog stays open however long it waits
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        app.expire_confirm(start);
+        app.expire_confirm(start + Duration::from_secs(3600));
+        assert!(app.state == AppState::ConfirmPreview);
+
+        app.confirm_timeout = Some(Duration::from_secs(30));
+        app.expire_confirm(start);
+        app.expire_confirm(start + Duration::from_secs(29));
+        assert!(app.state == AppState::ConfirmPreview);
+        app.expire_confirm(start + Duration::from_secs(30));
+        assert!(app.state == AppState::ScanComplete);
+        assert!(app.typed_confirmation.is_none());
+
+        // Single deletes are covered too, and forget what they were about to remove
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('x')));
+        app.expire_confirm(start);
+        app.expire_confirm(start + Duration::from_secs(30));
+        assert!(app.confirm_action.is_none());
+        assert!(app.pending_single_delete.is_none());
+        assert_eq!(app.dirs_to_clean.len(), 1);
+    }
+
+    #[test]
+    fn keep_recent_applies_when_the_scan_finishes() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.keep_recent = Some(1);
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 1, 10, false)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 90, 20, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 5, 40, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        let selected: Vec<(&str, bool)> = app
+            .dirs_to_clean
+            .iter()
+            .map(|d| (d.path.to_str().unwrap(), d.selected))
+            .collect();
+        assert_eq!(
+            selected,
+            vec![
+                ("/missing/a/target", false),
+                ("/missing/c/target", true),
+                ("/missing/b/target", true)
+            ]
+        );
+        assert_eq!(app.scan_results.selected_size_bytes, 60);
+    }
+
+    #[test]
+    fn glob_targets_are_found_by_the_scan() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["a/pkg.egg-info", "b/other.egg-info", "c/egg-info-notes"] {
+            std::fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.scan_config.target_globs = vec!["*.egg-info".to_string()];
+        app.run_scan_blocking();
+
+        let mut found: Vec<(String, String)> = app
+            .dirs_to_clean
+            .iter()
+            .map(|d| {
+                let name = d.path.file_name().unwrap().to_string_lossy().to_string();
+                (name, d.matched_name.clone())
+            })
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                ("other.egg-info".to_string(), "*.egg-info".to_string()),
+                ("pkg.egg-info".to_string(), "*.egg-info".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn eta_waits_for_enough_data() {
+        let secs = Duration::from_secs;
+        assert_eq!(estimate_remaining(secs(1), 5, 10), None);
+        assert_eq!(estimate_remaining(secs(10), 1, 10), None);
+        assert_eq!(estimate_remaining(secs(10), 10, 10), None);
+        assert_eq!(estimate_remaining(secs(10), 2, 10), Some(secs(40)));
+        assert_eq!(estimate_remaining(secs(6), 3, 4), Some(secs(2)));
+    }
+
+    #[test]
+    fn top_level_directories_drive_progress() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["a", "b", "c"] {
+            std::fs::create_dir_all(root.path().join(dir).join("nested")).unwrap();
+        }
+        let mut app = App::new();
+        app.scan_config.root = root.path().to_path_buf();
+        app.start_scan();
+        app.scan_stop_signal.store(true, Ordering::SeqCst);
+        assert_eq!(app.scan_progress.as_ref().unwrap().top_level_total, 3);
+
+        app.handle_scan_update(ScanUpdate::Path(root.path().join("a")));
+        app.handle_scan_update(ScanUpdate::Path(root.path().join("a/nested")));
+        app.handle_scan_update(ScanUpdate::Path(root.path().join("b")));
+        assert_eq!(app.scan_progress.as_ref().unwrap().top_level_seen, 2);
+    }
+
+    #[test]
+    fn hidden_directories_are_walked_only_with_include_hidden() {
+        let root = tempfile::tempdir().unwrap();
+        let hidden = root.path().join(".cache/tool/node_modules");
+        std::fs::create_dir_all(&hidden).unwrap();
+
+        let scan = |args: &[&str]| {
+            let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            args.push(root.path().display().to_string());
+            let mut app = App::from_cli(&parse_args(args).unwrap());
+            app.session_file = None;
+            app.run_scan_blocking();
+            app.dirs_to_clean
+                .iter()
+                .map(|d| d.path.clone())
+                .collect::<Vec<_>>()
+        };
+        assert!(scan(&[]).is_empty());
+        assert_eq!(scan(&["--include-hidden"]), vec![hidden.clone()]);
+    }
+
+    #[test]
+    fn small_selection_uses_simple_confirm() {
+        let mut app = App::new();
+        app.confirm_threshold = 2;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 1, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 40, 1, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        assert!(app.state == AppState::ConfirmPreview);
+        assert!(app.typed_confirmation.is_none());
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('n')));
+        assert!(app.state == AppState::ScanComplete);
+    }
+
+    #[test]
+    fn large_selection_requires_typing_the_count() {
+        let mut app = App::new();
+        app.confirm_threshold = 2;
+        for name in ["a", "b", "c"] {
+            let path = format!("/missing/{}/target", name);
+            app.handle_scan_update(ScanUpdate::Result(dir(&path, 40, 1, true)));
+        }
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        assert!(app.typed_confirmation.is_some());
+
+        // A single `y` is not enough
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('y')));
+        assert!(app.state == AppState::ConfirmPreview);
+
+        // The wrong number clears the input
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('2')));
+        app.handle_key_event(KeyEvent::from(KeyCode::Enter));
+        assert!(app.state == AppState::ConfirmPreview);
+        assert_eq!(app.typed_confirmation.as_ref().unwrap().input, "");
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('3')));
+        app.handle_key_event(KeyEvent::from(KeyCode::Enter));
+        assert!(app.state == AppState::Deleting);
+        assert!(app.typed_confirmation.is_none());
+    }
+
+    #[test]
+    fn preview_scrolls_within_selection_and_cancels() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 1, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 40, 1, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 1, 1, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Enter));
+        assert!(app.state == AppState::ConfirmPreview);
+        for _ in 0..5 {
+            app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        }
+        assert_eq!(app.preview_scroll, 1);
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(app.state == AppState::ScanComplete);
+        assert!(!app.should_exit);
+    }
+
+    #[test]
+    fn refreshed_sizes_are_reported_in_the_summary() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("app/target");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("a"), vec![0; 10]).unwrap();
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.run_scan_blocking();
+        app.dirs_to_clean[0].selected = true;
+        let scanned = app.dirs_to_clean[0].size_bytes;
+
+        // A build runs between the scan and the cleanup
+        std::fs::write(target.join("b"), vec![0; 5000]).unwrap();
+        app.refresh_sizes_before_delete = true;
+        app.delete_mode = DeleteMode::Permanent;
+        app.start_deletion();
+        assert!(app.dirs_to_clean[0].size_bytes > scanned);
+
+        let expected = app.dirs_to_clean[0].size_bytes;
+        while app.state != AppState::DeletionComplete {
+            app.process_deletion_updates();
+            thread::sleep(Duration::from_millis(5));
+        }
+        let report = app.deletion_report.as_ref().unwrap();
+        assert_eq!(report.succeeded, vec![target.clone()]);
+        assert_eq!(report.bytes_freed, expected);
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn deletion_updates_drive_progress_and_summary() {
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.deletion_receiver = Some(rx);
+        app.state = AppState::Deleting;
+
+        tx.send(DeletionUpdate::Progress(1, 2)).unwrap();
+        app.process_deletion_updates();
+        assert_eq!(app.deletion_progress, (1, 2));
+        assert!(app.state == AppState::Deleting);
+
+        tx.send(DeletionUpdate::Progress(2, 2)).unwrap();
+        tx.send(DeletionUpdate::Done(DeletionReport {
+            succeeded: vec![PathBuf::from("a/target"), PathBuf::from("b/target")],
+            failed: Vec::new(),
+            already_gone: Vec::new(),
+            bytes_freed: 4096,
+        }))
+        .unwrap();
+        app.process_deletion_updates();
+        assert!(app.state == AppState::DeletionComplete);
+        assert_eq!(app.deletion_report.as_ref().unwrap().bytes_freed, 4096);
+        assert!(app.deletion_receiver.is_none());
+    }
+
+    fn trashed_app() -> App {
+        let mut app = App::new();
+        app.state = AppState::DeletionComplete;
+        app.deletion_report = Some(DeletionReport {
+            succeeded: vec![PathBuf::from("a/target")],
+            failed: Vec::new(),
+            already_gone: Vec::new(),
+            bytes_freed: 4096,
+        });
+        app
+    }
+
+    #[test]
+    fn trash_reminder_shows_what_earlier_runs_left_behind() {
+        let mut app = App::new();
+        assert_eq!(app.trash_reminder(), None);
+
+        let (tx, rx) = mpsc::channel();
+        app.trash_size_receiver = Some(rx);
+        app.poll_trash_size();
+        assert_eq!(app.trash_size, None);
+        tx.send(8 * 1024 * 1024 * 1024).unwrap();
+        app.poll_trash_size();
+        assert!(app.trash_size_receiver.is_none());
+        assert_eq!(
+            app.trash_reminder().as_deref(),
+            Some("Trash holds ~8.0 GiB from prior cleanups")
+        );
+
+        // Nothing to remind about once the trash is empty
+        app.empty_trash_with(|| Ok(()));
+        assert_eq!(app.trash_reminder(), None);
+        app.trash_size = Some(0);
+        assert_eq!(app.trash_reminder(), None);
+    }
+
+    #[cfg(any(
+        target_os = "windows",
+        all(
+            unix,
+            not(target_os = "macos"),
+            not(target_os = "ios"),
+            not(target_os = "android")
+        )
+    ))]
+    #[test]
+    fn emptying_the_trash_needs_a_key_and_a_yes() {
+        let mut app = trashed_app();
+        assert!(app.can_empty_trash());
+
+        // Declining leaves the trash alone
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('e')));
+        assert!(app.pending_empty_trash);
+        assert_eq!(
+            app.confirm_action.as_deref(),
+            Some("Empty the trash to reclaim 4 KiB now? Everything in it is deleted for good")
+        );
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('n')));
+        assert!(!app.pending_empty_trash);
+        assert!(app.empty_trash_result.is_none());
+        assert!(!app.should_exit);
+
+        // Confirming runs the empty operation once
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('e')));
+        let mut calls = 0;
+        app.empty_trash_with(|| {
+            calls += 1;
+            Err("trash is busy".to_string())
+        });
+        assert_eq!(calls, 1);
+        assert!(!app.pending_empty_trash);
+        assert!(app.confirm_action.is_none());
+        assert_eq!(
+            app.empty_trash_result,
+            Some(Err("trash is busy".to_string()))
+        );
+        assert!(!app.can_empty_trash());
+
+        // Nothing went to the trash in a permanent cleanup
+        let mut permanent = trashed_app();
+        permanent.delete_mode = DeleteMode::Permanent;
+        permanent.handle_key_event(KeyEvent::from(KeyCode::Char('e')));
+        assert!(!permanent.pending_empty_trash);
+    }
+
+    #[cfg(not(any(
+        target_os = "windows",
+        all(
+            unix,
+            not(target_os = "macos"),
+            not(target_os = "ios"),
+            not(target_os = "android")
+        )
+    )))]
+    #[test]
+    fn emptying_the_trash_is_not_offered_where_unsupported() {
+        let mut app = trashed_app();
+        assert!(!app.can_empty_trash());
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('e')));
+        assert!(!app.pending_empty_trash);
+    }
+
+    #[test]
+    fn detects_unicode_from_locale() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(terminal_supports_unicode(env(&[("LANG", "en_US.UTF-8")])));
+        assert!(!terminal_supports_unicode(env(&[("LANG", "C")])));
+        assert!(!terminal_supports_unicode(env(&[
+            ("TERM", "linux"),
+            ("LANG", "en_US.UTF-8")
+        ])));
+    }
+
+    #[test]
+    fn detects_color_support() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(colors_supported(env(&[("TERM", "xterm-256color")])));
+        assert!(colors_supported(env(&[("NO_COLOR", "")])));
+        assert!(!colors_supported(env(&[("NO_COLOR", "1")])));
+        assert!(!colors_supported(env(&[("TERM", "dumb")])));
+    }
+
+    #[test]
+    fn confirmation_wording_follows_the_delete_mode() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 1024, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 40, 1024, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+        assert_eq!(
+            app.preview_question(),
+            "Move 2 selected items (2 KiB) to trash?"
+        );
+
+        // Build tools delete what they clean for good, whatever the mode
+        app.clean_tools = tooling::default_commands();
+        assert_eq!(
+            app.preview_question(),
+            "Move 2 selected items (2 KiB) to trash, except projects their build tool cleans, \
+             which can't be restored from the trash?"
+        );
+        app.clean_tools.clear();
+
+        app.delete_mode = DeleteMode::Permanent;
+        assert_eq!(
+            app.preview_question(),
+            "PERMANENTLY DELETE 2 selected items (2 KiB)? This cannot be undone"
+        );
+        app.dir_list_state.select(Some(0));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('x')));
+        assert_eq!(
+            app.confirm_action.as_deref(),
+            Some("PERMANENTLY DELETE /missing/a/target")
+        );
+    }
+
+    #[test]
+    fn space_toggles_every_row_in_a_visual_range() {
+        let mut app = App::new();
+        app.sort_order = SortOrder {
+            column: SortColumn::Path,
+            descending: false,
+        };
+        for (i, name) in ["a", "b", "c", "d", "e", "f", "g"].iter().enumerate() {
+            let selected = i % 2 == 0;
+            let path = format!("/missing/{}/target", name);
+            app.handle_scan_update(ScanUpdate::Result(dir(&path, 40, 100, selected)));
+        }
+        app.handle_scan_update(ScanUpdate::Done);
+        let before: Vec<bool> = app.dirs_to_clean.iter().map(|d| d.selected).collect();
+
+        // Rows 1 to 5, marked from the bottom up
+        app.dir_list_state.select(Some(5));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('V')));
+        for _ in 0..4 {
+            app.handle_key_event(KeyEvent::from(KeyCode::Up));
+        }
+        assert_eq!(app.visual_range(), Some(1..=5));
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        for (i, dir) in app.dirs_to_clean.iter().enumerate() {
+            assert_eq!(dir.selected, before[i] != (1..=5).contains(&i), "row {}", i);
+        }
+        assert_eq!(app.visual_anchor, None);
+        assert_eq!(app.scan_results.selected_folders_count, 5);
+
+        // Esc cancels a range instead of quitting, and Space is back to one row
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('V')));
+        app.handle_key_event(KeyEvent::from(KeyCode::Down));
+        app.handle_key_event(KeyEvent::from(KeyCode::Esc));
+        assert!(!app.should_exit);
+        assert_eq!(app.visual_range(), None);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char(' ')));
+        assert_eq!(app.dirs_to_clean[2].selected, before[2]);
+    }
+
+    #[test]
+    fn directories_in_use_are_flagged_and_optionally_unselected() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 100, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 40, 100, true)));
+        let building = || vec![PathBuf::from("/missing/a/target/debug/.cargo-lock")];
+
+        app.mark_in_use(building);
+        assert_eq!(
+            app.in_use,
+            HashSet::from([PathBuf::from("/missing/a/target")])
+        );
+        assert_eq!(app.scan_results.selected_folders_count, 2);
+
+        app.skip_in_use = true;
+        app.mark_in_use(building);
+        let selected: Vec<bool> = app.dirs_to_clean.iter().map(|d| d.selected).collect();
+        let in_use_at = app
+            .dirs_to_clean
+            .iter()
+            .position(|d| d.path == Path::new("/missing/a/target"))
+            .unwrap();
+        assert!(!selected[in_use_at]);
+        assert!(selected[1 - in_use_at]);
+        assert_eq!(app.scan_results.selected_folders_count, 1);
+    }
+
+    #[test]
+    fn cleanups_are_blocked_without_a_trash() {
+        let mut app = App::new();
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 100, true)));
+        app.handle_scan_update(ScanUpdate::Done);
+        app.trash_unavailable = Some("no trash directory".to_string());
+        app.trash_checked = true;
+
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        assert!(app.state == AppState::ScanComplete);
+        let toast = app.current_toast(Instant::now()).unwrap();
+        assert!(toast.contains("no trash directory"));
+        assert!(toast.contains("--permanent"));
+
+        // Removing folders some other way needs no trash
+        app.delete_mode = DeleteMode::Permanent;
+        app.check_trash_support();
+        assert_eq!(app.trash_unavailable, None);
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('c')));
+        assert!(app.state == AppState::ConfirmPreview);
+    }
+
+    #[test]
+    fn rescanning_a_subtree_keeps_matches_outside_it() {
+        let root = tempfile::tempdir().unwrap();
+        for project in ["a/one", "a/two", "b"] {
+            std::fs::create_dir_all(root.path().join(project).join("target")).unwrap();
+        }
+        let mut app = App::new();
+        app.session_file = None;
+        app.history_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.run_scan_blocking();
+        assert_eq!(app.dirs_to_clean.len(), 3);
+        for dir in &mut app.dirs_to_clean {
+            dir.selected = true;
+        }
+        // Outside the subtree: a match the rescan must not touch, even though it's gone
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/c/target", 40, 100, false)));
+
+        // Part of `a` was cleaned up, and a new build appeared next to it
+        std::fs::remove_dir_all(root.path().join("a/one/target")).unwrap();
+        std::fs::create_dir_all(root.path().join("a/three/target")).unwrap();
+        app.rescan_subtree(root.path().join("a"));
+
+        let mut listed: Vec<(PathBuf, bool)> = app
+            .dirs_to_clean
+            .iter()
+            .map(|d| (d.path.clone(), d.selected))
+            .collect();
+        listed.sort();
+        assert_eq!(
+            listed,
+            [
+                (PathBuf::from("/missing/c/target"), false),
+                (root.path().join("a/three/target"), false),
+                (root.path().join("a/two/target"), true),
+                (root.path().join("b/target"), true),
+            ]
+        );
+        assert_eq!(app.scan_results.found_folders, 4);
+        assert_eq!(app.scan_results.selected_folders_count, 2);
+        assert!(app.rescan_selection.is_none());
+    }
+
+    #[test]
+    fn relative_paths_strip_the_scan_root() {
+        let root = Path::new("/home/me/projects");
+        let relative = PathDisplay::Relative;
+        assert_eq!(
+            relative.render(Path::new("/home/me/projects/web/node_modules"), root),
+            "web/node_modules"
+        );
+        assert_eq!(relative.render(root, root), ".");
+        // A sibling that merely shares the prefix as a string isn't under the root
+        assert_eq!(
+            relative.render(Path::new("/home/me/projects-old/target"), root),
+            "/home/me/projects-old/target"
+        );
+        assert_eq!(
+            relative.render(Path::new("/tmp/target"), root),
+            "/tmp/target"
+        );
+        assert_eq!(
+            PathDisplay::Absolute.render(Path::new("/home/me/projects/target"), root),
+            "/home/me/projects/target"
+        );
+    }
+
+    #[test]
+    fn keep_recent_keys_change_which_rows_are_selected() {
+        let mut app = App::new();
+        app.session_file = None;
+        for (path, days_ago, size) in [
+            ("/missing/a/target", 1, 10),
+            ("/missing/b/target", 90, 20),
+            ("/missing/c/target", 5, 40),
+        ] {
+            app.handle_scan_update(ScanUpdate::Result(dir(path, days_ago, size, false)));
+        }
+        // Selected projects, by name
+        let selected = |app: &App| -> Vec<String> {
+            let mut selected: Vec<String> = app
+                .dirs_to_clean
+                .iter()
+                .filter(|d| d.selected)
+                .map(|d| d.path.parent().unwrap().display().to_string())
+                .collect();
+            selected.sort();
+            selected
+        };
+
+        // Keeping fewer than nothing leaves the selection alone
+        app.adjust_keep_recent(false);
+        assert_eq!(app.keep_recent, None);
+        assert!(selected(&app).is_empty());
+
+        app.adjust_keep_recent(true);
+        assert_eq!(app.keep_recent, Some(1));
+        assert_eq!(selected(&app), ["/missing/b", "/missing/c"]);
+        assert_eq!(app.scan_results.selected_size_bytes, 60);
+
+        app.adjust_keep_recent(true);
+        assert_eq!(selected(&app), ["/missing/b"]);
+        assert_eq!(app.scan_results.selected_size_bytes, 20);
+
+        app.adjust_keep_recent(false);
+        app.adjust_keep_recent(false);
+        assert_eq!(app.keep_recent, Some(0));
+        assert_eq!(selected(&app).len(), 3);
+    }
+
+    #[test]
+    fn ignored_paths_are_saved_and_skipped_by_later_scans() {
+        let root = tempfile::tempdir().unwrap();
+        let config = tempfile::tempdir().unwrap();
+        let file = config.path().join("ignored.txt");
+        for project in ["a", "b"] {
+            std::fs::create_dir_all(root.path().join(project).join("target")).unwrap();
+        }
+        let fresh_app = || {
+            let mut app = App::new();
+            app.session_file = None;
+            app.history_file = None;
+            app.ignored_file = Some(file.clone());
+            app.scan_config.root = root.path().to_path_buf();
+            app
+        };
+
+        let mut app = fresh_app();
+        app.run_scan_blocking();
+        assert_eq!(app.dirs_to_clean.len(), 2);
+        app.dir_list_state.select(Some(0));
+        let ignored = app.dirs_to_clean[0].path.clone();
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('I')));
+        assert_eq!(app.dirs_to_clean.len(), 1);
+        assert!(app.toast.as_ref().unwrap().0.starts_with("Ignoring"));
+        assert_eq!(ignored::load(&file), std::slice::from_ref(&ignored));
+
+        // A later session reads the list back and never reports the path
+        let mut app = fresh_app();
+        app.load_ignored();
+        app.run_scan_blocking();
+        assert_eq!(app.dirs_to_clean.len(), 1);
+        assert_ne!(app.dirs_to_clean[0].path, ignored);
+    }
+
+    #[test]
+    fn read_only_sessions_do_not_save_ignored_paths() {
+        let config = tempfile::tempdir().unwrap();
+        let file = config.path().join("ignored.txt");
+        let mut app = App::new();
+        app.session_file = None;
+        app.history_file = None;
+        app.ignored_file = Some(file.clone());
+        app.read_only = true;
+        app.state = AppState::ScanComplete;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 100, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+        app.dir_list_state.select(Some(0));
+
+        // The match is still hidden for this session, but nothing is written
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('I')));
+        assert!(app.dirs_to_clean.is_empty());
+        assert!(app.toast.as_ref().unwrap().0.contains("read-only"));
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn invert_only_flips_the_visible_rows() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.state = AppState::ScanComplete;
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/a/target", 40, 100, true)));
+        app.handle_scan_update(ScanUpdate::Result(dir("/missing/b/target", 40, 100, false)));
+        app.handle_scan_update(ScanUpdate::Done);
+
+        // Only the selected match is shown, so only it is inverted
+        app.hide_unselected = true;
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('i')));
+        assert!(app.dirs_to_clean.iter().all(|d| !d.selected));
+        assert_eq!(app.scan_results.selected_folders_count, 0);
+
+        // With everything shown, both flip
+        app.hide_unselected = false;
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('i')));
+        assert!(app.dirs_to_clean.iter().all(|d| d.selected));
+    }
+
+    #[test]
+    fn parents_of_failed_deletions_are_rescanned() {
+        let root = tempfile::tempdir().unwrap();
+        for path in ["a/target", "a/node_modules", "b/target"] {
+            std::fs::create_dir_all(root.path().join(path)).unwrap();
+        }
+        let mut app = App::new();
+        app.session_file = None;
+        app.history_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        app.run_scan_blocking();
+        for dir in &mut app.dirs_to_clean {
+            dir.selected = true;
+        }
+
+        // `a/node_modules` went, `a/target` couldn't be removed
+        std::fs::remove_dir_all(root.path().join("a/node_modules")).unwrap();
+        let (tx, rx) = mpsc::channel();
+        app.deletion_receiver = Some(rx);
+        app.state = AppState::Deleting;
+        tx.send(DeletionUpdate::Done(DeletionReport {
+            succeeded: vec![root.path().join("a/node_modules")],
+            failed: vec![(root.path().join("a/target"), "busy".to_string())],
+            already_gone: Vec::new(),
+            bytes_freed: 0,
+        }))
+        .unwrap();
+        app.process_deletion_updates();
+        assert!(app.subtree_receiver.is_some());
+        let started = Instant::now();
+        while app.subtree_receiver.is_some() && started.elapsed() < Duration::from_secs(10) {
+            app.poll_subtree_rescans();
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(app.subtree_receiver.is_none());
+
+        let mut listed: Vec<(PathBuf, bool)> = app
+            .dirs_to_clean
+            .iter()
+            .map(|d| (d.path.clone(), d.selected))
+            .collect();
+        listed.sort();
+        assert_eq!(
+            listed,
+            [
+                (root.path().join("a/target"), true),
+                (root.path().join("b/target"), true),
+            ]
+        );
+        assert!(app.state == AppState::DeletionComplete);
+
+        assert_eq!(
+            failed_parents(
+                &[
+                    PathBuf::from("/r/a/target"),
+                    PathBuf::from("/r/a/x/target"),
+                    PathBuf::from("/elsewhere/target"),
+                ],
+                Path::new("/r")
+            ),
+            [PathBuf::from("/r/a")]
+        );
+    }
 }
@@ -0,0 +1,145 @@
+use crate::scanner;
+use ratatui::widgets::ListState;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A simple directory browser for picking a new scan root
+#[derive(Debug, Clone)]
+pub struct DirBrowser {
+    pub path: PathBuf,
+    // Display label and destination; the first entry is `..` unless at the filesystem root
+    pub entries: Vec<(String, PathBuf)>,
+    pub list_state: ListState,
+    // Why the last directory couldn't be listed, e.g. a permission error
+    pub error: Option<String>,
+}
+
+impl DirBrowser {
+    pub fn open(path: &Path) -> Self {
+        let mut browser = DirBrowser {
+            path: scanner::absolute_path(path),
+            entries: Vec::new(),
+            list_state: ListState::default(),
+            error: None,
+        };
+        let path = browser.path.clone();
+        if let Err(err) = browser.load(&path) {
+            browser.error = Some(format!("Cannot read {}: {}", path.display(), err));
+        }
+        browser
+    }
+
+    // List the subdirectories of `path`, keeping the current listing if that fails
+    fn load(&mut self, path: &Path) -> io::Result<()> {
+        let mut subdirs: Vec<PathBuf> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        subdirs.sort_by_key(|p| p.file_name().unwrap_or_default().to_ascii_lowercase());
+
+        let mut entries = Vec::new();
+        if let Some(parent) = path.parent() {
+            entries.push(("..".to_string(), parent.to_path_buf()));
+        }
+        entries.extend(subdirs.into_iter().map(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            (format!("{}/", name), p)
+        }));
+
+        self.path = path.to_path_buf();
+        self.entries = entries;
+        self.list_state.select(Some(0));
+        self.error = None;
+        Ok(())
+    }
+
+    pub fn move_up(&mut self) {
+        let current = self.list_state.selected().unwrap_or(0);
+        self.list_state.select(Some(current.saturating_sub(1)));
+    }
+
+    pub fn move_down(&mut self) {
+        let current = self.list_state.selected().unwrap_or(0);
+        if current + 1 < self.entries.len() {
+            self.list_state.select(Some(current + 1));
+        }
+    }
+
+    // Open the highlighted entry
+    pub fn enter(&mut self) {
+        let Some((_, target)) = self
+            .list_state
+            .selected()
+            .and_then(|i| self.entries.get(i))
+            .cloned()
+        else {
+            return;
+        };
+        if let Err(err) = self.load(&target) {
+            self.error = Some(format!("Cannot read {}: {}", target.display(), err));
+        }
+    }
+
+    // Go to the parent directory
+    pub fn leave(&mut self) {
+        let Some(parent) = self.path.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        if let Err(err) = self.load(&parent) {
+            self.error = Some(format!("Cannot read {}: {}", parent.display(), err));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(browser: &DirBrowser) -> Vec<&str> {
+        browser.entries.iter().map(|(l, _)| l.as_str()).collect()
+    }
+
+    #[test]
+    fn lists_parent_then_sorted_subdirectories() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("beta")).unwrap();
+        fs::create_dir(root.path().join("Alpha")).unwrap();
+        fs::write(root.path().join("file.txt"), b"x").unwrap();
+
+        let browser = DirBrowser::open(root.path());
+        assert_eq!(labels(&browser), vec!["..", "Alpha/", "beta/"]);
+        assert!(browser.error.is_none());
+    }
+
+    #[test]
+    fn enters_and_leaves_directories() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("projects/app")).unwrap();
+
+        let mut browser = DirBrowser::open(root.path());
+        browser.move_down();
+        browser.enter();
+        assert_eq!(browser.path, root.path().join("projects"));
+        assert_eq!(labels(&browser), vec!["..", "app/"]);
+
+        browser.leave();
+        assert_eq!(browser.path, root.path());
+    }
+
+    #[test]
+    fn unreadable_directory_keeps_listing_and_reports_error() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("gone")).unwrap();
+
+        let mut browser = DirBrowser::open(root.path());
+        fs::remove_dir(root.path().join("gone")).unwrap();
+        browser.move_down();
+        browser.enter();
+
+        assert_eq!(browser.path, root.path());
+        assert_eq!(labels(&browser), vec!["..", "gone/"]);
+        assert!(browser.error.as_ref().unwrap().starts_with("Cannot read"));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::app::DirInfo;
+use crate::paths;
+use crate::scanner;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// How often a running scan saves its progress
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+// How far an interrupted scan of `root` got, for `--resume`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub root: PathBuf,
+    // Directories walked to the end; nothing under them is listed again
+    pub done: BTreeSet<PathBuf>,
+    // Matches reported before the scan stopped, with their sizes
+    pub matches: Vec<DirInfo>,
+}
+
+// Location of the checkpoint for `root`, in the platform's cache directory. The name is
+// a hash of the root, which may change between builds; that only loses a resume.
+pub fn default_path(root: &Path) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    scanner::absolute_path(root).hash(&mut hasher);
+    Some(
+        paths::cache_dir()?
+            .join("scans")
+            .join(format!("{:016x}.json", hasher.finish())),
+    )
+}
+
+// The checkpoint saved for `root`, if the file holds one
+pub fn load(file: &Path, root: &Path) -> Option<Checkpoint> {
+    let contents = fs::read_to_string(file).ok()?;
+    let checkpoint: Checkpoint = serde_json::from_str(&contents).ok()?;
+    (checkpoint.root == scanner::absolute_path(root)).then_some(checkpoint)
+}
+
+pub fn save(file: &Path, checkpoint: &Checkpoint) -> io::Result<()> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string(checkpoint).map_err(io::Error::other)?;
+    // Written aside and renamed, so a crash mid-save leaves the previous checkpoint
+    let partial = file.with_extension("json.partial");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, file)
+}
+
+// Forget the checkpoint once its scan has run to the end
+pub fn clear(file: &Path) {
+    let _ = fs::remove_file(file);
+}
+
+// A directory the walk is still inside, and its children it has finished
+#[derive(Debug)]
+struct OpenDir {
+    path: PathBuf,
+    done_children: Vec<PathBuf>,
+    // False once anything under it has to be walked again
+    complete: bool,
+}
+
+// Which directories a depth-first walk has finished. Only the outermost finished
+// directories are kept, so this stays about as small as the current path's siblings.
+#[derive(Debug, Default)]
+pub struct Frontier {
+    open: Vec<OpenDir>,
+    done: BTreeSet<PathBuf>,
+}
+
+impl Frontier {
+    pub fn new(done: BTreeSet<PathBuf>) -> Self {
+        Frontier {
+            open: Vec::new(),
+            done,
+        }
+    }
+
+    // The walk reached `path`: every open directory that doesn't contain it is finished
+    pub fn visit(&mut self, path: &Path, is_dir: bool) {
+        while self
+            .open
+            .last()
+            .is_some_and(|dir| !path.starts_with(&dir.path))
+        {
+            self.close();
+        }
+        if is_dir {
+            self.open.push(OpenDir {
+                path: path.to_path_buf(),
+                done_children: Vec::new(),
+                complete: true,
+            });
+        }
+    }
+
+    // `path` will be walked again later, so neither it nor its parents are finished
+    pub fn abandon(&mut self, path: &Path) {
+        for dir in self.open.iter_mut().rev() {
+            if path.starts_with(&dir.path) {
+                dir.complete = false;
+            }
+        }
+    }
+
+    // One walk ran out of entries: whatever is still open is finished
+    pub fn finish(&mut self) {
+        while !self.open.is_empty() {
+            self.close();
+        }
+    }
+
+    fn close(&mut self) {
+        let dir = self.open.pop().expect("an open directory");
+        let finished = if dir.complete {
+            vec![dir.path]
+        } else {
+            dir.done_children
+        };
+        match self.open.last_mut() {
+            Some(parent) => {
+                parent.complete &= dir.complete;
+                parent.done_children.extend(finished);
+            }
+            None => {
+                for path in finished {
+                    // Subsumes anything finished under it by an earlier walk
+                    self.done.retain(|done| !done.starts_with(&path));
+                    self.done.insert(path);
+                }
+            }
+        }
+    }
+
+    // Every finished directory, including those inside the ones still open
+    pub fn done(&self) -> BTreeSet<PathBuf> {
+        let mut done = self.done.clone();
+        for dir in &self.open {
+            done.extend(dir.done_children.iter().cloned());
+        }
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontier_keeps_the_outermost_finished_directories() {
+        let mut frontier = Frontier::default();
+        for (path, is_dir) in [
+            ("/r", true),
+            ("/r/a", true),
+            ("/r/a/x", true),
+            ("/r/a/file", false),
+            ("/r/b", true),
+            ("/r/b/x", true),
+            ("/r/c", true),
+            ("/r/c/x", true),
+        ] {
+            frontier.visit(Path::new(path), is_dir);
+        }
+        // `/r/c` and `/r/c/x` are still open
+        let done: Vec<PathBuf> = frontier.done().into_iter().collect();
+        assert_eq!(done, [PathBuf::from("/r/a"), PathBuf::from("/r/b")]);
+
+        // A directory that needs another walk keeps its parents unfinished
+        frontier.abandon(Path::new("/r/c/x"));
+        frontier.finish();
+        let done: Vec<PathBuf> = frontier.done().into_iter().collect();
+        assert_eq!(done, [PathBuf::from("/r/a"), PathBuf::from("/r/b")]);
+
+        // Walking it again finishes it on its own
+        frontier.visit(Path::new("/r/c/x"), true);
+        frontier.finish();
+        assert!(frontier.done().contains(Path::new("/r/c/x")));
+    }
+
+    #[test]
+    fn checkpoint_round_trips_for_its_root_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("scans/root.json");
+        let checkpoint = Checkpoint {
+            root: scanner::absolute_path(dir.path()),
+            done: BTreeSet::from([dir.path().join("a")]),
+            matches: Vec::new(),
+        };
+        save(&file, &checkpoint).unwrap();
+        let loaded = load(&file, dir.path()).unwrap();
+        assert_eq!(loaded.done, checkpoint.done);
+        assert!(load(&file, &dir.path().join("a")).is_none());
+
+        clear(&file);
+        assert!(load(&file, dir.path()).is_none());
+    }
+}
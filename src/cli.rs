@@ -0,0 +1,728 @@
+use crate::app::{SortColumn, SortOrder};
+use crate::headless::ListFormat;
+use crate::keymap::KeyBindings;
+use crate::owners;
+use crate::tooling::CleanCommand;
+use chrono::{Local, NaiveDate};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// Options parsed from the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cli {
+    pub directory: Option<PathBuf>,
+    pub ascii: bool,
+    pub max_threads: Option<usize>,
+    pub count_links: bool,
+    pub disk_usage: bool,
+    pub apparent_size: bool,
+    pub exclude: Vec<PathBuf>,
+    pub protect: Vec<PathBuf>,
+    pub min_size: Option<u64>,
+    pub min_files: Option<u64>,
+    pub hide_empty: bool,
+    pub min_age: Option<u32>,
+    pub min_depth: Option<usize>,
+    pub max_depth: Option<usize>,
+    // Uid from `--owner-only`, given as a user name or number
+    pub owner_only: Option<u32>,
+    pub before: Option<SystemTime>,
+    pub explain: Option<PathBuf>,
+    pub report: Option<PathBuf>,
+    pub emit_script: Option<PathBuf>,
+    pub plan: Option<PathBuf>,
+    pub apply: Option<PathBuf>,
+    pub match_full_path: bool,
+    pub confirm_threshold: Option<usize>,
+    pub confirm_timeout: Option<u64>,
+    pub targets: Vec<String>,
+    pub target_globs: Vec<String>,
+    pub ignore: Vec<String>,
+    pub include_hidden: bool,
+    pub targets_from: Option<PathBuf>,
+    pub ignore_from: Option<PathBuf>,
+    pub top: Option<usize>,
+    pub si: bool,
+    pub local_only: bool,
+    pub headless: bool,
+    pub delete: bool,
+    pub no_auto_select: bool,
+    pub keep_recent: Option<usize>,
+    pub select_older_than: Option<u32>,
+    pub permanent: bool,
+    pub archive: Option<PathBuf>,
+    pub watch: bool,
+    pub trash_fallback_permanent: bool,
+    pub limit: Option<usize>,
+    pub refresh_sizes_before_delete: bool,
+    // Keep a trash failure from deleting for good, even when the profile asks for it
+    pub no_trash_fallback_permanent: bool,
+    pub config: Option<PathBuf>,
+    pub profile_name: Option<String>,
+    pub read_only: bool,
+    pub require_targets: bool,
+    pub timestamps: bool,
+    pub use_tooling: bool,
+    pub size_in_background: bool,
+    pub resume: bool,
+    pub require_clean_git: bool,
+    pub since_last_run: bool,
+    pub file_counts: bool,
+    pub show_owners: bool,
+    pub no_follow_root_symlink: bool,
+    pub skip_in_use: bool,
+    pub sort: Option<SortColumn>,
+    pub reverse: bool,
+    pub format: ListFormat,
+    // `--group-by parent`: list totals per parent directory instead of each match
+    pub group_by_parent: bool,
+    // From the `[keys]` table of the config file
+    pub key_bindings: KeyBindings,
+    // From the `[[tooling]]` tables of the config file
+    pub tooling: Vec<CleanCommand>,
+    // Directories given after the first, scanned along with it
+    pub more_directories: Vec<PathBuf>,
+    // Scan up to this many of the directories at once instead of one after another
+    pub roots_parallel: Option<usize>,
+}
+
+impl Cli {
+    // `--sort` and `--reverse` applied to `default`, or `None` when neither was given
+    pub fn sort_order(&self, default: SortOrder) -> Option<SortOrder> {
+        if self.sort.is_none() && !self.reverse {
+            return None;
+        }
+        let order = self.sort.map_or(default, SortOrder::starting);
+        Some(if self.reverse {
+            order.reversed()
+        } else {
+            order
+        })
+    }
+}
+
+// Parse command-line arguments (excluding the program name)
+pub fn parse_args<I>(args: I) -> Result<Cli, String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut cli = Cli::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ascii" => cli.ascii = true,
+            "--count-links" => cli.count_links = true,
+            "--disk-usage" => cli.disk_usage = true,
+            "--apparent-size" => cli.apparent_size = true,
+            "--si" => cli.si = true,
+            "--match-full-path" => cli.match_full_path = true,
+            "--local-only" => cli.local_only = true,
+            "--include-hidden" => cli.include_hidden = true,
+            "--headless" => cli.headless = true,
+            "--no-auto-select" => cli.no_auto_select = true,
+            "--permanent" => cli.permanent = true,
+            "--archive" => cli.archive = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--delete" => cli.delete = true,
+            "--read-only" => cli.read_only = true,
+            "--require-targets" => cli.require_targets = true,
+            "--timestamps" => cli.timestamps = true,
+            "--use-tooling" => cli.use_tooling = true,
+            "--size-in-background" => cli.size_in_background = true,
+            "--resume" => cli.resume = true,
+            "--require-clean-git" => cli.require_clean_git = true,
+            "--since-last-run" => cli.since_last_run = true,
+            "--file-counts" => cli.file_counts = true,
+            "--show-owners" => cli.show_owners = true,
+            "--hide-empty" => cli.hide_empty = true,
+            "--no-follow-root-symlink" => cli.no_follow_root_symlink = true,
+            "--skip-in-use" => cli.skip_in_use = true,
+            "--reverse" => cli.reverse = true,
+            "--group-by" => {
+                let value = next_value(&mut args, &arg)?;
+                if value != "parent" {
+                    return Err(format!(
+                        "Invalid value for --group-by: {} (expected parent)",
+                        value
+                    ));
+                }
+                cli.group_by_parent = true;
+            }
+            "--format" => {
+                let value = next_value(&mut args, &arg)?;
+                cli.format = match value.as_str() {
+                    "list" => ListFormat::Flat,
+                    "tree" => ListFormat::Tree,
+                    _ => {
+                        return Err(format!(
+                            "Invalid value for --format: {} (expected list or tree)",
+                            value
+                        ));
+                    }
+                };
+            }
+            "--sort" => {
+                let value = next_value(&mut args, &arg)?;
+                cli.sort = Some(match value.as_str() {
+                    "age" => SortColumn::Age,
+                    "size" => SortColumn::Size,
+                    "path" => SortColumn::Path,
+                    "name" => SortColumn::Name,
+                    _ => {
+                        return Err(format!(
+                            "Invalid value for --sort: {} (expected age, size, path or name)",
+                            value
+                        ));
+                    }
+                });
+            }
+            "--watch" => cli.watch = true,
+            "--refresh-sizes-before-delete" => cli.refresh_sizes_before_delete = true,
+            "--trash-fallback-permanent" => cli.trash_fallback_permanent = true,
+            "--no-trash-fallback-permanent" => cli.no_trash_fallback_permanent = true,
+            "--max-threads" => {
+                let value = next_value(&mut args, &arg)?;
+                let threads: usize = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --max-threads: {}", value))?;
+                if threads < 1 {
+                    return Err("--max-threads must be at least 1".to_string());
+                }
+                cli.max_threads = Some(threads);
+            }
+            "--target" => cli.targets.push(next_value(&mut args, &arg)?),
+            "--target-glob" => cli.target_globs.push(next_value(&mut args, &arg)?),
+            "--ignore" => cli.ignore.push(next_value(&mut args, &arg)?),
+            "--targets-from" => {
+                cli.targets_from = Some(PathBuf::from(next_value(&mut args, &arg)?));
+            }
+            "--ignore-from" => cli.ignore_from = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--exclude" => cli
+                .exclude
+                .push(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--protect" => cli
+                .protect
+                .push(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--min-size" => {
+                let value = next_value(&mut args, &arg)?;
+                cli.min_size = Some(parse_size(&value)?);
+            }
+            "--min-files" => {
+                let value = next_value(&mut args, &arg)?;
+                let files = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --min-files: {}", value))?;
+                cli.min_files = Some(files);
+            }
+            "--owner-only" => {
+                let value = next_value(&mut args, &arg)?;
+                cli.owner_only = Some(owners::parse_user(&value)?);
+            }
+            "--min-age" => {
+                let value = next_value(&mut args, &arg)?;
+                let days = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --min-age: {}", value))?;
+                cli.min_age = Some(days);
+            }
+            "--report" => cli.report = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--emit-script" => {
+                cli.emit_script = Some(PathBuf::from(next_value(&mut args, &arg)?));
+            }
+            "--plan" => cli.plan = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--apply" => cli.apply = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--before" => {
+                let value = next_value(&mut args, &arg)?;
+                cli.before = Some(parse_date(&value)?);
+            }
+            "--confirm-threshold" => {
+                let value = next_value(&mut args, &arg)?;
+                let threshold = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --confirm-threshold: {}", value))?;
+                cli.confirm_threshold = Some(threshold);
+            }
+            "--confirm-timeout" => {
+                let value = next_value(&mut args, &arg)?;
+                let seconds: u64 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --confirm-timeout: {}", value))?;
+                if seconds < 1 {
+                    return Err("--confirm-timeout must be at least 1".to_string());
+                }
+                cli.confirm_timeout = Some(seconds);
+            }
+            "--top" => {
+                let value = next_value(&mut args, &arg)?;
+                let count = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --top: {}", value))?;
+                cli.top = Some(count);
+            }
+            "--min-depth" | "--max-depth" => {
+                let value = next_value(&mut args, &arg)?;
+                let depth = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for {}: {}", arg, value))?;
+                match arg.as_str() {
+                    "--min-depth" => cli.min_depth = Some(depth),
+                    _ => cli.max_depth = Some(depth),
+                }
+            }
+            "--roots-parallel" => {
+                let value = next_value(&mut args, &arg)?;
+                let roots: usize = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --roots-parallel: {}", value))?;
+                if roots < 1 {
+                    return Err("--roots-parallel must be at least 1".to_string());
+                }
+                cli.roots_parallel = Some(roots);
+            }
+            "--limit" => {
+                let value = next_value(&mut args, &arg)?;
+                let limit: usize = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --limit: {}", value))?;
+                if limit < 1 {
+                    return Err("--limit must be at least 1".to_string());
+                }
+                cli.limit = Some(limit);
+            }
+            "--select-older-than" => {
+                let value = next_value(&mut args, &arg)?;
+                let days = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --select-older-than: {}", value))?;
+                cli.select_older_than = Some(days);
+            }
+            "--keep-recent" => {
+                let value = next_value(&mut args, &arg)?;
+                let count = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for --keep-recent: {}", value))?;
+                cli.keep_recent = Some(count);
+            }
+            "--config" => cli.config = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            "--profile-name" => cli.profile_name = Some(next_value(&mut args, &arg)?),
+            "--explain" => cli.explain = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option: {}", flag)),
+            path => match cli.directory {
+                Some(_) => cli.more_directories.push(PathBuf::from(path)),
+                None => cli.directory = Some(PathBuf::from(path)),
+            },
+        }
+    }
+
+    if cli.disk_usage && cli.apparent_size {
+        return Err("--disk-usage and --apparent-size can't be combined".to_string());
+    }
+    if cli.permanent && cli.archive.is_some() {
+        return Err("--permanent and --archive can't be combined".to_string());
+    }
+    if cli.trash_fallback_permanent && cli.no_trash_fallback_permanent {
+        return Err(
+            "--trash-fallback-permanent and --no-trash-fallback-permanent can't be combined"
+                .to_string(),
+        );
+    }
+    if cli.trash_fallback_permanent && (cli.permanent || cli.archive.is_some()) {
+        return Err("--trash-fallback-permanent only applies when moving to the trash".to_string());
+    }
+    if cli.emit_script.is_some() && cli.delete {
+        return Err(
+            "--emit-script only writes a script; it can't be combined with --delete".to_string(),
+        );
+    }
+    if cli.plan.is_some() && (cli.delete || cli.apply.is_some()) {
+        return Err(
+            "--plan only writes a plan; apply it in a separate run with --apply".to_string(),
+        );
+    }
+    if cli.read_only && cli.apply.is_some() {
+        return Err("--read-only can't be combined with --apply".to_string());
+    }
+    if cli.read_only && cli.delete {
+        return Err("--read-only can't be combined with --delete".to_string());
+    }
+    if cli.delete && cli.top.is_some() {
+        return Err("--top only lists matches; it can't be combined with --delete".to_string());
+    }
+    if cli.delete && cli.explain.is_some() {
+        return Err(
+            "--explain only describes a path; it can't be combined with --delete".to_string(),
+        );
+    }
+    if !cli.more_directories.is_empty() {
+        // Each of these is tied to a single scan root
+        for (flag, given) in [
+            ("--resume", cli.resume),
+            ("--watch", cli.watch),
+            ("--plan", cli.plan.is_some()),
+        ] {
+            if given {
+                return Err(format!("{} only works with a single directory", flag));
+            }
+        }
+    }
+    if cli.delete && !cli.headless {
+        return Err("--delete requires --headless".to_string());
+    }
+    if cli.group_by_parent {
+        if cli.format == ListFormat::Tree {
+            return Err("--group-by parent can't be combined with --format tree".to_string());
+        }
+        cli.format = ListFormat::ByParent;
+    }
+    if let (Some(min), Some(max)) = (cli.min_depth, cli.max_depth)
+        && min > max
+    {
+        return Err("--min-depth can't be greater than --max-depth".to_string());
+    }
+    Ok(cli)
+}
+
+// Read `--targets-from`/`--ignore-from` files and merge their entries into the lists,
+// then check that every ignore pattern is a valid glob
+pub fn load_list_files(mut cli: Cli) -> Result<Cli, String> {
+    if let Some(path) = &cli.targets_from {
+        let entries = read_list_file(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        merge_entries(&mut cli.targets, entries);
+    }
+    if let Some(path) = &cli.ignore_from {
+        let entries = read_list_file(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        merge_entries(&mut cli.ignore, entries);
+    }
+    for pattern in &cli.ignore {
+        glob::Pattern::new(pattern)
+            .map_err(|err| format!("Invalid ignore pattern {}: {}", pattern, err))?;
+    }
+    for pattern in &cli.target_globs {
+        glob::Pattern::new(pattern)
+            .map_err(|err| format!("Invalid target glob {}: {}", pattern, err))?;
+    }
+    Ok(cli)
+}
+
+// Check that the directories to scan exist, so a typo doesn't quietly scan the current
+// directory instead
+pub fn check_directory(cli: Cli) -> Result<Cli, String> {
+    for path in cli.directory.iter().chain(&cli.more_directories) {
+        if !path.is_dir() {
+            return Err(format!("{} is not a directory", path.display()));
+        }
+    }
+    Ok(cli)
+}
+
+// Read a list file, or stdin when the path is `-`
+pub fn read_list_file(path: &Path) -> io::Result<Vec<String>> {
+    let contents = if path == Path::new("-") {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    Ok(parse_list(&contents))
+}
+
+// One entry per line; surrounding whitespace is trimmed and blank or `#` lines are skipped
+pub fn parse_list(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn merge_entries(list: &mut Vec<String>, entries: Vec<String>) {
+    for entry in entries {
+        if !list.contains(&entry) {
+            list.push(entry);
+        }
+    }
+}
+
+// Take the value following a flag
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+// Parse a size such as `512`, `100K`, `20M` or `1.5G` (binary units) into bytes
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let trimmed = value.trim();
+    let split = trimmed
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!("Invalid size unit: {}", value)),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid size: {}", value))?;
+    if number < 0.0 {
+        return Err(format!("Invalid size: {}", value));
+    }
+    Ok((number * multiplier as f64) as u64)
+}
+
+// Parse a `YYYY-MM-DD` date as local midnight at the start of that day
+pub fn parse_date(value: &str) -> Result<SystemTime, String> {
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date (expected YYYY-MM-DD): {}", value))?;
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+    midnight
+        .and_local_timezone(Local)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("Date does not exist in the local timezone: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_directory_and_flags() {
+        let cli = parse(&["--ascii", "--local-only", "./projects"]).unwrap();
+        assert!(cli.ascii);
+        assert!(cli.local_only);
+        assert_eq!(cli.directory, Some(PathBuf::from("./projects")));
+
+        let cli = parse(&["--headless", "--delete"]).unwrap();
+        assert!(cli.headless && cli.delete);
+        assert!(parse(&["--delete"]).is_err());
+        assert_eq!(parse(&["--keep-recent", "3"]).unwrap().keep_recent, Some(3));
+        assert!(parse(&["--keep-recent", "-1"]).is_err());
+        assert_eq!(
+            parse(&["--archive", "/tmp/old"]).unwrap().archive,
+            Some(PathBuf::from("/tmp/old"))
+        );
+        assert!(parse(&["--archive", "/tmp/old", "--permanent"]).is_err());
+        assert!(parse(&["--watch"]).unwrap().watch);
+        assert!(parse(&["--disk-usage"]).unwrap().disk_usage);
+        assert_eq!(
+            parse(&["--emit-script", "cleanup.sh"]).unwrap().emit_script,
+            Some(PathBuf::from("cleanup.sh"))
+        );
+        assert!(parse(&["--emit-script", "cleanup.sh", "--headless", "--delete"]).is_err());
+        assert!(parse(&["--top", "5", "--headless", "--delete"]).is_err());
+        assert!(parse(&["--explain", "./target", "--headless", "--delete"]).is_err());
+        assert_eq!(
+            parse(&["--plan", "plan.toml"]).unwrap().plan,
+            Some(PathBuf::from("plan.toml"))
+        );
+        assert_eq!(
+            parse(&["--apply", "plan.toml"]).unwrap().apply,
+            Some(PathBuf::from("plan.toml"))
+        );
+        assert!(parse(&["--plan", "a.toml", "--apply", "b.toml"]).is_err());
+        assert!(parse(&["--apply", "plan.toml", "--read-only"]).is_err());
+        let cli = parse(&["--config", "team.toml", "--profile-name", "ci"]).unwrap();
+        assert_eq!(cli.config, Some(PathBuf::from("team.toml")));
+        assert_eq!(cli.profile_name.as_deref(), Some("ci"));
+        assert!(parse(&["--read-only"]).unwrap().read_only);
+        assert!(parse(&["--size-in-background"]).unwrap().size_in_background);
+        assert!(parse(&["--resume"]).unwrap().resume);
+        assert!(parse(&["--require-targets"]).unwrap().require_targets);
+        assert!(parse(&["--timestamps"]).unwrap().timestamps);
+        assert!(parse(&["--use-tooling"]).unwrap().use_tooling);
+        assert!(parse(&["--require-clean-git"]).unwrap().require_clean_git);
+        assert!(parse(&["--since-last-run"]).unwrap().since_last_run);
+        assert!(parse(&["--file-counts"]).unwrap().file_counts);
+        assert!(parse(&["--show-owners"]).unwrap().show_owners);
+        assert!(parse(&["--hide-empty"]).unwrap().hide_empty);
+        assert!(
+            parse(&["--no-follow-root-symlink"])
+                .unwrap()
+                .no_follow_root_symlink
+        );
+        assert!(parse(&["--skip-in-use"]).unwrap().skip_in_use);
+        let cli = parse(&["--sort", "size", "--reverse"]).unwrap();
+        assert_eq!(cli.sort, Some(SortColumn::Size));
+        assert!(cli.reverse);
+        assert_eq!(
+            parse(&["--sort", "name"]).unwrap().sort,
+            Some(SortColumn::Name)
+        );
+        assert!(parse(&["--sort", "selected"]).is_err());
+        assert_eq!(
+            parse(&["--min-files", "10000"]).unwrap().min_files,
+            Some(10000)
+        );
+        assert!(parse(&["--min-files", "many"]).is_err());
+        #[cfg(unix)]
+        assert_eq!(
+            parse(&["--owner-only", "1000"]).unwrap().owner_only,
+            Some(1000)
+        );
+        assert_eq!(
+            parse(&["--format", "tree"]).unwrap().format,
+            ListFormat::Tree
+        );
+        assert_eq!(parse(&[]).unwrap().format, ListFormat::Flat);
+        assert!(parse(&["--format", "json"]).is_err());
+        assert_eq!(
+            parse(&["--group-by", "parent"]).unwrap().format,
+            ListFormat::ByParent
+        );
+        assert!(parse(&["--group-by", "owner"]).is_err());
+        assert!(parse(&["--group-by", "parent", "--format", "tree"]).is_err());
+        assert!(parse(&["--read-only", "--headless", "--delete"]).is_err());
+        assert_eq!(
+            parse(&["--confirm-timeout", "30"]).unwrap().confirm_timeout,
+            Some(30)
+        );
+        assert!(parse(&["--confirm-timeout", "0"]).is_err());
+        assert_eq!(
+            parse(&["--select-older-than", "60"])
+                .unwrap()
+                .select_older_than,
+            Some(60)
+        );
+        assert!(parse(&["--disk-usage", "--apparent-size"]).is_err());
+        assert_eq!(parse(&["--limit", "1000"]).unwrap().limit, Some(1000));
+        let cli = parse(&["--min-depth", "2", "--max-depth", "3"]).unwrap();
+        assert_eq!((cli.min_depth, cli.max_depth), (Some(2), Some(3)));
+        assert!(parse(&["--max-depth", "deep"]).is_err());
+        assert!(parse(&["--min-depth", "3", "--max-depth", "2"]).is_err());
+        assert!(parse(&["--limit", "0"]).is_err());
+        assert!(
+            parse(&["--refresh-sizes-before-delete"])
+                .unwrap()
+                .refresh_sizes_before_delete
+        );
+        assert!(
+            parse(&["--trash-fallback-permanent"])
+                .unwrap()
+                .trash_fallback_permanent
+        );
+        assert!(
+            parse(&[
+                "--trash-fallback-permanent",
+                "--no-trash-fallback-permanent"
+            ])
+            .is_err()
+        );
+        assert!(parse(&["--trash-fallback-permanent", "--permanent"]).is_err());
+    }
+
+    #[test]
+    fn validates_max_threads() {
+        assert_eq!(parse(&["--max-threads", "4"]).unwrap().max_threads, Some(4));
+        assert!(parse(&["--max-threads", "0"]).is_err());
+        assert!(parse(&["--max-threads", "many"]).is_err());
+        assert!(parse(&["--max-threads"]).is_err());
+    }
+
+    #[test]
+    fn parses_several_directories() {
+        let cli = parse(&["./a", "--roots-parallel", "2", "./b", "./c"]).unwrap();
+        assert_eq!(cli.directory, Some(PathBuf::from("./a")));
+        assert_eq!(
+            cli.more_directories,
+            [PathBuf::from("./b"), PathBuf::from("./c")]
+        );
+        assert_eq!(cli.roots_parallel, Some(2));
+        assert!(parse(&["--roots-parallel", "0"]).is_err());
+        assert!(parse(&["./a", "./b", "--resume"]).is_err());
+        assert!(parse(&["./a", "./b", "--watch"]).is_err());
+        assert!(parse(&["./a", "./b", "--plan", "plan.toml"]).is_err());
+        assert!(parse(&["./a", "--resume"]).is_ok());
+    }
+
+    #[test]
+    fn parses_sizes_with_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("100K").unwrap(), 100 * 1024);
+        assert_eq!(parse_size("20mb").unwrap(), 20 * 1024 * 1024);
+        assert_eq!(parse_size("1.5G").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("-1").is_err());
+    }
+
+    #[test]
+    fn parses_valid_dates() {
+        let cutoff = parse_date("2024-01-01").unwrap();
+        let expected = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap();
+        assert_eq!(cutoff, SystemTime::from(expected));
+        assert_eq!(
+            parse(&["--before", "2023-12-31"]).unwrap().before,
+            Some(parse_date("2023-12-31").unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("2024-02-30").is_err());
+        assert!(parse_date("01/02/2024").is_err());
+        assert!(parse_date("yesterday").is_err());
+        assert!(parse(&["--before"]).is_err());
+    }
+
+    #[test]
+    fn parses_list_with_comments_and_blank_lines() {
+        let contents =
+            "# build outputs\nnode_modules\n\n   target  \n  # indented comment\n.venv\n";
+        assert_eq!(
+            parse_list(contents),
+            vec!["node_modules", "target", ".venv"]
+        );
+    }
+
+    #[test]
+    fn merges_list_files_with_cli_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let targets = dir.path().join("targets.txt");
+        let ignore = dir.path().join("ignore.txt");
+        std::fs::write(&targets, "# mine\ndist\n\nnode_modules\n").unwrap();
+        std::fs::write(&ignore, "vendor\n# none\n").unwrap();
+
+        let cli = parse(&[
+            "--target",
+            "node_modules",
+            "--targets-from",
+            targets.to_str().unwrap(),
+            "--ignore-from",
+            ignore.to_str().unwrap(),
+        ])
+        .and_then(load_list_files)
+        .unwrap();
+        assert_eq!(cli.targets, vec!["node_modules", "dist"]);
+        assert_eq!(cli.ignore, vec!["vendor"]);
+
+        let missing = parse(&["--targets-from", "/nonexistent/targets.txt"]).unwrap();
+        assert!(load_list_files(missing).is_err());
+        let invalid = parse(&["--ignore", "[unclosed"]).unwrap();
+        assert!(load_list_files(invalid).is_err());
+        let invalid = parse(&["--target-glob", "[unclosed"]).unwrap();
+        assert!(load_list_files(invalid).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_flags() {
+        assert!(parse(&["--bogus"]).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+// Copy text to the system clipboard. Without the `clipboard` feature this always fails,
+// which callers report like any other clipboard error.
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<(), String> {
+    use std::cell::RefCell;
+
+    // On X11 the copied text is served by whoever owns the clipboard, so keep it alive
+    thread_local! {
+        static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+    }
+    CLIPBOARD.with(|cell| {
+        let mut clipboard = cell.borrow_mut();
+        if clipboard.is_none() {
+            *clipboard = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        clipboard
+            .as_mut()
+            .map_or(Ok(()), |c| c.set_text(text).map_err(|e| e.to_string()))
+    })
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<(), String> {
+    Err("built without clipboard support".to_string())
+}
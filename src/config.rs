@@ -0,0 +1,534 @@
+use crate::cli::{self, Cli};
+use crate::keymap::KeyBindings;
+use crate::paths;
+use crate::tooling::CleanCommand;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Looked for in the current directory when `--config` isn't given, so a team can commit one
+pub const DEFAULT_FILES: &[&str] = &["disk-cleaner.toml", "disk-cleaner.json"];
+
+// Per-user fallback, inside `paths::config_dir()`
+pub const USER_FILE: &str = "config.toml";
+
+// Environment variables stand in for flags under this prefix, e.g. `DISK_CLEANER_TARGETS`
+pub const ENV_PREFIX: &str = "DISK_CLEANER_";
+
+// A file of named profiles, each under `[profiles.<name>]`, plus key bindings under `[keys]`
+// and clean commands for `--use-tooling` under `[[tooling]]`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileFile {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    // Action name to the key, or keys, that trigger it
+    #[serde(default)]
+    pub keys: BTreeMap<String, KeySpec>,
+    // Tried before the built-in commands
+    #[serde(default)]
+    pub tooling: Vec<CleanCommand>,
+}
+
+// One key (`select_all = "r"`) or several (`clean = ["c", "Enter"]`)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpec {
+    fn into_keys(self) -> Vec<String> {
+        match self {
+            KeySpec::One(key) => vec![key],
+            KeySpec::Many(keys) => keys,
+        }
+    }
+}
+
+// Settings bundled under one name; anything left out keeps its default
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub targets: Vec<String>,
+    pub ignore: Vec<String>,
+    // Auto-select matches older than this many days
+    pub age: Option<u32>,
+    // Skip matches smaller than this, e.g. "100M"
+    pub min_size: Option<String>,
+    // Skip matches holding fewer files than this
+    pub min_files: Option<u64>,
+    pub mode: Option<ProfileMode>,
+    // Where `mode = "archive"` moves folders
+    pub archive: Option<PathBuf>,
+    // Delete folders the trash refuses for good, like `--trash-fallback-permanent`
+    pub trash_fallback_permanent: Option<bool>,
+}
+
+// How a profile cleans up, matching `--permanent` and `--archive`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileMode {
+    Trash,
+    Permanent,
+    Archive,
+}
+
+impl ProfileFile {
+    // TOML, or JSON when the file name ends in `.json`
+    pub fn parse(contents: &str, json: bool) -> Result<Self, String> {
+        if json {
+            serde_json::from_str(contents).map_err(|err| err.to_string())
+        } else {
+            toml::from_str(contents).map_err(|err| err.to_string())
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        let json = path.extension().is_some_and(|ext| ext == "json");
+        Self::parse(&contents, json).map_err(|err| format!("Invalid {}: {}", path.display(), err))
+    }
+
+    // The default key bindings with this file's `[keys]` applied
+    pub fn key_bindings(&self) -> Result<KeyBindings, String> {
+        let overrides = self
+            .keys
+            .iter()
+            .map(|(action, spec)| (action.clone(), spec.clone().into_keys()))
+            .collect();
+        KeyBindings::default().with_overrides(&overrides)
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, String> {
+        self.profiles.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            match known.len() {
+                0 => format!("Unknown profile {}: the file defines none", name),
+                _ => format!("Unknown profile {} (available: {})", name, known.join(", ")),
+            }
+        })
+    }
+}
+
+impl Profile {
+    // Fill in what the command line left unset; flags given explicitly always win
+    pub fn apply(&self, cli: &mut Cli) -> Result<(), String> {
+        if cli.targets.is_empty() && cli.target_globs.is_empty() && cli.targets_from.is_none() {
+            cli.targets = self.targets.clone();
+        }
+        for pattern in &self.ignore {
+            if !cli.ignore.contains(pattern) {
+                cli.ignore.push(pattern.clone());
+            }
+        }
+        if cli.select_older_than.is_none() {
+            cli.select_older_than = self.age;
+        }
+        if cli.min_size.is_none()
+            && let Some(size) = &self.min_size
+        {
+            cli.min_size = Some(cli::parse_size(size)?);
+        }
+        if cli.min_files.is_none() {
+            cli.min_files = self.min_files;
+        }
+        if self.mode == Some(ProfileMode::Archive) && self.archive.is_none() {
+            return Err("Profile mode \"archive\" needs an archive folder".to_string());
+        }
+        if cli.permanent || cli.archive.is_some() {
+            return Ok(());
+        }
+        match self.mode {
+            Some(ProfileMode::Permanent) => cli.permanent = true,
+            Some(ProfileMode::Archive) => cli.archive = self.archive.clone(),
+            Some(ProfileMode::Trash) | None => {}
+        }
+        // `--no-trash-fallback-permanent` keeps a profile from turning the fallback on
+        if !cli.no_trash_fallback_permanent
+            && !cli.permanent
+            && cli.archive.is_none()
+            && let Some(fallback) = self.trash_fallback_permanent
+        {
+            cli.trash_fallback_permanent |= fallback;
+        }
+        if cli.trash_fallback_permanent && (cli.permanent || cli.archive.is_some()) {
+            return Err(
+                "--trash-fallback-permanent only applies when moving to the trash".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// Fill in what the command line left unset from `DISK_CLEANER_*` variables. Runs before
+// the config file is read, so flags win over the environment, which wins over the file.
+pub fn apply_env(cli: Cli) -> Result<Cli, String> {
+    apply_env_with(cli, |name| std::env::var(name).ok())
+}
+
+fn apply_env_with(mut cli: Cli, var: impl Fn(&str) -> Option<String>) -> Result<Cli, String> {
+    // Set but empty counts as unset, so a variable can be blanked out in a compose file
+    let var = |name: &str| {
+        let name = format!("{}{}", ENV_PREFIX, name);
+        var(&name)
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| (name, value))
+    };
+    // Comma-separated, like `node_modules,target`
+    let list = |(_, value): (String, String)| -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(String::from)
+            .collect()
+    };
+
+    if cli.targets.is_empty()
+        && cli.target_globs.is_empty()
+        && cli.targets_from.is_none()
+        && let Some(targets) = var("TARGETS")
+    {
+        cli.targets = list(targets);
+    }
+    // Combined with the command line's, as a profile's are
+    if let Some(ignore) = var("IGNORE") {
+        for pattern in list(ignore) {
+            if !cli.ignore.contains(&pattern) {
+                cli.ignore.push(pattern);
+            }
+        }
+    }
+    if cli.min_age.is_none()
+        && let Some(days) = var("MIN_AGE")
+    {
+        cli.min_age = Some(number(days)?);
+    }
+    if cli.select_older_than.is_none()
+        && let Some(days) = var("SELECT_OLDER_THAN")
+    {
+        cli.select_older_than = Some(number(days)?);
+    }
+    if cli.min_size.is_none()
+        && let Some((name, size)) = var("MIN_SIZE")
+    {
+        cli.min_size = Some(
+            cli::parse_size(size.trim())
+                .map_err(|_| format!("Invalid value for {}: {}", name, size))?,
+        );
+    }
+    if cli.min_files.is_none()
+        && let Some(files) = var("MIN_FILES")
+    {
+        cli.min_files = Some(number(files)?);
+    }
+    if cli.config.is_none()
+        && let Some((_, file)) = var("CONFIG")
+    {
+        cli.config = Some(PathBuf::from(file));
+    }
+    if cli.profile_name.is_none()
+        && let Some((_, name)) = var("PROFILE")
+    {
+        cli.profile_name = Some(name);
+    }
+    Ok(cli)
+}
+
+// A numeric variable, given as its name and value
+fn number<T: std::str::FromStr>((name, value): (String, String)) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+// The profile file to read: `--config`, the first default file in the current directory,
+// or the user's own `config.toml` in the platform's config directory
+fn profile_file(cli: &Cli) -> Option<PathBuf> {
+    cli.config.clone().or_else(|| {
+        DEFAULT_FILES
+            .iter()
+            .map(PathBuf::from)
+            .chain(paths::config_dir().map(|dir| dir.join(USER_FILE)))
+            .find(|path| path.is_file())
+    })
+}
+
+// Read the config file, if there is one, into the parsed options: its key bindings, clean
+// commands, and the profile picked with `--profile-name`
+pub fn apply_config(mut cli: Cli) -> Result<Cli, String> {
+    let Some(path) = profile_file(&cli) else {
+        return match cli.profile_name {
+            Some(_) => Err(format!(
+                "--profile-name needs a profile file: pass --config or add {}",
+                DEFAULT_FILES.join(" or ")
+            )),
+            None => Ok(cli),
+        };
+    };
+    let file = ProfileFile::load(&path)?;
+    cli.key_bindings = file
+        .key_bindings()
+        .map_err(|err| format!("Invalid {}: {}", path.display(), err))?;
+    cli.tooling = file.tooling.clone();
+    if let Some(name) = cli.profile_name.clone() {
+        file.profile(&name)?.apply(&mut cli)?;
+    }
+    Ok(cli)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEAM_FILE: &str = r#"
+[profiles.ci]
+targets = ["target", "node_modules"]
+ignore = ["**/vendor/**"]
+age = 7
+min_size = "100M"
+min_files = 10000
+mode = "permanent"
+
+[profiles.laptop]
+targets = ["node_modules"]
+mode = "archive"
+archive = "/tmp/old-builds"
+"#;
+
+    #[test]
+    fn loads_a_multi_profile_file_and_selects_one() {
+        let file = ProfileFile::parse(TEAM_FILE, false).unwrap();
+        assert_eq!(file.profiles.len(), 2);
+
+        let mut cli = Cli::default();
+        file.profile("ci").unwrap().apply(&mut cli).unwrap();
+        assert_eq!(cli.targets, vec!["target", "node_modules"]);
+        assert_eq!(cli.ignore, vec!["**/vendor/**"]);
+        assert_eq!(cli.select_older_than, Some(7));
+        assert_eq!(cli.min_size, Some(100 * 1024 * 1024));
+        assert_eq!(cli.min_files, Some(10000));
+        assert!(cli.permanent);
+
+        let mut cli = Cli::default();
+        file.profile("laptop").unwrap().apply(&mut cli).unwrap();
+        assert_eq!(cli.targets, vec!["node_modules"]);
+        assert_eq!(cli.archive, Some(PathBuf::from("/tmp/old-builds")));
+        assert!(!cli.permanent);
+        assert_eq!(cli.select_older_than, None);
+
+        let err = file.profile("nightly").unwrap_err();
+        assert_eq!(err, "Unknown profile nightly (available: ci, laptop)");
+    }
+
+    #[test]
+    fn command_line_flags_win_over_the_profile() {
+        let file = ProfileFile::parse(TEAM_FILE, false).unwrap();
+        let mut cli = Cli {
+            targets: vec!["dist".to_string()],
+            ignore: vec!["**/keep/**".to_string()],
+            select_older_than: Some(60),
+            archive: Some(PathBuf::from("/mnt/archive")),
+            ..Cli::default()
+        };
+        file.profile("ci").unwrap().apply(&mut cli).unwrap();
+        assert_eq!(cli.targets, vec!["dist"]);
+        assert_eq!(cli.ignore, vec!["**/keep/**", "**/vendor/**"]);
+        assert_eq!(cli.select_older_than, Some(60));
+        assert!(!cli.permanent);
+        assert_eq!(cli.archive, Some(PathBuf::from("/mnt/archive")));
+
+        // A targets file replaces the profile's targets rather than adding to them
+        let mut cli = Cli {
+            targets_from: Some(PathBuf::from("targets.txt")),
+            ..Cli::default()
+        };
+        file.profile("ci").unwrap().apply(&mut cli).unwrap();
+        assert!(cli.targets.is_empty());
+    }
+
+    #[test]
+    fn no_trash_fallback_permanent_overrides_the_profile() {
+        let file = ProfileFile::parse(
+            "[profiles.shared]\ntrash_fallback_permanent = true\n",
+            false,
+        )
+        .unwrap();
+        let profile = file.profile("shared").unwrap();
+
+        let mut cli = Cli::default();
+        profile.apply(&mut cli).unwrap();
+        assert!(cli.trash_fallback_permanent);
+
+        let mut cli = Cli {
+            no_trash_fallback_permanent: true,
+            ..Cli::default()
+        };
+        profile.apply(&mut cli).unwrap();
+        assert!(!cli.trash_fallback_permanent);
+
+        // Deleting for good or archiving anyway, there's nothing to fall back from
+        let mut cli = Cli {
+            permanent: true,
+            ..Cli::default()
+        };
+        profile.apply(&mut cli).unwrap();
+        assert!(!cli.trash_fallback_permanent);
+    }
+
+    #[test]
+    fn reads_json_and_rejects_bad_profiles() {
+        let json = r#"{"profiles": {"ci": {"targets": ["target"], "age": 3}}}"#;
+        let file = ProfileFile::parse(json, true).unwrap();
+        assert_eq!(file.profile("ci").unwrap().age, Some(3));
+
+        assert!(ProfileFile::parse("[profiles.ci]\nagee = 3\n", false).is_err());
+        let archive_without_folder =
+            ProfileFile::parse("[profiles.x]\nmode = \"archive\"\n", false).unwrap();
+        assert!(
+            archive_without_folder
+                .profile("x")
+                .unwrap()
+                .apply(&mut Cli::default())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn profile_name_loads_the_given_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("team.toml");
+        fs::write(&path, TEAM_FILE).unwrap();
+
+        let cli = Cli {
+            config: Some(path),
+            profile_name: Some("ci".to_string()),
+            ..Cli::default()
+        };
+        let cli = apply_config(cli).unwrap();
+        assert!(cli.permanent);
+
+        // A config file that was asked for has to exist
+        let cli = Cli {
+            config: Some(dir.path().join("missing.toml")),
+            ..Cli::default()
+        };
+        assert!(apply_config(cli).is_err());
+    }
+
+    #[test]
+    fn keys_table_rebinds_actions_and_rejects_conflicts() {
+        use crate::keymap::Action;
+        use crossterm::event::KeyCode;
+
+        let file = ProfileFile::parse(
+            "[keys]\nselect_all = \"r\"\nclean = [\"k\", \"Enter\"]\n",
+            false,
+        )
+        .unwrap();
+        let keys = file.key_bindings().unwrap();
+        assert_eq!(keys.action_for(KeyCode::Char('r')), Some(Action::SelectAll));
+        assert_eq!(keys.action_for(KeyCode::Char('k')), Some(Action::Clean));
+        assert_eq!(keys.action_for(KeyCode::Char('c')), None);
+
+        let clash = ProfileFile::parse("[keys]\ninvert = \"a\"\n", false).unwrap();
+        assert!(clash.key_bindings().is_err());
+    }
+
+    // A stand-in for the process environment
+    fn env(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn environment_fills_in_unset_options() {
+        let vars = env(&[
+            ("DISK_CLEANER_TARGETS", "node_modules, .venv"),
+            ("DISK_CLEANER_IGNORE", "**/vendor/**,**/keep/**"),
+            ("DISK_CLEANER_MIN_AGE", "14"),
+            ("DISK_CLEANER_SELECT_OLDER_THAN", "60"),
+            ("DISK_CLEANER_MIN_SIZE", "1G"),
+            ("DISK_CLEANER_MIN_FILES", "100"),
+            ("DISK_CLEANER_PROFILE", "ci"),
+            ("DISK_CLEANER_CONFIG", ""),
+        ]);
+        let cli = Cli {
+            ignore: vec!["**/keep/**".to_string()],
+            ..Cli::default()
+        };
+        let cli = apply_env_with(cli, |name| vars.get(name).cloned()).unwrap();
+        assert_eq!(cli.targets, vec!["node_modules", ".venv"]);
+        assert_eq!(cli.ignore, vec!["**/keep/**", "**/vendor/**"]);
+        assert_eq!(cli.min_age, Some(14));
+        assert_eq!(cli.select_older_than, Some(60));
+        assert_eq!(cli.min_size, Some(1024 * 1024 * 1024));
+        assert_eq!(cli.min_files, Some(100));
+        assert_eq!(cli.profile_name, Some("ci".to_string()));
+        assert_eq!(cli.config, None);
+
+        let err = apply_env_with(Cli::default(), |name| {
+            env(&[("DISK_CLEANER_MIN_AGE", "old")]).remove(name)
+        });
+        assert_eq!(
+            err.unwrap_err(),
+            "Invalid value for DISK_CLEANER_MIN_AGE: old"
+        );
+    }
+
+    #[test]
+    fn flags_win_over_the_environment_which_wins_over_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("team.toml");
+        fs::write(&path, TEAM_FILE).unwrap();
+        let vars = env(&[
+            ("DISK_CLEANER_CONFIG", path.to_str().unwrap()),
+            ("DISK_CLEANER_PROFILE", "ci"),
+            ("DISK_CLEANER_TARGETS", "dist"),
+            ("DISK_CLEANER_SELECT_OLDER_THAN", "30"),
+            ("DISK_CLEANER_MIN_FILES", "5"),
+        ]);
+        let cli = Cli {
+            min_files: Some(1),
+            ..Cli::default()
+        };
+        let cli =
+            apply_config(apply_env_with(cli, |name| vars.get(name).cloned()).unwrap()).unwrap();
+        // Flag over environment
+        assert_eq!(cli.min_files, Some(1));
+        // Environment over the profile's `targets` and `age`
+        assert_eq!(cli.targets, vec!["dist"]);
+        assert_eq!(cli.select_older_than, Some(30));
+        // The profile still fills in what neither set
+        assert_eq!(cli.min_size, Some(100 * 1024 * 1024));
+        assert!(cli.permanent);
+    }
+
+    #[test]
+    fn reads_tooling_commands() {
+        let file = ProfileFile::parse(
+            r#"
+[[tooling]]
+target = "target"
+marker = "Cargo.toml"
+command = ["cargo", "clean", "--release"]
+"#,
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            file.tooling,
+            vec![CleanCommand {
+                target: "target".to_string(),
+                marker: "Cargo.toml".to_string(),
+                command: vec!["cargo".into(), "clean".into(), "--release".into()],
+            }]
+        );
+    }
+}
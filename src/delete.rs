@@ -0,0 +1,741 @@
+use crate::app::DirInfo;
+use crate::scanner;
+use crate::tooling::{self, CleanCommand};
+use crate::units::{SizeUnits, human_size};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+// What happens to the directories chosen for cleanup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeleteMode {
+    // Move to the system trash (the default)
+    Trash(TrashFailure),
+    // Remove for good, bypassing the trash
+    Permanent,
+    // Move under a timestamped folder inside this directory, keeping paths relative to the scan root
+    Archive(PathBuf),
+}
+
+impl Default for DeleteMode {
+    fn default() -> Self {
+        DeleteMode::Trash(TrashFailure::default())
+    }
+}
+
+// What to do with a directory the trash refused to take
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrashFailure {
+    // Leave it where it is and count it as failed (the default)
+    #[default]
+    Report,
+    // Delete it for good instead
+    DeletePermanently,
+}
+
+impl DeleteMode {
+    // Confirmation question for cleaning up `what`, e.g. "3 selected items". With
+    // `tooling`, projects a build tool owns are cleaned by it for good, which is said too.
+    pub fn confirm_question(&self, what: &str, tooling: bool) -> String {
+        let question = match self {
+            DeleteMode::Trash(TrashFailure::Report) => format!("Move {} to trash", what),
+            DeleteMode::Trash(TrashFailure::DeletePermanently) => format!(
+                "Move {} to trash (deleting permanently if that fails)",
+                what
+            ),
+            DeleteMode::Permanent => return format!("PERMANENTLY DELETE {}", what),
+            DeleteMode::Archive(dir) => format!("Move {} to {}", what, dir.display()),
+        };
+        if !tooling {
+            return question;
+        }
+        let kept_in = match self {
+            DeleteMode::Archive(_) => "the archive",
+            _ => "the trash",
+        };
+        format!(
+            "{}, except projects their build tool cleans, which can't be restored from {}",
+            question, kept_in
+        )
+    }
+
+    // Nothing removed this way can be brought back
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, DeleteMode::Permanent)
+    }
+
+    // Title for the progress gauge
+    pub fn progress_label(&self) -> &'static str {
+        match self {
+            DeleteMode::Trash(_) => "Moving to trash",
+            DeleteMode::Permanent => "Deleting permanently",
+            DeleteMode::Archive(_) => "Moving to archive",
+        }
+    }
+}
+
+// A rough model of how long removing things takes: a fixed cost per folder for finding
+// and moving it, plus one per file inside it, which is what makes huge `node_modules` slow
+const SECONDS_PER_FOLDER: f64 = 0.05;
+const FILES_PER_SECOND: f64 = 20_000.0;
+
+// Expected time to clean up `folders` folders holding `file_count` files in all
+pub fn estimate_duration(folders: usize, file_count: u64) -> Duration {
+    Duration::from_secs_f64(
+        folders as f64 * SECONDS_PER_FOLDER + file_count as f64 / FILES_PER_SECOND,
+    )
+}
+
+// What a cleanup did, path by path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeletionReport {
+    pub succeeded: Vec<PathBuf>,
+    // Each path that is still there, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    // Removed by something else between the scan and the cleanup, so not attempted
+    pub already_gone: Vec<PathBuf>,
+    pub bytes_freed: u64,
+}
+
+impl DeletionReport {
+    pub fn attempted(&self) -> usize {
+        self.succeeded.len() + self.failed.len()
+    }
+
+    // e.g. "Cleaned 2 of 3 folders, freeing 1.5 GiB"
+    pub fn summary(&self, units: SizeUnits) -> String {
+        let summary = format!(
+            "Cleaned {} of {} folders, freeing {}",
+            self.succeeded.len(),
+            self.attempted(),
+            human_size(self.bytes_freed, units)
+        );
+        match self.already_gone_note() {
+            Some(note) => format!("{}; {}", summary, note),
+            None => summary,
+        }
+    }
+
+    // e.g. "3 already removed by something else"
+    pub fn already_gone_note(&self) -> Option<String> {
+        match self.already_gone.len() {
+            0 => None,
+            gone => Some(format!("{} already removed by something else", gone)),
+        }
+    }
+
+    // One line per failure: path and reason
+    pub fn failure_lines(&self) -> Vec<String> {
+        self.failed
+            .iter()
+            .map(|(path, reason)| format!("{}: {}", path.display(), reason))
+            .collect()
+    }
+}
+
+// Clean up each directory, calling `on_progress` after every attempt. A directory
+// one of `tools` owns is cleaned by that tool first, and by `mode` if that leaves it.
+pub fn remove_dirs(
+    dirs: &[DirInfo],
+    mode: &DeleteMode,
+    tools: &[CleanCommand],
+    root: &Path,
+    mut on_progress: impl FnMut(usize, usize),
+) -> DeletionReport {
+    let archive_batch = match mode {
+        DeleteMode::Archive(dir) => Some(archive_batch(dir)),
+        _ => None,
+    };
+    let root = scanner::absolute_path(root);
+
+    let mut report = DeletionReport::default();
+    for (i, dir) in dirs.iter().enumerate() {
+        let path = scanner::long_path(&dir.path);
+        // Gone since the scan; trashing it would only fail
+        if fs::symlink_metadata(&path).is_err_and(|err| err.kind() == io::ErrorKind::NotFound) {
+            report.already_gone.push(dir.path.clone());
+            on_progress(i + 1, dirs.len());
+            continue;
+        }
+        if let Some(command) = tooling::command_for(&dir.path, tools)
+            && tooling::run(command, &dir.path).is_ok()
+            && fs::symlink_metadata(&path).is_err()
+        {
+            report.succeeded.push(dir.path.clone());
+            report.bytes_freed += dir.size_bytes;
+            on_progress(i + 1, dirs.len());
+            continue;
+        }
+        let result = match (mode, &archive_batch) {
+            (DeleteMode::Archive(_), Some(batch)) => {
+                let destination = batch.join(relative_to_root(&dir.path, &root));
+                move_dir(&path, &scanner::long_path(&destination)).map_err(|e| e.to_string())
+            }
+            (DeleteMode::Permanent, _) => fs::remove_dir_all(&path).map_err(|e| e.to_string()),
+            (DeleteMode::Trash(on_failure), _) => trash_or_fallback(&path, *on_failure, |p| {
+                trash::delete(p).map_err(|e| e.to_string())
+            }),
+            (DeleteMode::Archive(_), None) => Err("no archive folder".to_string()),
+        };
+        // Only count directories that are actually gone; a recycle bin move can report
+        // success without removing anything
+        let result = result.and_then(|()| match fs::symlink_metadata(&path) {
+            Ok(_) => Err("still exists after removal".to_string()),
+            Err(_) => Ok(()),
+        });
+        match result {
+            Ok(()) => {
+                report.succeeded.push(dir.path.clone());
+                report.bytes_freed += dir.size_bytes;
+            }
+            Err(reason) => report.failed.push((dir.path.clone(), reason)),
+        }
+        on_progress(i + 1, dirs.len());
+    }
+    report
+}
+
+// Whether this platform lets us empty the system trash
+pub const CAN_EMPTY_TRASH: bool = cfg!(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+));
+
+// Whether folders under `root` can be moved to the trash. Minimal containers often have
+// no trash at all, and every cleanup would fail. A throwaway file is made in `root`,
+// trashed and purged from the trash again; `Err` says why trashing it failed. Where the
+// probe can't be purged it would be left in the trash, so those platforms, which always
+// have one, aren't probed.
+pub fn probe_trash(root: &Path) -> Result<(), String> {
+    if !CAN_EMPTY_TRASH {
+        return Ok(());
+    }
+    probe_trash_with(
+        root,
+        |path| trash::delete(path).map_err(|e| e.to_string()),
+        purge_probe,
+    )
+}
+
+fn probe_trash_with(
+    root: &Path,
+    trash: impl FnOnce(&Path) -> Result<(), String>,
+    purge: impl FnOnce(&Path),
+) -> Result<(), String> {
+    let probe = root.join(format!(".disk-cleaner-trash-probe-{}", std::process::id()));
+    // Nothing under a root we can't write to can be cleaned up anyway, trash or not
+    if fs::write(&probe, b"").is_err() {
+        return Ok(());
+    }
+    match trash(&probe) {
+        Ok(()) => {
+            purge(&probe);
+            Ok(())
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&probe);
+            Err(err)
+        }
+    }
+}
+
+// Take the probe file back out of the trash, where the platform lets us
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn purge_probe(probe: &Path) {
+    if let Ok(items) = trash::os_limited::list() {
+        let probes: Vec<_> = items
+            .into_iter()
+            .filter(|item| item.original_path() == probe)
+            .collect();
+        let _ = trash::os_limited::purge_all(probes);
+    }
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+fn purge_probe(_probe: &Path) {}
+
+// Permanently delete everything in the system trash, not just what we put there
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+pub fn empty_trash() -> Result<(), String> {
+    let items = trash::os_limited::list().map_err(|e| e.to_string())?;
+    trash::os_limited::purge_all(items).map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+pub fn empty_trash() -> Result<(), String> {
+    Err("emptying the trash isn't supported on this platform".to_string())
+}
+
+// How much the system trash holds, so people remember it still takes up disk space
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+pub fn trash_size() -> Result<u64, String> {
+    let items = trash::os_limited::list().map_err(|e| e.to_string())?;
+    Ok(trash_listing_total(&items, |item| {
+        trash::os_limited::metadata(item).ok().map(|m| m.size)
+    }))
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+pub fn trash_size() -> Result<u64, String> {
+    Err("reading the trash isn't supported on this platform".to_string())
+}
+
+// Add up a trash listing. The trash only reports entry counts for directories, so those
+// are measured from their contents; items it can't describe count as nothing
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn trash_listing_total(
+    items: &[trash::TrashItem],
+    metadata: impl Fn(&trash::TrashItem) -> Option<trash::TrashItemSize>,
+) -> u64 {
+    items
+        .iter()
+        .map(|item| match metadata(item) {
+            Some(trash::TrashItemSize::Bytes(bytes)) => bytes,
+            Some(trash::TrashItemSize::Entries(_)) => scanner::calculate_directory_size(
+                &trashed_contents(item),
+                scanner::SizeOptions::default(),
+            ),
+            None => 0,
+        })
+        .sum()
+}
+
+// Where a trashed item's contents are. Freedesktop ids name its `info/<name>.trashinfo`
+// file, with the contents in `files/<name>`; Windows ids are already the path
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+fn trashed_contents(item: &trash::TrashItem) -> PathBuf {
+    let id = Path::new(&item.id);
+    if cfg!(windows) {
+        return id.to_path_buf();
+    }
+    match (id.parent().and_then(Path::parent), id.file_stem()) {
+        (Some(trash), Some(name)) => trash.join("files").join(name),
+        _ => id.to_path_buf(),
+    }
+}
+
+// Timestamped folder inside `dir` that one cleanup archives into
+pub fn archive_batch(dir: &Path) -> PathBuf {
+    dir.join(
+        chrono::Local::now()
+            .format("disk-cleaner-%Y%m%d-%H%M%S")
+            .to_string(),
+    )
+}
+
+// Trash a directory, falling back to deleting it only when that was explicitly asked for
+fn trash_or_fallback(
+    path: &Path,
+    on_failure: TrashFailure,
+    trash: impl FnOnce(&Path) -> Result<(), String>,
+) -> Result<(), String> {
+    match (trash(path), on_failure) {
+        (Ok(()), _) => Ok(()),
+        (Err(err), TrashFailure::Report) => Err(format!("could not move to trash: {}", err)),
+        (Err(err), TrashFailure::DeletePermanently) => fs::remove_dir_all(path)
+            .map_err(|e| format!("could not move to trash ({}) or delete: {}", err, e)),
+    }
+}
+
+// The directory's path below the scan root, or its full path without the root prefix
+pub fn relative_to_root(path: &Path, root: &Path) -> PathBuf {
+    let path = scanner::absolute_path(path);
+    match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect(),
+    }
+}
+
+// Rename into place, copying and then removing the original when crossing filesystems
+pub fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir_all(from, to)?;
+            fs::remove_dir_all(from)
+        }
+        result => result,
+    }
+}
+
+// Recursively copy a directory, recreating symlinks rather than following them
+pub fn copy_dir_all(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_info(path: &Path, size_bytes: u64) -> DirInfo {
+        DirInfo {
+            selected: true,
+            size_bytes,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    #[test]
+    fn archive_moves_dirs_keeping_relative_structure() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = tempfile::tempdir().unwrap();
+        let target = root.path().join("project/target");
+        fs::create_dir_all(target.join("debug")).unwrap();
+        fs::write(target.join("debug/app"), b"binary").unwrap();
+
+        let mode = DeleteMode::Archive(archive.path().to_path_buf());
+        let report = remove_dirs(&[dir_info(&target, 6)], &mode, &[], root.path(), |_, _| {});
+
+        assert_eq!(report.succeeded, vec![target.clone()]);
+        assert_eq!(report.bytes_freed, 6);
+        assert!(!target.exists());
+        let batches: Vec<PathBuf> = fs::read_dir(archive.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(batches.len(), 1);
+        let name = batches[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        assert!(name.starts_with("disk-cleaner-"));
+        let moved = batches[0].join("project/target/debug/app");
+        assert_eq!(fs::read(moved).unwrap(), b"binary");
+    }
+
+    #[test]
+    fn permanent_removes_dirs_and_counts_only_successes() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("target");
+        fs::create_dir_all(&target).unwrap();
+        // Under a file, so it can't be looked at, let alone removed
+        fs::write(root.path().join("file"), "").unwrap();
+        let blocked = root.path().join("file/target");
+
+        let mut progress = Vec::new();
+        let dirs = [dir_info(&target, 10), dir_info(&blocked, 20)];
+        let report = remove_dirs(
+            &dirs,
+            &DeleteMode::Permanent,
+            &[],
+            root.path(),
+            |done, total| progress.push((done, total)),
+        );
+
+        assert_eq!(report.succeeded, vec![target.clone()]);
+        assert_eq!(report.bytes_freed, 10);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, blocked);
+        assert!(!target.exists());
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn dirs_removed_since_the_scan_are_skipped_as_already_gone() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs: Vec<DirInfo> = ["a", "b", "c"]
+            .iter()
+            .map(|project| {
+                let target = root.path().join(project).join("target");
+                fs::create_dir_all(&target).unwrap();
+                dir_info(&target, 5)
+            })
+            .collect();
+        // Another process cleans up `b` before the deletion pass
+        fs::remove_dir_all(&dirs[1].path).unwrap();
+
+        let report = remove_dirs(&dirs, &DeleteMode::Permanent, &[], root.path(), |_, _| {});
+        assert_eq!(
+            report.succeeded,
+            [dirs[0].path.clone(), dirs[2].path.clone()]
+        );
+        assert!(report.failed.is_empty());
+        assert_eq!(report.already_gone, [dirs[1].path.clone()]);
+        assert_eq!(report.bytes_freed, 10);
+        assert_eq!(
+            report.summary(SizeUnits::Binary),
+            "Cleaned 2 of 2 folders, freeing 10 B; 1 already removed by something else"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tooling_cleans_owned_dirs_and_falls_back_for_the_rest() {
+        let root = tempfile::tempdir().unwrap();
+        for project in ["tool", "plain", "broken"] {
+            fs::create_dir_all(root.path().join(project).join("target")).unwrap();
+        }
+        fs::write(root.path().join("tool/Cargo.toml"), "").unwrap();
+        fs::write(root.path().join("broken/pom.xml"), "").unwrap();
+        let command = |marker: &str, command: &[&str]| CleanCommand {
+            target: "target".to_string(),
+            marker: marker.to_string(),
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+        };
+        let tools = [
+            command("Cargo.toml", &["rm", "-r", "target"]),
+            command("pom.xml", &["false"]),
+        ];
+
+        let dirs: Vec<DirInfo> = ["tool", "plain", "broken"]
+            .iter()
+            .map(|project| dir_info(&root.path().join(project).join("target"), 1))
+            .collect();
+        let report = remove_dirs(
+            &dirs,
+            &DeleteMode::Permanent,
+            &tools,
+            root.path(),
+            |_, _| {},
+        );
+
+        // The tool's own clean, no tool at all, and a failing tool all end with it gone
+        assert_eq!(report.succeeded.len(), 3);
+        assert!(report.failed.is_empty());
+        assert!(dirs.iter().all(|dir| !dir.path.exists()));
+        assert!(root.path().join("tool/Cargo.toml").exists());
+    }
+
+    #[test]
+    fn report_summarizes_successes_and_failures() {
+        let report = DeletionReport {
+            succeeded: vec![PathBuf::from("/p/a/target"), PathBuf::from("/p/b/target")],
+            failed: vec![(
+                PathBuf::from("/p/c/target"),
+                "permission denied".to_string(),
+            )],
+            already_gone: Vec::new(),
+            bytes_freed: 2048,
+        };
+        assert_eq!(report.attempted(), 3);
+        assert_eq!(
+            report.summary(SizeUnits::Binary),
+            "Cleaned 2 of 3 folders, freeing 2 KiB"
+        );
+        assert_eq!(
+            report.failure_lines(),
+            vec!["/p/c/target: permission denied"]
+        );
+        assert_eq!(
+            DeletionReport::default().summary(SizeUnits::Binary),
+            "Cleaned 0 of 0 folders, freeing 0 B"
+        );
+    }
+
+    #[test]
+    fn trash_failures_only_delete_permanently_when_asked() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("target");
+        let trash_fails = |_: &Path| Err("no trash here".to_string());
+
+        // Trash works: nothing else happens, whatever the fallback
+        for on_failure in [TrashFailure::Report, TrashFailure::DeletePermanently] {
+            fs::create_dir_all(&dir).unwrap();
+            assert!(trash_or_fallback(&dir, on_failure, |_| Ok(())).is_ok());
+            assert!(dir.exists());
+        }
+
+        // Trash fails: reported and kept by default
+        let err = trash_or_fallback(&dir, TrashFailure::Report, trash_fails).unwrap_err();
+        assert_eq!(err, "could not move to trash: no trash here");
+        assert!(dir.exists());
+
+        // Trash fails: deleted for good only when opted in
+        assert!(trash_or_fallback(&dir, TrashFailure::DeletePermanently, trash_fails).is_ok());
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn copy_fallback_copies_nested_files() {
+        let from = tempfile::tempdir().unwrap();
+        let to = tempfile::tempdir().unwrap();
+        fs::create_dir_all(from.path().join("a/b")).unwrap();
+        fs::write(from.path().join("a/b/file"), b"data").unwrap();
+        fs::write(from.path().join("top"), b"x").unwrap();
+
+        let destination = to.path().join("copy");
+        copy_dir_all(from.path(), &destination).unwrap();
+        assert_eq!(fs::read(destination.join("a/b/file")).unwrap(), b"data");
+        assert_eq!(fs::read(destination.join("top")).unwrap(), b"x");
+    }
+
+    #[test]
+    fn paths_outside_the_root_keep_their_full_structure() {
+        assert_eq!(
+            relative_to_root(Path::new("/elsewhere/app/target"), Path::new("/projects")),
+            PathBuf::from("elsewhere/app/target")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn trash_listing_adds_up_files_and_measured_directories() {
+        use std::ffi::OsString;
+
+        let trash = tempfile::tempdir().unwrap();
+        let files = trash.path().join("files");
+        fs::create_dir_all(files.join("target/debug")).unwrap();
+        fs::write(files.join("target/debug/app"), vec![0; 1000]).unwrap();
+        fs::write(files.join("notes.txt"), vec![0; 500]).unwrap();
+
+        let item = |name: &str| trash::TrashItem {
+            id: trash
+                .path()
+                .join("info")
+                .join(format!("{}.trashinfo", name))
+                .into(),
+            name: OsString::from(name),
+            original_parent: PathBuf::from("/projects/app"),
+            time_deleted: 0,
+        };
+        let items = [item("target"), item("notes.txt"), item("vanished")];
+        assert_eq!(trashed_contents(&items[0]), files.join("target"));
+
+        let total = trash_listing_total(&items, |item| match item.name.to_str() {
+            Some("target") => Some(trash::TrashItemSize::Entries(1)),
+            Some("notes.txt") => Some(trash::TrashItemSize::Bytes(500)),
+            _ => None,
+        });
+        assert_eq!(total, 1500);
+    }
+
+    #[test]
+    fn trash_probe_reports_failure_and_leaves_nothing_behind() {
+        let root = tempfile::tempdir().unwrap();
+        let leftovers = |root: &Path| fs::read_dir(root).unwrap().count();
+
+        let mut purged = None;
+        let supported = probe_trash_with(
+            root.path(),
+            |path| fs::remove_file(path).map_err(|e| e.to_string()),
+            |path| purged = Some(path.to_path_buf()),
+        );
+        assert_eq!(supported, Ok(()));
+        assert!(purged.unwrap().starts_with(root.path()));
+        assert_eq!(leftovers(root.path()), 0);
+
+        let unsupported = probe_trash_with(
+            root.path(),
+            |_| Err("no trash directory".to_string()),
+            |_| panic!("nothing was trashed"),
+        );
+        assert_eq!(unsupported, Err("no trash directory".to_string()));
+        assert_eq!(leftovers(root.path()), 0);
+
+        // A root that can't be written to can't be probed, or cleaned up either way
+        let missing = root.path().join("missing");
+        let unknown = probe_trash_with(&missing, |_| Err("unreachable".to_string()), |_| {});
+        assert_eq!(unknown, Ok(()));
+    }
+
+    #[test]
+    fn deletion_estimate_grows_with_files_and_folders() {
+        assert_eq!(estimate_duration(0, 0), Duration::ZERO);
+        assert_eq!(estimate_duration(1, 0), Duration::from_millis(50));
+        // 900k files in 10 folders: 45s for the files and half a second for the folders
+        assert_eq!(
+            estimate_duration(10, 900_000),
+            Duration::from_millis(45_500)
+        );
+        assert!(estimate_duration(2, 1_000) < estimate_duration(2, 100_000));
+    }
+}
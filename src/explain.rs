@@ -0,0 +1,388 @@
+use crate::app::App;
+use crate::mounts;
+use crate::owners::{self, Owner};
+use crate::scanner;
+use crate::units::{SizeUnits, human_size};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Why a path was or wasn't offered as a cleanup candidate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Candidate { size_bytes: u64, days_ago: u32 },
+    OutsideRoot(PathBuf),
+    NotADirectory,
+    Excluded(PathBuf),
+    NonLocalMount(PathBuf),
+    Ignored { dir: PathBuf, pattern: String },
+    InsideMatch(PathBuf),
+    NameNotTarget(String),
+    OutsideDepthRange { depth: usize },
+    Protected(PathBuf),
+    BelowMinAge { days_ago: u32, min_days: u32 },
+    NotBeforeCutoff,
+    BelowMinSize { size_bytes: u64, min_bytes: u64 },
+    BelowMinFiles { file_count: u64, min_files: u64 },
+    Empty,
+    OtherOwner { owner: u32, wanted: u32 },
+}
+
+impl Verdict {
+    // One-line explanation, with sizes in the given units
+    pub fn describe(&self, units: SizeUnits) -> String {
+        match self {
+            Verdict::Candidate {
+                size_bytes,
+                days_ago,
+            } => format!(
+                "candidate ({}, modified {} days ago)",
+                human_size(*size_bytes, units),
+                days_ago
+            ),
+            Verdict::OutsideRoot(root) => {
+                format!("skipped: outside the scan root {}", root.display())
+            }
+            Verdict::NotADirectory => "skipped: not a directory".to_string(),
+            Verdict::Excluded(rule) => {
+                format!("skipped: excluded by path {}", rule.display())
+            }
+            Verdict::NonLocalMount(dir) => format!(
+                "skipped: {} is a network or removable mount (--local-only)",
+                dir.display()
+            ),
+            Verdict::Ignored { dir, pattern } => format!(
+                "skipped: {} matches ignore pattern '{}'",
+                dir.display(),
+                pattern
+            ),
+            Verdict::InsideMatch(dir) => format!(
+                "skipped: inside matched directory {}, which is offered instead",
+                dir.display()
+            ),
+            Verdict::NameNotTarget(name) => {
+                format!("skipped: name '{}' is not in the target list", name)
+            }
+            Verdict::OutsideDepthRange { depth } => {
+                format!(
+                    "skipped: at depth {}, outside the --min-depth/--max-depth range",
+                    depth
+                )
+            }
+            Verdict::Protected(rule) => {
+                format!("skipped: protected by {}", rule.display())
+            }
+            Verdict::BelowMinAge { days_ago, min_days } => format!(
+                "skipped: modified {} days ago, below the minimum age of {} days",
+                days_ago, min_days
+            ),
+            Verdict::NotBeforeCutoff => {
+                "skipped: modified on or after the --before date".to_string()
+            }
+            Verdict::BelowMinSize {
+                size_bytes,
+                min_bytes,
+            } => format!(
+                "skipped: size {} is below the minimum of {}",
+                human_size(*size_bytes, units),
+                human_size(*min_bytes, units)
+            ),
+            Verdict::BelowMinFiles {
+                file_count,
+                min_files,
+            } => format!(
+                "skipped: {} files is below the minimum of {}",
+                file_count, min_files
+            ),
+            Verdict::Empty => "skipped: empty, and --hide-empty is on".to_string(),
+            Verdict::OtherOwner { owner, wanted } => format!(
+                "skipped: owned by {}, not {}",
+                user_label(*owner),
+                user_label(*wanted)
+            ),
+        }
+    }
+}
+
+// A uid as its user name, where it has one
+fn user_label(uid: u32) -> String {
+    Owner {
+        uid,
+        name: owners::user_name(uid),
+    }
+    .label()
+}
+
+// Walk from the scan root down to `target`, applying the scanner's rules in the same order
+pub fn explain(app: &App, target: &Path) -> Verdict {
+    let non_local_mounts = if app.scan_config.local_only {
+        mounts::non_local_mount_points()
+    } else {
+        Vec::new()
+    };
+    explain_with_mounts(app, target, &non_local_mounts)
+}
+
+fn explain_with_mounts(app: &App, target: &Path, non_local_mounts: &[PathBuf]) -> Verdict {
+    let root = scanner::absolute_path(&app.scan_config.root);
+    let target = scanner::absolute_path(target);
+
+    let Ok(relative) = target.strip_prefix(&root) else {
+        return Verdict::OutsideRoot(root);
+    };
+    let metadata = match fs::metadata(&target) {
+        Ok(metadata) if metadata.is_dir() => metadata,
+        _ => return Verdict::NotADirectory,
+    };
+
+    let ignore_patterns =
+        scanner::compile_patterns(&app.scan_config.ignore_patterns).unwrap_or_default();
+    let target_globs = scanner::compile_patterns(&app.scan_config.target_globs).unwrap_or_default();
+    let components: Vec<_> = relative.components().collect();
+    let mut current = root.clone();
+    for (i, component) in components.iter().enumerate() {
+        current.push(component);
+        let name = component.as_os_str().to_string_lossy();
+
+        if let Some(rule) = scanner::containing_rule(&current, &app.scan_config.exclude_paths) {
+            return Verdict::Excluded(rule.clone());
+        }
+        if scanner::is_mount_point(&current, non_local_mounts) {
+            return Verdict::NonLocalMount(current);
+        }
+        let relative = current.strip_prefix(&root).unwrap_or(&current);
+        if let Some(pattern) =
+            scanner::matching_pattern(relative, &ignore_patterns, app.scan_config.match_full_path)
+        {
+            return Verdict::Ignored {
+                dir: current,
+                pattern: pattern.as_str().to_string(),
+            };
+        }
+        let is_target = i + 1 == components.len();
+        let matched =
+            scanner::matching_target(&name, &app.scan_config.folders_to_clean, &target_globs);
+        if !is_target && matched.is_some() {
+            return Verdict::InsideMatch(current);
+        }
+    }
+
+    let name = target
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let matched = scanner::matching_target(&name, &app.scan_config.folders_to_clean, &target_globs);
+    if components.is_empty() || matched.is_none() {
+        return Verdict::NameNotTarget(name);
+    }
+    if !app.scan_config.in_depth_range(components.len()) {
+        return Verdict::OutsideDepthRange {
+            depth: components.len(),
+        };
+    }
+    if let Some(rule) = scanner::containing_rule(&target, &app.scan_config.protected_paths) {
+        return Verdict::Protected(rule.clone());
+    }
+
+    let days_ago = scanner::days_since_modified(&metadata);
+    if days_ago < app.scan_config.min_age_days {
+        return Verdict::BelowMinAge {
+            days_ago,
+            min_days: app.scan_config.min_age_days,
+        };
+    }
+    if let Some(cutoff) = app.scan_config.modified_before
+        && !scanner::modified_before(&metadata, cutoff)
+    {
+        return Verdict::NotBeforeCutoff;
+    }
+    if let Some(wanted) = app.scan_config.owner_only
+        && let Some(owner) = owners::uid(&metadata)
+        && owner != wanted
+    {
+        return Verdict::OtherOwner { owner, wanted };
+    }
+    let stats = scanner::directory_stats(&target, app.scan_config.size_options);
+    let size_bytes = stats.size_bytes;
+    if size_bytes < app.scan_config.min_size_bytes {
+        return Verdict::BelowMinSize {
+            size_bytes,
+            min_bytes: app.scan_config.min_size_bytes,
+        };
+    }
+    if stats.file_count < app.scan_config.min_files {
+        return Verdict::BelowMinFiles {
+            file_count: stats.file_count,
+            min_files: app.scan_config.min_files,
+        };
+    }
+
+    if app.scan_config.hide_empty && stats.is_empty() {
+        return Verdict::Empty;
+    }
+
+    Verdict::Candidate {
+        size_bytes,
+        days_ago,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Build a scan root containing `project/node_modules` with a 10-byte file
+    fn fixture() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let modules = dir.path().join("project/node_modules");
+        fs::create_dir_all(modules.join("pkg")).unwrap();
+        fs::write(modules.join("pkg/index.js"), b"0123456789").unwrap();
+        fs::write(dir.path().join("project/README.md"), b"hi").unwrap();
+
+        let mut app = App::new();
+        app.scan_config.root = dir.path().to_path_buf();
+        (dir, app)
+    }
+
+    #[test]
+    fn reports_candidate() {
+        let (dir, app) = fixture();
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(
+            verdict,
+            Verdict::Candidate {
+                size_bytes: 10,
+                days_ago: 0
+            }
+        );
+    }
+
+    #[test]
+    fn reports_outside_root() {
+        let (dir, app) = fixture();
+        let verdict = explain(&app, dir.path().parent().unwrap());
+        assert!(matches!(verdict, Verdict::OutsideRoot(_)));
+    }
+
+    #[test]
+    fn reports_not_a_directory() {
+        let (dir, app) = fixture();
+        let verdict = explain(&app, &dir.path().join("project/README.md"));
+        assert_eq!(verdict, Verdict::NotADirectory);
+    }
+
+    #[test]
+    fn reports_name_not_in_targets() {
+        let (dir, app) = fixture();
+        let verdict = explain(&app, &dir.path().join("project"));
+        assert_eq!(verdict, Verdict::NameNotTarget("project".to_string()));
+    }
+
+    #[test]
+    fn reports_ignore_pattern() {
+        let (dir, app) = fixture();
+        let hidden = dir.path().join(".cache/node_modules");
+        fs::create_dir_all(&hidden).unwrap();
+        let verdict = explain(&app, &hidden);
+        assert_eq!(
+            verdict,
+            Verdict::Ignored {
+                dir: dir.path().join(".cache"),
+                pattern: ".*".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn reports_excluded_path() {
+        let (dir, mut app) = fixture();
+        app.scan_config.exclude_paths = vec![dir.path().join("project")];
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(verdict, Verdict::Excluded(dir.path().join("project")));
+    }
+
+    #[test]
+    fn reports_non_local_mount() {
+        let (dir, mut app) = fixture();
+        app.scan_config.local_only = true;
+        let mount = dir.path().join("project");
+        let verdict = explain_with_mounts(
+            &app,
+            &dir.path().join("project/node_modules"),
+            std::slice::from_ref(&mount),
+        );
+        assert_eq!(verdict, Verdict::NonLocalMount(mount));
+    }
+
+    #[test]
+    fn reports_inside_match() {
+        let (dir, app) = fixture();
+        let verdict = explain(&app, &dir.path().join("project/node_modules/pkg"));
+        assert_eq!(
+            verdict,
+            Verdict::InsideMatch(dir.path().join("project/node_modules"))
+        );
+    }
+
+    #[test]
+    fn reports_protected() {
+        let (dir, mut app) = fixture();
+        app.scan_config.protected_paths = vec![dir.path().join("project")];
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(verdict, Verdict::Protected(dir.path().join("project")));
+    }
+
+    #[test]
+    fn reports_below_min_age() {
+        let (dir, mut app) = fixture();
+        app.scan_config.min_age_days = 7;
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(
+            verdict,
+            Verdict::BelowMinAge {
+                days_ago: 0,
+                min_days: 7
+            }
+        );
+    }
+
+    #[test]
+    fn reports_not_before_cutoff() {
+        let (dir, mut app) = fixture();
+        app.scan_config.modified_before = Some(crate::cli::parse_date("2000-01-01").unwrap());
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(verdict, Verdict::NotBeforeCutoff);
+    }
+
+    #[test]
+    fn reports_below_min_size() {
+        let (dir, mut app) = fixture();
+        app.scan_config.min_size_bytes = 1024;
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(
+            verdict,
+            Verdict::BelowMinSize {
+                size_bytes: 10,
+                min_bytes: 1024
+            }
+        );
+    }
+
+    #[test]
+    fn reports_below_min_files() {
+        let (dir, mut app) = fixture();
+        app.scan_config.min_files = 2;
+        let verdict = explain(&app, &dir.path().join("project/node_modules"));
+        assert_eq!(
+            verdict,
+            Verdict::BelowMinFiles {
+                file_count: 1,
+                min_files: 2
+            }
+        );
+        assert_eq!(
+            verdict.describe(SizeUnits::Binary),
+            "skipped: 1 files is below the minimum of 2"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::app::DirInfo;
+use crate::delete::{self, DeleteMode, TrashFailure};
+use crate::scanner;
+use crate::stats;
+use crate::units::{SizeUnits, human_size};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const REPORT_HEADER: &str = "timestamp,directory,folders_deleted,bytes_freed";
+
+// One cleanup recorded in the CSV audit report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportRow {
+    pub timestamp: String,
+    pub directory: PathBuf,
+    pub folders_deleted: usize,
+    pub bytes_freed: u64,
+}
+
+impl ReportRow {
+    pub fn now(directory: &Path, folders_deleted: usize, bytes_freed: u64) -> Self {
+        ReportRow {
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            directory: directory.to_path_buf(),
+            folders_deleted,
+            bytes_freed,
+        }
+    }
+}
+
+// Quote a CSV field if it contains a delimiter, quote or line break
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Append a row to the report, writing the header first if the file is new or empty
+pub fn append_report_row(file: &Path, row: &ReportRow) -> io::Result<()> {
+    let mut out = OpenOptions::new().create(true).append(true).open(file)?;
+    if out.metadata()?.len() == 0 {
+        writeln!(out, "{}", REPORT_HEADER)?;
+    }
+    writeln!(
+        out,
+        "{},{},{},{}",
+        csv_field(&row.timestamp),
+        csv_field(&row.directory.to_string_lossy()),
+        row.folders_deleted,
+        row.bytes_freed
+    )
+}
+
+// Quote a path for a POSIX shell: plain single quotes for printable text, ANSI-C `$'...'`
+// escapes when it holds control characters or bytes that aren't UTF-8
+pub fn shell_quote(path: &Path) -> String {
+    let bytes = path.as_os_str().as_encoded_bytes();
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(char::is_control) => {
+            format!("'{}'", text.replace('\'', "'\\''"))
+        }
+        _ => {
+            let mut quoted = String::from("$'");
+            for &byte in bytes {
+                match byte {
+                    b'\'' | b'\\' => {
+                        quoted.push('\\');
+                        quoted.push(byte as char);
+                    }
+                    0x20..=0x7e => quoted.push(byte as char),
+                    _ => quoted.push_str(&format!("\\x{:02x}", byte)),
+                }
+            }
+            quoted.push('\'');
+            quoted
+        }
+    }
+}
+
+// A shell script that cleans up `dirs` the way `mode` would, to review and run later
+pub fn cleanup_script(
+    dirs: &[DirInfo],
+    mode: &DeleteMode,
+    root: &Path,
+    units: SizeUnits,
+) -> String {
+    let total = stats::reclaimable_bytes(dirs);
+    let mut script = format!(
+        "#!/bin/sh\n# Generated by disk-cleaner on {} for {}\n# {} folders, {}\n",
+        chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        root.display(),
+        dirs.len(),
+        human_size(total, units)
+    );
+    let archive_batch = match mode {
+        DeleteMode::Archive(dir) => Some(delete::archive_batch(dir)),
+        _ => None,
+    };
+    let root = scanner::absolute_path(root);
+    for dir in dirs {
+        let path = shell_quote(&dir.path);
+        let command = match mode {
+            DeleteMode::Trash(TrashFailure::Report) => format!("trash-put -- {}", path),
+            DeleteMode::Trash(TrashFailure::DeletePermanently) => {
+                format!("trash-put -- {} || rm -rf -- {}", path, path)
+            }
+            DeleteMode::Permanent => format!("rm -rf -- {}", path),
+            DeleteMode::Archive(_) => {
+                let batch = archive_batch.as_deref().unwrap_or(Path::new("."));
+                let destination = batch.join(delete::relative_to_root(&dir.path, &root));
+                let parent = destination.parent().unwrap_or(batch);
+                format!(
+                    "mkdir -p -- {} && mv -- {} {}",
+                    shell_quote(parent),
+                    path,
+                    shell_quote(&destination)
+                )
+            }
+        };
+        script.push_str(&format!(
+            "\n# {}, modified {} days ago\n{}\n",
+            human_size(dir.size_bytes, units),
+            dir.modified_days_ago,
+            command
+        ));
+    }
+    script
+}
+
+// Write the script, executable where that means something
+pub fn write_script(file: &Path, script: &str) -> io::Result<()> {
+    fs::write(file, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(file, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_paths_with_commas_and_quotes() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("report.csv");
+        let row = ReportRow {
+            timestamp: "2024-05-01T10:00:00+00:00".to_string(),
+            directory: PathBuf::from("/home/me/a,b \"quoted\""),
+            folders_deleted: 3,
+            bytes_freed: 4096,
+        };
+
+        append_report_row(&report, &row).unwrap();
+        append_report_row(&report, &row).unwrap();
+
+        let contents = fs::read_to_string(&report).unwrap();
+        let expected_row = "2024-05-01T10:00:00+00:00,\"/home/me/a,b \"\"quoted\"\"\",3,4096";
+        assert_eq!(
+            contents,
+            format!("{}\n{}\n{}\n", REPORT_HEADER, expected_row, expected_row)
+        );
+    }
+
+    #[test]
+    fn leaves_plain_fields_unquoted() {
+        assert_eq!(csv_field("/tmp/projects"), "/tmp/projects");
+    }
+
+    fn dir_info(path: &str) -> DirInfo {
+        DirInfo {
+            modified_days_ago: 40,
+            selected: true,
+            size_bytes: 2048,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    #[test]
+    fn shell_quote_escapes_spaces_and_special_characters() {
+        assert_eq!(
+            shell_quote(Path::new("/p/my app/target")),
+            "'/p/my app/target'"
+        );
+        assert_eq!(
+            shell_quote(Path::new("/p/it's $HOME `x`/target")),
+            r"'/p/it'\''s $HOME `x`/target'"
+        );
+        assert_eq!(
+            shell_quote(Path::new("/p/two\nlines/target")),
+            r"$'/p/two\x0alines/target'"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_quote_escapes_bytes_that_are_not_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"/p/caf\xe9's/target"));
+        assert_eq!(shell_quote(path), r"$'/p/caf\xe9\'s/target'");
+    }
+
+    #[test]
+    fn script_has_one_quoted_command_per_directory() {
+        let dirs = [
+            dir_info("/p/my app/target"),
+            dir_info("/p/a;b/node_modules"),
+        ];
+        let script = cleanup_script(
+            &dirs,
+            &DeleteMode::Permanent,
+            Path::new("/p"),
+            SizeUnits::Binary,
+        );
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("# 2 folders, 4 KiB\n"));
+        let commands: Vec<&str> = script
+            .lines()
+            .filter(|l| !l.starts_with('#') && !l.is_empty())
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                "rm -rf -- '/p/my app/target'",
+                "rm -rf -- '/p/a;b/node_modules'"
+            ]
+        );
+
+        let trash = cleanup_script(
+            &dirs[..1],
+            &DeleteMode::default(),
+            Path::new("/p"),
+            SizeUnits::Binary,
+        );
+        assert!(trash.contains("\ntrash-put -- '/p/my app/target'\n"));
+
+        let archive = cleanup_script(
+            &dirs[..1],
+            &DeleteMode::Archive(PathBuf::from("/old")),
+            Path::new("/p"),
+            SizeUnits::Binary,
+        );
+        let command = archive.lines().last().unwrap();
+        assert!(command.starts_with("mkdir -p -- '/old/disk-cleaner-"));
+        assert!(command.contains("/my app' && mv -- '/p/my app/target' '/old/disk-cleaner-"));
+        assert!(command.ends_with("/my app/target'"));
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+use std::process::Command;
+
+// Where a directory stands in git, as far as cleaning it up goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeState {
+    NotARepo,
+    Clean,
+    // Number of changed or untracked entries `git status` lists
+    Dirty(usize),
+}
+
+// Ask git about the working tree `dir` is in. Errors when git itself can't be run.
+pub fn tree_state(dir: &Path) -> Result<TreeState, String> {
+    let inside = git(dir, &["rev-parse", "--is-inside-work-tree"])?;
+    if !inside.status.success() || String::from_utf8_lossy(&inside.stdout).trim() != "true" {
+        return Ok(TreeState::NotARepo);
+    }
+    let status = git(dir, &["status", "--porcelain"])?;
+    if !status.status.success() {
+        return Err(String::from_utf8_lossy(&status.stderr).trim().to_string());
+    }
+    match String::from_utf8_lossy(&status.stdout).lines().count() {
+        0 => Ok(TreeState::Clean),
+        changes => Ok(TreeState::Dirty(changes)),
+    }
+}
+
+// Why `--require-clean-git` won't let `root` be cleaned, if it won't
+pub fn cleanup_blocker(root: &Path) -> Option<String> {
+    match tree_state(root) {
+        Ok(TreeState::NotARepo | TreeState::Clean) => None,
+        Ok(TreeState::Dirty(changes)) => Some(format!(
+            "Not cleaning: {} has {} uncommitted {} (--require-clean-git)",
+            root.display(),
+            changes,
+            if changes == 1 { "change" } else { "changes" }
+        )),
+        Err(err) => Some(format!(
+            "Not cleaning: could not check git status of {}: {}",
+            root.display(),
+            err
+        )),
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<std::process::Output, String> {
+    Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|err| format!("could not run git: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A repository with one committed file
+    fn committed_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        for args in [
+            &["init", "-q"][..],
+            &["add", "main.rs"],
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "-m",
+                "initial",
+            ],
+        ] {
+            assert!(git(dir.path(), args).unwrap().status.success());
+        }
+        dir
+    }
+
+    #[test]
+    fn tells_clean_and_dirty_trees_apart() {
+        let repo = committed_repo();
+        assert_eq!(tree_state(repo.path()), Ok(TreeState::Clean));
+        assert_eq!(cleanup_blocker(repo.path()), None);
+
+        fs::write(repo.path().join("main.rs"), "fn main() { todo!() }\n").unwrap();
+        fs::write(repo.path().join("new.rs"), "").unwrap();
+        assert_eq!(tree_state(repo.path()), Ok(TreeState::Dirty(2)));
+        let blocker = cleanup_blocker(repo.path()).unwrap();
+        assert!(blocker.contains("2 uncommitted changes"));
+    }
+
+    #[test]
+    fn ignored_build_output_does_not_count_as_a_change() {
+        let repo = committed_repo();
+        fs::write(repo.path().join(".git/info/exclude"), "target/\n").unwrap();
+        fs::create_dir_all(repo.path().join("target/debug")).unwrap();
+        fs::write(repo.path().join("target/debug/app"), "binary").unwrap();
+        assert_eq!(tree_state(repo.path()), Ok(TreeState::Clean));
+    }
+
+    #[test]
+    fn directories_outside_git_are_not_blocked() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(tree_state(dir.path()), Ok(TreeState::NotARepo));
+        assert_eq!(cleanup_blocker(dir.path()), None);
+    }
+}
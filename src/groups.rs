@@ -0,0 +1,259 @@
+use crate::app::DirInfo;
+use crate::stats;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// How the results list is organised
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GroupMode {
+    // One row per match (the default)
+    #[default]
+    Flat,
+    // Matches gathered under the directory that contains them
+    ByProject,
+}
+
+impl GroupMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            GroupMode::Flat => GroupMode::ByProject,
+            GroupMode::ByProject => GroupMode::Flat,
+        }
+    }
+}
+
+// Matches sharing a project directory, as indices into the results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group {
+    pub project: PathBuf,
+    pub members: Vec<usize>,
+}
+
+impl Group {
+    pub fn all_selected(&self, dirs: &[DirInfo]) -> bool {
+        self.members.iter().all(|&i| dirs[i].selected)
+    }
+
+    pub fn size_bytes(&self, dirs: &[DirInfo]) -> u64 {
+        stats::reclaimable_bytes(self.members.iter().map(|&i| &dirs[i]))
+    }
+}
+
+// Matches expanded inline, with the sizes of their immediate subdirectories
+pub type Expanded = HashMap<PathBuf, Vec<(PathBuf, u64)>>;
+
+// One line of the results list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    // Header for `groups[index]`
+    Group(usize),
+    // `dirs_to_clean[index]`
+    Dir(usize),
+    // Subdirectory `child` of the expanded `dirs_to_clean[dir]`, one level deeper
+    Child { dir: usize, child: usize },
+}
+
+// The rows to show and the groups they refer to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub groups: Vec<Group>,
+    pub rows: Vec<Row>,
+}
+
+impl Layout {
+    // With `hide_unselected`, only selected matches (and groups containing any) get a row
+    pub fn build(
+        dirs: &[DirInfo],
+        mode: GroupMode,
+        collapsed: &HashSet<PathBuf>,
+        expanded: &Expanded,
+        hide_unselected: bool,
+    ) -> Self {
+        let visible = |i: usize| !hide_unselected || dirs[i].selected;
+        // A match's row, followed by its subdirectories when expanded
+        let dir_rows = |i: usize| {
+            let children = expanded.get(&dirs[i].path).map_or(0, Vec::len);
+            std::iter::once(Row::Dir(i))
+                .chain((0..children).map(move |child| Row::Child { dir: i, child }))
+        };
+        match mode {
+            GroupMode::Flat => Layout {
+                groups: Vec::new(),
+                rows: (0..dirs.len())
+                    .filter(|&i| visible(i))
+                    .flat_map(dir_rows)
+                    .collect(),
+            },
+            GroupMode::ByProject => {
+                let groups = group_by_project(dirs);
+                let mut rows = Vec::new();
+                for (index, group) in groups.iter().enumerate() {
+                    if !group.members.iter().any(|&i| visible(i)) {
+                        continue;
+                    }
+                    rows.push(Row::Group(index));
+                    if !collapsed.contains(&group.project) {
+                        rows.extend(
+                            group
+                                .members
+                                .iter()
+                                .filter(|&&i| visible(i))
+                                .flat_map(|&i| dir_rows(i)),
+                        );
+                    }
+                }
+                Layout { groups, rows }
+            }
+        }
+    }
+
+    // Row showing the given group's header
+    pub fn group_row(&self, group: usize) -> Option<usize> {
+        self.rows.iter().position(|row| *row == Row::Group(group))
+    }
+
+    // Row showing the given match
+    pub fn dir_row(&self, dir: usize) -> Option<usize> {
+        self.rows.iter().position(|row| *row == Row::Dir(dir))
+    }
+
+    // Group containing the given directory
+    pub fn group_of(&self, dir: usize) -> Option<usize> {
+        self.groups.iter().position(|g| g.members.contains(&dir))
+    }
+}
+
+// The directory a match lives in, standing in for its project root
+pub fn project_of(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+// Group matches by project, in the order each project first appears
+pub fn group_by_project(dirs: &[DirInfo]) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    for (index, dir) in dirs.iter().enumerate() {
+        let project = project_of(&dir.path);
+        match groups.iter_mut().find(|g| g.project == project) {
+            Some(group) => group.members.push(index),
+            None => groups.push(Group {
+                project,
+                members: vec![index],
+            }),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(path: &str, size_bytes: u64, selected: bool) -> DirInfo {
+        DirInfo {
+            selected,
+            size_bytes,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    fn dirs() -> Vec<DirInfo> {
+        vec![
+            dir("/repo/app/target", 10, true),
+            dir("/repo/web/node_modules", 20, false),
+            dir("/repo/app/node_modules", 30, true),
+        ]
+    }
+
+    #[test]
+    fn groups_matches_by_containing_directory_in_first_seen_order() {
+        let groups = group_by_project(&dirs());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].project, PathBuf::from("/repo/app"));
+        assert_eq!(groups[0].members, vec![0, 2]);
+        assert_eq!(groups[1].project, PathBuf::from("/repo/web"));
+        assert_eq!(groups[1].members, vec![1]);
+        assert_eq!(groups[0].size_bytes(&dirs()), 40);
+        assert!(groups[0].all_selected(&dirs()));
+        assert!(!groups[1].all_selected(&dirs()));
+    }
+
+    #[test]
+    fn flat_layout_has_one_row_per_match() {
+        let layout = Layout::build(
+            &dirs(),
+            GroupMode::Flat,
+            &HashSet::new(),
+            &Expanded::new(),
+            false,
+        );
+        assert!(layout.groups.is_empty());
+        assert_eq!(layout.rows, vec![Row::Dir(0), Row::Dir(1), Row::Dir(2)]);
+    }
+
+    #[test]
+    fn collapsed_groups_hide_their_members() {
+        let collapsed = HashSet::from([PathBuf::from("/repo/app")]);
+        let layout = Layout::build(
+            &dirs(),
+            GroupMode::ByProject,
+            &collapsed,
+            &Expanded::new(),
+            false,
+        );
+        assert_eq!(layout.rows, vec![Row::Group(0), Row::Group(1), Row::Dir(1)]);
+        assert_eq!(layout.group_row(1), Some(1));
+        assert_eq!(layout.group_of(2), Some(0));
+    }
+
+    #[test]
+    fn hiding_unselected_drops_their_rows_and_empty_groups() {
+        let flat = Layout::build(
+            &dirs(),
+            GroupMode::Flat,
+            &HashSet::new(),
+            &Expanded::new(),
+            true,
+        );
+        assert_eq!(flat.rows, vec![Row::Dir(0), Row::Dir(2)]);
+
+        let grouped = Layout::build(
+            &dirs(),
+            GroupMode::ByProject,
+            &HashSet::new(),
+            &Expanded::new(),
+            true,
+        );
+        assert_eq!(grouped.rows, vec![Row::Group(0), Row::Dir(0), Row::Dir(2)]);
+    }
+
+    #[test]
+    fn expanded_matches_list_their_subdirectories_below_them() {
+        let expanded = Expanded::from([(
+            PathBuf::from("/repo/app/target"),
+            vec![
+                (PathBuf::from("/repo/app/target/debug"), 8),
+                (PathBuf::from("/repo/app/target/release"), 2),
+            ],
+        )]);
+        let layout = Layout::build(&dirs(), GroupMode::Flat, &HashSet::new(), &expanded, false);
+        assert_eq!(
+            layout.rows,
+            vec![
+                Row::Dir(0),
+                Row::Child { dir: 0, child: 0 },
+                Row::Child { dir: 0, child: 1 },
+                Row::Dir(1),
+                Row::Dir(2)
+            ]
+        );
+        assert_eq!(layout.dir_row(1), Some(3));
+
+        // Folding the group hides the children along with the match
+        let collapsed = HashSet::from([PathBuf::from("/repo/app")]);
+        let grouped = Layout::build(&dirs(), GroupMode::ByProject, &collapsed, &expanded, false);
+        assert_eq!(
+            grouped.rows,
+            vec![Row::Group(0), Row::Group(1), Row::Dir(1)]
+        );
+    }
+}
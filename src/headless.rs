@@ -0,0 +1,782 @@
+use crate::app::{self, App, DirInfo, ScanUpdate};
+use crate::delete::{self, DeletionReport};
+use crate::export;
+use crate::plan::Plan;
+use crate::stats;
+use crate::tree;
+use crate::units::{SizeUnits, human_size};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::path::Path;
+
+// Orders matches by size, so the heap below can drop the smallest
+#[derive(Debug, Clone)]
+struct BySize(DirInfo);
+
+impl PartialEq for BySize {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for BySize {}
+
+impl PartialOrd for BySize {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BySize {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .size_bytes
+            .cmp(&other.0.size_bytes)
+            .then_with(|| other.0.path.cmp(&self.0.path))
+    }
+}
+
+// Running totals and the `k` largest matches seen so far. Memory stays bounded by `k`
+// however many matches are pushed, so reports don't need the full result list.
+#[derive(Debug, Clone)]
+pub struct TopK {
+    k: usize,
+    // Min-heap, so the smallest kept match is the one to drop
+    heap: BinaryHeap<Reverse<BySize>>,
+    pub count: usize,
+    pub total_bytes: u64,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+            count: 0,
+            total_bytes: 0,
+        }
+    }
+
+    pub fn push(&mut self, dir: DirInfo) {
+        self.count += 1;
+        self.total_bytes += dir.size_bytes;
+        self.heap.push(Reverse(BySize(dir)));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    // The kept matches, largest first
+    pub fn into_sorted(self) -> Vec<DirInfo> {
+        let mut dirs: Vec<DirInfo> = self.heap.into_iter().map(|Reverse(d)| d.0).collect();
+        dirs.sort_by(|a, b| a.path.cmp(&b.path));
+        app::sort_by_size_desc(&mut dirs);
+        dirs
+    }
+}
+
+// How headless modes list matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListFormat {
+    // One line per match
+    #[default]
+    Flat,
+    // Indented by path, with totals for the directories in between
+    Tree,
+    // One line per directory containing matches, with their count and total
+    ByParent,
+}
+
+// List matches in the app's chosen format
+fn format_listing(app: &App, dirs: &[DirInfo]) -> String {
+    match app.list_format {
+        ListFormat::Flat => format_entries(dirs, app.size_units),
+        ListFormat::Tree => tree::render(&tree::build(dirs, &app.scan_config.root), app.size_units),
+        ListFormat::ByParent => format_parent_totals(dirs, app.size_units),
+    }
+}
+
+// One line per parent directory, e.g. "/src/project-a: 3 dirs, 4.1 GiB"
+pub fn format_parent_totals(dirs: &[DirInfo], units: SizeUnits) -> String {
+    stats::totals_by_parent(dirs)
+        .iter()
+        .map(|total| {
+            format!(
+                "{}: {} {}, {}\n",
+                total.parent.display(),
+                total.count,
+                if total.count == 1 { "dir" } else { "dirs" },
+                human_size(total.size_bytes, units)
+            )
+        })
+        .collect()
+}
+
+// Render one line per entry: size, then path
+pub fn format_entries(dirs: &[DirInfo], units: SizeUnits) -> String {
+    dirs.iter()
+        .map(|dir| {
+            format!(
+                "{:>10}  {}\n",
+                human_size(dir.size_bytes, units),
+                dir.path.display()
+            )
+        })
+        .collect()
+}
+
+// Exit codes for `--headless`, so scripts can tell "did work" from "nothing to do"
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_ERRORS: i32 = 1;
+pub const EXIT_NOTHING_FOUND: i32 = 3;
+
+// What a headless run printed and how the process should exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub output: String,
+    pub exit_code: i32,
+}
+
+// Scan, list the directories that would be selected and, with `delete`, trash them
+pub fn run(app: &mut App, delete: bool) -> Outcome {
+    if delete {
+        app.check_trash_support();
+    }
+    let mode = app.delete_mode.clone();
+    let tools = app.clean_tools.clone();
+    let root = app.scan_config.root.clone();
+    run_with(app, delete, |dirs| {
+        delete::remove_dirs(dirs, &mode, &tools, &root, |_, _| {})
+    })
+}
+
+fn run_with(
+    app: &mut App,
+    delete: bool,
+    trash: impl FnOnce(&[DirInfo]) -> DeletionReport,
+) -> Outcome {
+    app.run_scan_blocking();
+    if delete && app.refresh_sizes_before_delete {
+        app.refresh_selected_sizes();
+    }
+    let selected: Vec<DirInfo> = app
+        .dirs_to_clean
+        .iter()
+        .filter(|d| d.selected)
+        .cloned()
+        .collect();
+    if selected.is_empty() {
+        return Outcome {
+            output: stamp(app, "Nothing to clean\n"),
+            exit_code: EXIT_NOTHING_FOUND,
+        };
+    }
+
+    let mut output = stamp(app, &format_listing(app, &selected));
+    if !delete {
+        return Outcome {
+            output,
+            exit_code: EXIT_OK,
+        };
+    }
+    if let Some(reason) = app.cleanup_blocker() {
+        output.push_str(&stamp(app, &format!("{}\n", reason)));
+        return Outcome {
+            output,
+            exit_code: EXIT_ERRORS,
+        };
+    }
+
+    let report = trash(&selected);
+    app.write_report(report.succeeded.len(), report.bytes_freed);
+    output.push_str(&stamp(app, &deletion_lines(app, &report)));
+    let exit_code = deletion_exit_code(&report, selected.len(), false);
+    Outcome { output, exit_code }
+}
+
+// `EXIT_ERRORS` when anything failed or was `skipped`, even if nothing else was freed;
+// `EXIT_NOTHING_FOUND` only when every one of the `selected` folders was already gone
+fn deletion_exit_code(report: &DeletionReport, selected: usize, skipped: bool) -> i32 {
+    if skipped
+        || !report.failed.is_empty()
+        || report.attempted() < selected - report.already_gone.len()
+    {
+        EXIT_ERRORS
+    } else if report.succeeded.is_empty() {
+        EXIT_NOTHING_FOUND
+    } else {
+        EXIT_OK
+    }
+}
+
+// What didn't get removed, then the totals
+fn deletion_lines(app: &App, report: &DeletionReport) -> String {
+    let mut lines: String = report
+        .failure_lines()
+        .iter()
+        .map(|failure| format!("Could not remove {}\n", failure))
+        .collect();
+    lines.push_str(&format!("{}\n", report.summary(app.size_units)));
+    lines
+}
+
+// With `--timestamps`, start each line with the time it was written, so log aggregators
+// can put the events in order
+fn stamp(app: &App, text: &str) -> String {
+    if !app.timestamps {
+        return text.to_string();
+    }
+    let now = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    text.lines()
+        .map(|line| format!("{} {}\n", now, line))
+        .collect()
+}
+
+// Scan and write a shell script that would clean up the selected directories,
+// without touching them
+pub fn emit_script(app: &mut App, file: &Path) -> Outcome {
+    app.run_scan_blocking();
+    let selected: Vec<DirInfo> = app
+        .dirs_to_clean
+        .iter()
+        .filter(|d| d.selected)
+        .cloned()
+        .collect();
+    if selected.is_empty() {
+        return Outcome {
+            output: "Nothing to clean\n".to_string(),
+            exit_code: EXIT_NOTHING_FOUND,
+        };
+    }
+
+    let script = export::cleanup_script(
+        &selected,
+        &app.delete_mode,
+        &app.scan_config.root,
+        app.size_units,
+    );
+    match export::write_script(file, &script) {
+        Ok(()) => Outcome {
+            output: format!(
+                "Wrote commands for {} folders to {}\n",
+                selected.len(),
+                file.display()
+            ),
+            exit_code: EXIT_OK,
+        },
+        Err(err) => Outcome {
+            output: format!("Could not write script {}: {}\n", file.display(), err),
+            exit_code: EXIT_ERRORS,
+        },
+    }
+}
+
+// Scan and write every match to a plan file, marked for deletion when it's selected,
+// for someone to review before `apply_plan`
+pub fn write_plan(app: &mut App, file: &Path) -> Outcome {
+    app.run_scan_blocking();
+    if app.dirs_to_clean.is_empty() {
+        return Outcome {
+            output: "Nothing to clean\n".to_string(),
+            exit_code: EXIT_NOTHING_FOUND,
+        };
+    }
+
+    let plan = Plan::from_dirs(&app.scan_config.root, &app.dirs_to_clean, app.size_units);
+    let marked = plan.candidates.iter().filter(|c| c.delete).count();
+    match plan.write(file) {
+        Ok(()) => Outcome {
+            output: format!(
+                "Wrote a plan for {} folders ({} marked for deletion) to {}\n",
+                plan.candidates.len(),
+                marked,
+                file.display()
+            ),
+            exit_code: EXIT_OK,
+        },
+        Err(err) => Outcome {
+            output: format!("Could not write plan {}: {}\n", file.display(), err),
+            exit_code: EXIT_ERRORS,
+        },
+    }
+}
+
+// Remove what a reviewed plan approves, skipping anything that's gone or moved since
+pub fn apply_plan(app: &mut App, file: &Path) -> Outcome {
+    let plan = match Plan::load(file) {
+        Ok(plan) => plan,
+        Err(err) => {
+            return Outcome {
+                output: format!("{}\n", err),
+                exit_code: EXIT_ERRORS,
+            };
+        }
+    };
+    app.scan_config.root = plan.root.clone();
+    app.check_trash_support();
+    let (ready, skipped) = plan.approved(app.scan_config.size_options);
+    let skipped_lines: String = skipped
+        .iter()
+        .map(|(path, reason)| format!("Skipped {}: {}\n", path.display(), reason))
+        .collect();
+    let mut output = stamp(app, &skipped_lines);
+    if ready.is_empty() {
+        output.push_str(&stamp(app, "Nothing to clean\n"));
+        return Outcome {
+            output,
+            exit_code: EXIT_NOTHING_FOUND,
+        };
+    }
+    if let Some(reason) = app.cleanup_blocker() {
+        output.push_str(&stamp(app, &format!("{}\n", reason)));
+        return Outcome {
+            output,
+            exit_code: EXIT_ERRORS,
+        };
+    }
+
+    let report = delete::remove_dirs(
+        &ready,
+        &app.delete_mode,
+        &app.clean_tools,
+        &plan.root,
+        |_, _| {},
+    );
+    app.write_report(report.succeeded.len(), report.bytes_freed);
+    output.push_str(&stamp(app, &deletion_lines(app, &report)));
+    let exit_code = deletion_exit_code(&report, ready.len(), !skipped.is_empty());
+    Outcome { output, exit_code }
+}
+
+// Scan and print the `n` largest matches. Results stream straight into a `TopK` instead
+// of the app's list, so memory doesn't grow with the number of matches.
+pub fn top(app: &mut App, n: usize, order: Option<app::SortOrder>) -> String {
+    // Ranking needs every size as the match arrives
+    app.scan_config.concurrent_sizes = false;
+    app.start_scan();
+    let mut top = TopK::new(n);
+    while let Some(receiver) = &app.scan_receiver {
+        match receiver.recv() {
+            Ok(ScanUpdate::Result(dir)) => top.push(dir),
+            Ok(ScanUpdate::Skipped(path, reason)) => app.scan_warnings.push((path, reason)),
+            Ok(ScanUpdate::Done) | Err(_) => app.scan_receiver = None,
+            Ok(_) => {}
+        }
+    }
+    let (count, total_bytes) = (top.count, top.total_bytes);
+    let mut dirs = top.into_sorted();
+    // The largest are kept either way; `--sort` only changes how they're listed
+    if let Some(order) = order {
+        app::sort_dirs(&mut dirs, order);
+    }
+    let mut output = format_listing(app, &dirs);
+    output.push_str(&format!(
+        "{} matches, {} in total\n",
+        count,
+        human_size(total_bytes, app.size_units)
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::delete::DeleteMode;
+    use std::fs;
+
+    fn dir(path: &str, size_bytes: u64) -> DirInfo {
+        DirInfo {
+            size_bytes,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    // The `n` largest of `dirs`, largest first
+    fn top_entries(dirs: &[DirInfo], n: usize) -> Vec<DirInfo> {
+        let mut top = TopK::new(n);
+        for dir in dirs {
+            top.push(dir.clone());
+        }
+        top.into_sorted()
+    }
+
+    #[test]
+    fn top_entries_are_sorted_and_truncated() {
+        let dirs = vec![dir("small", 10), dir("large", 3000), dir("medium", 200)];
+        let top = top_entries(&dirs, 2);
+        let paths: Vec<&str> = top.iter().map(|d| d.path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["large", "medium"]);
+        assert_eq!(top_entries(&dirs, 10).len(), 3);
+    }
+
+    #[test]
+    fn top_k_keeps_totals_in_bounded_memory() {
+        let k = 10;
+        let mut top = TopK::new(k);
+        for i in 0..100_000u64 {
+            // Sizes in a scrambled order, so the largest don't simply arrive last
+            let size = (i * 7919) % 100_000;
+            top.push(dir(&format!("/p/{}/target", i), size));
+            assert!(top.heap.capacity() <= k + 1);
+        }
+
+        assert_eq!(top.count, 100_000);
+        assert_eq!(top.total_bytes, (0..100_000u64).sum::<u64>());
+        let sizes: Vec<u64> = top.into_sorted().iter().map(|d| d.size_bytes).collect();
+        assert_eq!(sizes, (99_990..100_000).rev().collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn top_scans_and_prints_largest_first() {
+        let root = tempfile::tempdir().unwrap();
+        for (project, size) in [("a", 100), ("b", 5000), ("c", 2000)] {
+            let target = root.path().join(project).join("target");
+            fs::create_dir_all(&target).unwrap();
+            fs::write(target.join("blob"), vec![0u8; size]).unwrap();
+        }
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        let output = top(&mut app, 2, None);
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(&root.path().join("b/target").display().to_string()));
+        assert!(lines[1].ends_with(&root.path().join("c/target").display().to_string()));
+        assert!(lines[0].trim_start().starts_with("4 KiB"));
+        assert!(lines[2].starts_with("3 matches, "));
+    }
+
+    // A scan root with one old and one fresh `target`; only the old one is auto-selected
+    fn fixture() -> (tempfile::TempDir, App) {
+        let root = tempfile::tempdir().unwrap();
+        for project in ["old", "new"] {
+            let target = root.path().join(project).join("target");
+            fs::create_dir_all(&target).unwrap();
+            fs::write(target.join("blob"), vec![0u8; 100]).unwrap();
+        }
+        let old = root.path().join("old/target");
+        let mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(&old, mtime).unwrap();
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        (root, app)
+    }
+
+    #[test]
+    fn lists_selected_matches_without_deleting() {
+        let (root, mut app) = fixture();
+        let outcome = run_with(&mut app, false, |_| panic!("must not delete"));
+        assert_eq!(outcome.exit_code, EXIT_OK);
+        assert_eq!(outcome.output.lines().count(), 1);
+        assert!(
+            outcome
+                .output
+                .contains(&root.path().join("old/target").display().to_string())
+        );
+    }
+
+    #[test]
+    fn dirty_git_tree_skips_deletion_when_required() {
+        let (root, mut app) = fixture();
+        app.require_clean_git = true;
+        let init = std::process::Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(root.path())
+            .status()
+            .unwrap();
+        assert!(init.success());
+
+        // The untracked project files count as uncommitted work
+        let outcome = run_with(&mut app, true, |_| panic!("must not delete"));
+        assert_eq!(outcome.exit_code, EXIT_ERRORS);
+        assert!(outcome.output.contains("uncommitted"));
+        assert!(outcome.output.contains("--require-clean-git"));
+        assert!(root.path().join("old/target").exists());
+
+        // Listing alone is still allowed
+        let outcome = run_with(&mut app, false, |_| panic!("must not delete"));
+        assert_eq!(outcome.exit_code, EXIT_OK);
+    }
+
+    #[test]
+    fn exit_code_reflects_what_was_freed() {
+        let (_root, mut app) = fixture();
+        let outcome = run_with(&mut app, true, |dirs| DeletionReport {
+            succeeded: dirs.iter().map(|d| d.path.clone()).collect(),
+            failed: Vec::new(),
+            already_gone: Vec::new(),
+            bytes_freed: 100,
+        });
+        assert_eq!(outcome.exit_code, EXIT_OK);
+        assert!(
+            outcome
+                .output
+                .ends_with("Cleaned 1 of 1 folders, freeing 100 B\n")
+        );
+
+        // Everything selected was removed by something else in the meantime
+        let (_root, mut app) = fixture();
+        let outcome = run_with(&mut app, true, |dirs| DeletionReport {
+            already_gone: dirs.iter().map(|d| d.path.clone()).collect(),
+            ..DeletionReport::default()
+        });
+        assert_eq!(outcome.exit_code, EXIT_NOTHING_FOUND);
+
+        // Nothing freed because every deletion failed is an error, not "nothing found"
+        let (_root, mut app) = fixture();
+        let outcome = run_with(&mut app, true, |dirs| DeletionReport {
+            failed: dirs
+                .iter()
+                .map(|d| (d.path.clone(), "permission denied".to_string()))
+                .collect(),
+            ..DeletionReport::default()
+        });
+        assert_eq!(outcome.exit_code, EXIT_ERRORS);
+        assert!(outcome.output.contains("Could not remove"));
+
+        // Three old matches, only one of which could be trashed
+        let (_root, mut app) = fixture();
+        for dir in ["a", "b"] {
+            let target = app.scan_config.root.join(dir).join("target");
+            fs::create_dir_all(&target).unwrap();
+            filetime::set_file_mtime(&target, filetime::FileTime::from_unix_time(0, 0)).unwrap();
+        }
+        let outcome = run_with(&mut app, true, |dirs| DeletionReport {
+            succeeded: vec![dirs[0].path.clone()],
+            failed: dirs[1..]
+                .iter()
+                .map(|d| (d.path.clone(), "permission denied".to_string()))
+                .collect(),
+            already_gone: Vec::new(),
+            bytes_freed: 100,
+        });
+        assert_eq!(outcome.exit_code, EXIT_ERRORS);
+        assert_eq!(
+            outcome
+                .output
+                .lines()
+                .filter(|l| l.starts_with("Could not remove") && l.ends_with("permission denied"))
+                .count(),
+            2
+        );
+        assert!(
+            outcome
+                .output
+                .ends_with("Cleaned 1 of 3 folders, freeing 100 B\n")
+        );
+    }
+
+    #[test]
+    fn nothing_found_exits_with_3() {
+        let root = tempfile::tempdir().unwrap();
+        let mut app = App::new();
+        app.session_file = None;
+        app.scan_config.root = root.path().to_path_buf();
+        let outcome = run_with(&mut app, true, |_| panic!("nothing to delete"));
+        assert_eq!(outcome.exit_code, EXIT_NOTHING_FOUND);
+        assert_eq!(outcome.output, "Nothing to clean\n");
+    }
+
+    #[test]
+    fn emit_script_writes_commands_without_deleting() {
+        let (root, mut app) = fixture();
+        let script = root.path().join("cleanup.sh");
+        let outcome = emit_script(&mut app, &script);
+
+        assert_eq!(outcome.exit_code, EXIT_OK);
+        assert!(outcome.output.starts_with("Wrote commands for 1 folders"));
+        let contents = fs::read_to_string(&script).unwrap();
+        let old = root.path().join("old/target");
+        assert!(contents.contains(&format!("trash-put -- {}", export::shell_quote(&old))));
+        assert!(!contents.contains("new/target"));
+        assert!(old.exists());
+    }
+
+    #[test]
+    fn applying_an_edited_plan_removes_only_approved_folders() {
+        let (root, mut app) = fixture();
+        let file = root.path().join("plan.toml");
+        let outcome = write_plan(&mut app, &file);
+        assert_eq!(outcome.exit_code, EXIT_OK);
+        assert!(
+            outcome
+                .output
+                .starts_with("Wrote a plan for 2 folders (1 marked")
+        );
+
+        // The reviewer keeps the old build and approves the new one instead
+        let old = root.path().join("old/target");
+        let new = root.path().join("new/target");
+        let mut plan = Plan::load(&file).unwrap();
+        for candidate in &mut plan.candidates {
+            assert_eq!(candidate.delete, candidate.path == old);
+            candidate.delete = candidate.path == new;
+        }
+        plan.write(&file).unwrap();
+
+        let mut app = App::new();
+        app.session_file = None;
+        app.delete_mode = DeleteMode::Permanent;
+        let outcome = apply_plan(&mut app, &file);
+        assert_eq!(outcome.exit_code, EXIT_OK);
+        assert!(outcome.output.starts_with("Cleaned 1 of 1 folders"));
+        assert!(old.exists());
+        assert!(!new.exists());
+
+        // Applying again finds the approved folder already gone
+        let outcome = apply_plan(&mut app, &file);
+        assert_eq!(outcome.exit_code, EXIT_NOTHING_FOUND);
+        assert!(outcome.output.contains("no longer exists"));
+        assert!(old.exists());
+    }
+
+    #[test]
+    fn sort_flags_order_a_fixed_listing() {
+        let dirs = vec![
+            DirInfo {
+                modified_days_ago: 10,
+                ..dir("/p/zeta/target", 300)
+            },
+            DirInfo {
+                modified_days_ago: 50,
+                ..dir("/p/alpha/node_modules", 100)
+            },
+            DirInfo {
+                modified_days_ago: 30,
+                ..dir("/q/mid/target", 200)
+            },
+        ];
+        let listed = |args: &[&str]| -> Vec<String> {
+            let cli = crate::cli::parse_args(args.iter().map(|a| a.to_string())).unwrap();
+            let mut sorted = dirs.clone();
+            app::sort_dirs(
+                &mut sorted,
+                cli.sort_order(app::SortOrder::default()).unwrap(),
+            );
+            sorted
+                .iter()
+                .map(|d| d.path.parent().unwrap().display().to_string())
+                .collect()
+        };
+        assert_eq!(
+            listed(&["--sort", "age"]),
+            ["/p/alpha", "/q/mid", "/p/zeta"]
+        );
+        assert_eq!(
+            listed(&["--sort", "size"]),
+            ["/p/zeta", "/q/mid", "/p/alpha"]
+        );
+        assert_eq!(
+            listed(&["--sort", "path"]),
+            ["/p/alpha", "/p/zeta", "/q/mid"]
+        );
+        assert_eq!(
+            listed(&["--sort", "name"]),
+            ["/p/alpha", "/q/mid", "/p/zeta"]
+        );
+        assert_eq!(
+            listed(&["--sort", "size", "--reverse"]),
+            ["/p/alpha", "/q/mid", "/p/zeta"]
+        );
+        assert_eq!(
+            listed(&["--sort", "name", "--reverse"]),
+            ["/p/zeta", "/q/mid", "/p/alpha"]
+        );
+        // Alone, --reverse flips the default newest-first order
+        assert_eq!(listed(&["--reverse"]), ["/p/alpha", "/q/mid", "/p/zeta"]);
+        assert_eq!(
+            crate::cli::parse_args(Vec::new())
+                .unwrap()
+                .sort_order(app::SortOrder::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn a_missing_directory_is_a_usage_error() {
+        let root = tempfile::tempdir().unwrap();
+        let check = |path: &std::path::Path| {
+            let args = ["--headless".to_string(), path.display().to_string()];
+            crate::cli::parse_args(args).and_then(crate::cli::check_directory)
+        };
+        assert!(check(root.path()).is_ok());
+        let err = check(&root.path().join("no-such-dir")).unwrap_err();
+        assert!(err.ends_with("is not a directory"));
+        fs::write(root.path().join("file"), "").unwrap();
+        assert!(check(&root.path().join("file")).is_err());
+    }
+
+    #[test]
+    fn tree_format_lists_matches_under_the_root() {
+        let (root, mut app) = fixture();
+        app.list_format = ListFormat::Tree;
+        let outcome = run(&mut app, false);
+        let lines: Vec<&str> = outcome.output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(&format!("  {}", root.path().display())));
+        assert!(lines[1].ends_with("  └── old/target"));
+    }
+
+    #[test]
+    fn parent_format_prints_one_total_per_project() {
+        let dirs = [
+            dir("/src/a/target", 1024),
+            dir("/src/a/node_modules", 1024),
+            dir("/src/b/target", 512),
+        ];
+        assert_eq!(
+            format_parent_totals(&dirs, SizeUnits::Binary),
+            "/src/a: 2 dirs, 2 KiB\n/src/b: 1 dir, 512 B\n"
+        );
+    }
+
+    #[test]
+    fn require_targets_without_any_removes_nothing() {
+        let (root, _) = fixture();
+        let cli = Cli {
+            directory: Some(root.path().to_path_buf()),
+            require_targets: true,
+            ..Cli::default()
+        };
+        let mut app = App::from_cli(&cli);
+        app.session_file = None;
+        let outcome = run_with(&mut app, true, |_| panic!("must not delete"));
+        assert_eq!(outcome.exit_code, EXIT_ERRORS);
+        assert!(
+            outcome
+                .output
+                .contains("Not cleaning: no targets were given")
+        );
+    }
+
+    #[test]
+    fn timestamps_start_every_line_with_rfc3339() {
+        let (_root, mut app) = fixture();
+        app.timestamps = true;
+        let outcome = run_with(&mut app, true, |dirs| DeletionReport {
+            succeeded: dirs.iter().map(|d| d.path.clone()).collect(),
+            failed: Vec::new(),
+            already_gone: Vec::new(),
+            bytes_freed: 100,
+        });
+        assert_eq!(outcome.exit_code, EXIT_OK);
+        // The listed match and the summary
+        assert_eq!(outcome.output.lines().count(), 2);
+        for line in outcome.output.lines() {
+            let (timestamp, rest) = line.split_once(' ').unwrap();
+            assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+            assert!(!rest.is_empty());
+        }
+
+        // Off by default
+        let (_root, mut app) = fixture();
+        let outcome = run_with(&mut app, false, |_| panic!("must not delete"));
+        let first = outcome.output.split_whitespace().next().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(first).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::app::DirInfo;
+use crate::paths;
+use crate::scanner;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// What the last run of each scan root found, for `--since-last-run`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    runs: BTreeMap<PathBuf, LastRun>,
+}
+
+// When a root was last scanned and every match seen under it so far. Cleaned-up paths
+// drop out once they're gone, so a folder that comes back counts as new again.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastRun {
+    // Seconds since the Unix epoch
+    pub finished: u64,
+    pub seen: BTreeSet<PathBuf>,
+}
+
+impl LastRun {
+    // A match worth showing again: never reported before, or modified since the last run
+    pub fn is_new(&self, dir: &DirInfo) -> bool {
+        let finished = UNIX_EPOCH + Duration::from_secs(self.finished);
+        dir.modified > finished || !self.seen.contains(&scanner::absolute_path(&dir.path))
+    }
+}
+
+// Location of the history file, in the platform's data directory
+pub fn default_path() -> Option<PathBuf> {
+    Some(paths::data_dir()?.join("history.json"))
+}
+
+fn read(file: &Path) -> HistoryFile {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+// The last recorded run of `root`, if there was one
+pub fn load(file: &Path, root: &Path) -> Option<LastRun> {
+    read(file).runs.remove(&scanner::absolute_path(root))
+}
+
+// Record a run of `root` that just found `dirs`, adding them to what earlier runs saw
+pub fn record(file: &Path, root: &Path, dirs: &[DirInfo], now: SystemTime) -> io::Result<()> {
+    let mut history = read(file);
+    let run = history
+        .runs
+        .entry(scanner::absolute_path(root))
+        .or_default();
+    run.finished = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    run.seen
+        .extend(dirs.iter().map(|d| scanner::absolute_path(&d.path)));
+    run.seen.retain(|path| path.exists());
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(&history).map_err(io::Error::other)?;
+    fs::write(file, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_info(path: &Path, modified: SystemTime) -> DirInfo {
+        DirInfo {
+            modified,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    #[test]
+    fn recorded_matches_stop_being_new_until_they_change() {
+        let root = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let file = data.path().join("nested/history.json");
+        let seen = root.path().join("a/target");
+        let cleaned = root.path().join("b/target");
+        fs::create_dir_all(&seen).unwrap();
+        fs::create_dir_all(&cleaned).unwrap();
+
+        let long_ago = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let run_time = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        assert_eq!(load(&file, root.path()), None);
+        record(
+            &file,
+            root.path(),
+            &[dir_info(&seen, long_ago), dir_info(&cleaned, long_ago)],
+            run_time,
+        )
+        .unwrap();
+        let last = load(&file, root.path()).unwrap();
+        assert_eq!(last.finished, 1_500_000_000);
+        assert!(!last.is_new(&dir_info(&seen, long_ago)));
+        assert!(last.is_new(&dir_info(&seen, SystemTime::now())));
+        assert!(last.is_new(&dir_info(&root.path().join("c/target"), long_ago)));
+        assert_eq!(load(&file, data.path()), None);
+
+        // Cleaned paths are forgotten, so they're new if they come back
+        fs::remove_dir_all(&cleaned).unwrap();
+        record(&file, root.path(), &[], run_time).unwrap();
+        let last = load(&file, root.path()).unwrap();
+        assert_eq!(last.seen, BTreeSet::from([seen]));
+        assert!(last.is_new(&dir_info(&cleaned, long_ago)));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::cli;
+use crate::paths;
+use crate::scanner;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Location of the paths ignored with `I`, in the platform's config directory: a plain
+// list, one path per line, so it can be edited by hand to bring one back
+pub fn default_path() -> Option<PathBuf> {
+    Some(paths::config_dir()?.join("ignored.txt"))
+}
+
+// Every path in the file; none when it doesn't exist yet
+pub fn load(file: &Path) -> Vec<PathBuf> {
+    fs::read_to_string(file)
+        .map(|contents| {
+            cli::parse_list(&contents)
+                .into_iter()
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Add `path` to the file, creating it if needed; `false` if it was already there
+pub fn add(file: &Path, path: &Path) -> io::Result<bool> {
+    let path = scanner::absolute_path(path);
+    if load(file).contains(&path) {
+        return Ok(false);
+    }
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)?;
+    writeln!(out, "{}", path.display())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn added_paths_are_kept_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config/ignored.txt");
+        assert!(load(&file).is_empty());
+
+        let path = dir.path().join("project/target");
+        assert!(add(&file, &path).unwrap());
+        assert!(!add(&file, &path).unwrap());
+        assert!(add(&file, &dir.path().join("other/target")).unwrap());
+        assert_eq!(load(&file), [path, dir.path().join("other/target")]);
+    }
+}
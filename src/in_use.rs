@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// Files other processes have open and their working directories. A `target` being
+// written by `cargo build` or a `node_modules` mid `npm install` shows up here.
+#[cfg(target_os = "linux")]
+pub fn open_paths() -> Vec<PathBuf> {
+    use std::fs;
+
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let own_pid = std::process::id().to_string();
+    let mut paths = Vec::new();
+    for process in processes.flatten() {
+        // Only the numbered entries are processes
+        let name = process.file_name();
+        let name = name.to_string_lossy();
+        if !name.bytes().all(|b| b.is_ascii_digit()) || name == own_pid {
+            continue;
+        }
+        let dir = process.path();
+        paths.extend(fs::read_link(dir.join("cwd")));
+        // Other users' processes can't be read without privileges, and are skipped
+        if let Ok(fds) = fs::read_dir(dir.join("fd")) {
+            paths.extend(fds.flatten().filter_map(|fd| fs::read_link(fd.path()).ok()));
+        }
+    }
+    paths
+}
+
+// Not checked elsewhere yet: nothing is reported as in use
+#[cfg(not(target_os = "linux"))]
+pub fn open_paths() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+// The `dirs` that contain one of the `open` paths. Both sides are compared in canonical
+// form, since matches under a relative or symlinked root are spelled differently from
+// the absolute paths the system reports.
+pub fn dirs_in_use<'a>(
+    dirs: impl IntoIterator<Item = &'a Path>,
+    open: &[PathBuf],
+) -> HashSet<PathBuf> {
+    let open: Vec<PathBuf> = open.iter().map(|path| canonical(path)).collect();
+    dirs.into_iter()
+        .filter(|dir| {
+            let dir = canonical(dir);
+            open.iter().any(|path| path.starts_with(&dir))
+        })
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+// `path` with symlinks and `..` resolved, or as given when it no longer exists
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirs_holding_an_open_path_are_in_use() {
+        let open = [
+            PathBuf::from("/p/a/target/debug/.cargo-lock"),
+            PathBuf::from("/p/b/node_modules"),
+            PathBuf::from("/p/c/target-old/file"),
+        ];
+        let dirs = [
+            Path::new("/p/a/target"),
+            Path::new("/p/b/node_modules"),
+            Path::new("/p/c/target"),
+            Path::new("/p/d/target"),
+        ];
+        let in_use = dirs_in_use(dirs, &open);
+        assert_eq!(
+            in_use,
+            HashSet::from([
+                PathBuf::from("/p/a/target"),
+                PathBuf::from("/p/b/node_modules")
+            ])
+        );
+        assert!(dirs_in_use(dirs, &[]).is_empty());
+    }
+
+    #[test]
+    fn matches_under_a_relative_root_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("project/target");
+        std::fs::create_dir_all(target.join("debug")).unwrap();
+        let lock = target.join("debug/.cargo-lock");
+        std::fs::write(&lock, "").unwrap();
+
+        // The same folder, spelled relative to the working directory
+        let cwd = std::env::current_dir().unwrap();
+        let mut relative: PathBuf = cwd.components().skip(1).map(|_| "..").collect();
+        relative.push(target.strip_prefix("/").unwrap());
+        assert!(relative.is_relative());
+
+        let in_use = dirs_in_use([relative.as_path()], &[lock.canonicalize().unwrap()]);
+        assert_eq!(in_use, HashSet::from([relative]));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn a_running_process_inside_a_directory_is_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .current_dir(dir.path())
+            .spawn()
+            .unwrap();
+        // The child's working directory is readable as soon as it exists
+        let found = dirs_in_use([dir.path()], &open_paths());
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(found.contains(dir.path()));
+    }
+}
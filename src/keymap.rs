@@ -0,0 +1,539 @@
+use crossterm::event::KeyCode;
+use std::collections::BTreeMap;
+
+// Something a key can be bound to in the results list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    Toggle,
+    VisualRange,
+    SelectAll,
+    DeselectAll,
+    Invert,
+    View,
+    PathDisplay,
+    AgeUp,
+    AgeDown,
+    KeepMore,
+    KeepFewer,
+    HideUnselected,
+    Group,
+    Collapse,
+    Expand,
+    SortSelected,
+    SortSize,
+    SortAge,
+    SortPath,
+    Clean,
+    TrashOne,
+    CopyPath,
+    IgnoreForever,
+    Peek,
+    Stats,
+    Browse,
+    Quit,
+    Help,
+}
+
+// Every action with its config name and the keys it has out of the box
+const DEFAULT_KEYS: &[(Action, &str, &[KeyCode])] = &[
+    (Action::MoveUp, "move_up", &[KeyCode::Up]),
+    (Action::MoveDown, "move_down", &[KeyCode::Down]),
+    (Action::Toggle, "toggle", &[KeyCode::Char(' ')]),
+    (Action::VisualRange, "visual_range", &[KeyCode::Char('V')]),
+    (Action::SelectAll, "select_all", &[KeyCode::Char('a')]),
+    (Action::DeselectAll, "deselect_all", &[KeyCode::Char('d')]),
+    (Action::Invert, "invert", &[KeyCode::Char('i')]),
+    (Action::View, "view", &[KeyCode::Char('v')]),
+    (Action::PathDisplay, "path_display", &[KeyCode::Char('t')]),
+    (
+        Action::AgeUp,
+        "age_up",
+        &[KeyCode::Char('+'), KeyCode::Char('=')],
+    ),
+    (Action::AgeDown, "age_down", &[KeyCode::Char('-')]),
+    (Action::KeepMore, "keep_more", &[KeyCode::Char(']')]),
+    (Action::KeepFewer, "keep_fewer", &[KeyCode::Char('[')]),
+    (
+        Action::HideUnselected,
+        "hide_unselected",
+        &[KeyCode::Char('h')],
+    ),
+    (Action::Group, "group", &[KeyCode::Char('g')]),
+    (Action::Collapse, "collapse", &[KeyCode::Left]),
+    (Action::Expand, "expand", &[KeyCode::Right]),
+    (Action::SortSelected, "sort_selected", &[KeyCode::Char('1')]),
+    (Action::SortSize, "sort_size", &[KeyCode::Char('2')]),
+    (Action::SortAge, "sort_age", &[KeyCode::Char('3')]),
+    (Action::SortPath, "sort_path", &[KeyCode::Char('4')]),
+    (
+        Action::Clean,
+        "clean",
+        &[KeyCode::Char('c'), KeyCode::Enter],
+    ),
+    (
+        Action::TrashOne,
+        "trash_one",
+        &[KeyCode::Char('x'), KeyCode::Delete],
+    ),
+    (Action::CopyPath, "copy_path", &[KeyCode::Char('y')]),
+    (
+        Action::IgnoreForever,
+        "ignore_forever",
+        &[KeyCode::Char('I')],
+    ),
+    (Action::Peek, "peek", &[KeyCode::Char('p')]),
+    (Action::Stats, "stats", &[KeyCode::Char('s')]),
+    (Action::Browse, "browse", &[KeyCode::Char('b')]),
+    (Action::Quit, "quit", &[KeyCode::Char('q')]),
+    (Action::Help, "help", &[KeyCode::Char('?')]),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        DEFAULT_KEYS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .map(|(_, name, _)| *name)
+            .expect("every action has a default binding")
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        DEFAULT_KEYS
+            .iter()
+            .find(|(_, known, _)| *known == name)
+            .map(|(action, _, _)| *action)
+    }
+}
+
+// Which keys trigger which action; defaults to the bindings in `DEFAULT_KEYS`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    keys: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            keys: DEFAULT_KEYS
+                .iter()
+                .map(|(action, _, keys)| (*action, keys.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn action_for(&self, code: KeyCode) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(_, keys)| keys.contains(&code))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn keys_for(&self, action: Action) -> &[KeyCode] {
+        self.keys
+            .iter()
+            .find(|(known, _)| *known == action)
+            .map_or(&[], |(_, keys)| keys.as_slice())
+    }
+
+    // Rebind actions by config name, e.g. `select_all = ["r"]`; an empty list unbinds one.
+    // Fails on unknown actions or keys, and when two actions end up on the same key.
+    pub fn with_overrides(
+        mut self,
+        overrides: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self, String> {
+        for (name, keys) in overrides {
+            let action = Action::from_name(name)
+                .ok_or_else(|| format!("Unknown key binding action: {}", name))?;
+            let keys = keys
+                .iter()
+                .map(|key| parse_key(key))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some((_, bound)) = self.keys.iter_mut().find(|(known, _)| *known == action) {
+                *bound = keys;
+            }
+        }
+        self.check_conflicts()?;
+        Ok(self)
+    }
+
+    fn check_conflicts(&self) -> Result<(), String> {
+        for (i, (action, keys)) in self.keys.iter().enumerate() {
+            for key in keys {
+                if let Some((other, _)) = self.keys[i + 1..].iter().find(|(_, k)| k.contains(key)) {
+                    return Err(format!(
+                        "Key {} is bound to both {} and {}",
+                        key_name(*key),
+                        action.name(),
+                        other.name()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Key names as written in the config file and shown in the help
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Delete => "Delete".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+// A single character, or one of Space, Enter, Delete, Backspace, Tab, Up, Down, Left and Right.
+// Esc always cancels or quits, so it can't be rebound.
+pub fn parse_key(name: &str) -> Result<KeyCode, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            '↑' => Ok(KeyCode::Up),
+            '↓' => Ok(KeyCode::Down),
+            '←' => Ok(KeyCode::Left),
+            '→' => Ok(KeyCode::Right),
+            c => Ok(KeyCode::Char(c)),
+        };
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "space" => Ok(KeyCode::Char(' ')),
+        "enter" => Ok(KeyCode::Enter),
+        "delete" => Ok(KeyCode::Delete),
+        "backspace" => Ok(KeyCode::Backspace),
+        "tab" => Ok(KeyCode::Tab),
+        "up" => Ok(KeyCode::Up),
+        "down" => Ok(KeyCode::Down),
+        "left" => Ok(KeyCode::Left),
+        "right" => Ok(KeyCode::Right),
+        "esc" | "escape" => Err("Esc can't be rebound".to_string()),
+        _ => Err(format!("Unknown key: {}", name)),
+    }
+}
+
+// A documented key binding, shown in the help popup and (optionally) the instructions bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    // Shown as is for keys that can't be rebound
+    pub keys: &'static str,
+    // The rebindable actions this entry covers; their current keys are shown instead of `keys`
+    pub actions: &'static [Action],
+    pub description: &'static str,
+    // Short label for the instructions bar; `None` keeps it out of the bar
+    pub hint: Option<&'static str>,
+    // Cleans something up, so it's left out of read-only sessions
+    pub destructive: bool,
+}
+
+impl KeyBinding {
+    // The keys as currently bound: every key of a single action ("c / Enter"), or the
+    // first key of each of several ("↑/↓"). `short` keeps only the first key throughout.
+    pub fn keys_label(&self, bindings: &KeyBindings, short: bool) -> String {
+        match self.actions {
+            [] => self.keys.to_string(),
+            [action] if !short => bindings
+                .keys_for(*action)
+                .iter()
+                .map(|key| key_name(*key))
+                .collect::<Vec<_>>()
+                .join(" / "),
+            actions => actions
+                .iter()
+                .filter_map(|action| bindings.keys_for(*action).first())
+                .map(|key| key_name(*key))
+                .collect::<Vec<_>>()
+                .join("/"),
+        }
+    }
+}
+
+// Single source of truth for the keyboard shortcuts
+pub const KEYMAP: &[KeyBinding] = &[
+    KeyBinding {
+        keys: "↑/↓",
+        actions: &[Action::MoveUp, Action::MoveDown],
+        description: "Move the highlight up/down the list",
+        hint: Some("move"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "Space",
+        actions: &[Action::Toggle],
+        description: "Select or deselect the highlighted directory",
+        hint: Some("toggle"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "V",
+        actions: &[Action::VisualRange],
+        description: "Start a range at the highlight (again, or Esc, to cancel); move, then Space toggles every directory in it",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "a",
+        actions: &[Action::SelectAll],
+        description: "Select all directories",
+        hint: Some("all"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "d",
+        actions: &[Action::DeselectAll],
+        description: "Deselect all directories",
+        hint: Some("none"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "i",
+        actions: &[Action::Invert],
+        description: "Invert the selection",
+        hint: Some("invert"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "v",
+        actions: &[Action::View],
+        description: "Switch between compact (size + name) and detailed (size + date + path) rows",
+        hint: Some("view"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "t",
+        actions: &[Action::PathDisplay],
+        description: "Show paths relative to the scanned directory, or in full",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "+/-",
+        actions: &[Action::AgeUp, Action::AgeDown],
+        description: "Raise/lower the auto-select age by 5 days and select by age again",
+        hint: Some("age"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "]/[",
+        actions: &[Action::KeepMore, Action::KeepFewer],
+        description: "Keep one more/fewer of the most recent matches per target and select the rest",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "h",
+        actions: &[Action::HideUnselected],
+        description: "Hide unselected directories to review only what will be cleaned (press again to show all)",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "g",
+        actions: &[Action::Group],
+        description: "Group matches under the project directory containing them (Space on a group toggles all of it)",
+        hint: Some("group"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "←/→",
+        actions: &[Action::Collapse, Action::Expand],
+        description: "Expand the highlighted directory to list its subdirectories with sizes, or collapse it; on a group header, fold/unfold the group",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "1/2/3/4",
+        actions: &[
+            Action::SortSelected,
+            Action::SortSize,
+            Action::SortAge,
+            Action::SortPath,
+        ],
+        description: "Sort by the selected, size, age or path column; again to reverse (or click the column header)",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "c / Enter",
+        actions: &[Action::Clean],
+        description: "Review the selected paths, then move them to the trash",
+        hint: Some("clean"),
+        destructive: true,
+    },
+    KeyBinding {
+        keys: "x / Delete",
+        actions: &[Action::TrashOne],
+        description: "Move only the highlighted directory to the trash (asks first)",
+        hint: Some("trash one"),
+        destructive: true,
+    },
+    KeyBinding {
+        keys: "e",
+        actions: &[],
+        description: "In the summary after moving folders to the trash, empty the trash to reclaim the space (asks first; Linux and Windows)",
+        hint: None,
+        destructive: true,
+    },
+    KeyBinding {
+        keys: "y",
+        actions: &[Action::CopyPath],
+        description: "Copy the highlighted directory's path to the clipboard",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "I",
+        actions: &[Action::IgnoreForever],
+        description: "Ignore the highlighted directory in this and every future scan",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "p",
+        actions: &[Action::Peek],
+        description: "Peek inside the highlighted directory: its largest files and folders",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "s",
+        actions: &[Action::Stats],
+        description: "Show how many matches (and how much space) are 0-30, 31-90 and over 90 days old",
+        hint: Some("stats"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "b",
+        actions: &[Action::Browse],
+        description: "Browse to another directory and scan it (Enter opens, Backspace goes up, s scans)",
+        hint: Some("browse"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "Esc",
+        actions: &[],
+        description: "Stop a running scan (asks first), close a dialog, or quit",
+        hint: Some("cancel/quit"),
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "q",
+        actions: &[Action::Quit],
+        description: "Quit",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "Ctrl+C",
+        actions: &[],
+        description: "Quit immediately",
+        hint: None,
+        destructive: false,
+    },
+    KeyBinding {
+        keys: "?",
+        actions: &[Action::Help],
+        description: "Show or hide this help",
+        hint: Some("help"),
+        destructive: false,
+    },
+];
+
+// The bindings that work in this session
+pub fn bindings(read_only: bool) -> impl Iterator<Item = &'static KeyBinding> {
+    KEYMAP
+        .iter()
+        .filter(move |binding| !(read_only && binding.destructive))
+}
+
+// Abbreviated hints for the instructions bar
+pub fn hint_line(read_only: bool, keys: &KeyBindings) -> String {
+    bindings(read_only)
+        .filter_map(|binding| {
+            let label = binding.keys_label(keys, true);
+            match (binding.hint, label.is_empty()) {
+                (Some(hint), false) => Some(format!("{}: {}", label, hint)),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hint_line_lists_hinted_bindings_in_order() {
+        let keys = KeyBindings::default();
+        let hints = hint_line(false, &keys);
+        assert!(hints.starts_with("↑/↓: move | Space: toggle"));
+        assert!(hints.ends_with("?: help"));
+        assert!(!hints.contains("Ctrl+C"));
+        assert!(hints.contains("c: clean"));
+        assert!(hints.contains("+/-: age"));
+
+        let read_only = hint_line(true, &keys);
+        assert!(!read_only.contains("c: clean"));
+        assert!(!read_only.contains("x: trash one"));
+        assert!(read_only.contains("b: browse"));
+    }
+
+    #[test]
+    fn defaults_match_the_documented_keys() {
+        let keys = KeyBindings::default();
+        for binding in KEYMAP.iter().filter(|b| !b.actions.is_empty()) {
+            assert_eq!(binding.keys_label(&keys, false), binding.keys);
+        }
+        assert_eq!(keys.action_for(KeyCode::Enter), Some(Action::Clean));
+        assert_eq!(keys.action_for(KeyCode::Esc), None);
+    }
+
+    #[test]
+    fn remapping_an_action_moves_it_to_the_new_keys() {
+        let overrides = BTreeMap::from([
+            ("select_all".to_string(), vec!["r".to_string()]),
+            ("clean".to_string(), vec!["Enter".to_string()]),
+        ]);
+        let keys = KeyBindings::default().with_overrides(&overrides).unwrap();
+        assert_eq!(keys.action_for(KeyCode::Char('r')), Some(Action::SelectAll));
+        assert_eq!(keys.action_for(KeyCode::Char('a')), None);
+        assert_eq!(keys.action_for(KeyCode::Char('c')), None);
+        assert_eq!(keys.action_for(KeyCode::Enter), Some(Action::Clean));
+        assert!(hint_line(false, &keys).contains("r: all"));
+    }
+
+    #[test]
+    fn rejects_conflicts_and_unknown_names() {
+        let rebind = |action: &str, key: &str| {
+            KeyBindings::default().with_overrides(&BTreeMap::from([(
+                action.to_string(),
+                vec![key.to_string()],
+            )]))
+        };
+        assert_eq!(
+            rebind("select_all", "d"),
+            Err("Key d is bound to both select_all and deselect_all".to_string())
+        );
+        assert!(rebind("select_everything", "r").is_err());
+        assert!(rebind("select_all", "Esc").is_err());
+        assert!(rebind("select_all", "PageUp").is_err());
+        // Swapping two keys is fine once both are moved
+        let swapped = KeyBindings::default().with_overrides(&BTreeMap::from([
+            ("select_all".to_string(), vec!["d".to_string()]),
+            ("deselect_all".to_string(), vec!["a".to_string()]),
+        ]));
+        assert!(swapped.is_ok());
+    }
+}
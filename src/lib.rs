@@ -0,0 +1,29 @@
+pub mod app;
+pub mod browser;
+pub mod checkpoint;
+pub mod cli;
+pub mod clipboard;
+pub mod config;
+pub mod delete;
+pub mod explain;
+pub mod export;
+pub mod git;
+pub mod groups;
+pub mod headless;
+pub mod history;
+pub mod ignored;
+pub mod in_use;
+pub mod keymap;
+pub mod mounts;
+pub mod owners;
+pub mod paths;
+pub mod plan;
+pub mod scanner;
+pub mod session;
+pub mod stats;
+pub mod terminal;
+pub mod tooling;
+pub mod tree;
+pub mod ui;
+pub mod units;
+pub mod watch;
@@ -1,103 +1,180 @@
-mod app;
-mod scanner;
-mod ui;
-
-use crate::app::{App, AppState, ScanUpdate};
-use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
-    execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+use crossterm::event::{
+    self, Event, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use disk_cleaner::app::{App, AppState, SortColumn, SortOrder};
+use disk_cleaner::{cli, config, explain, headless, terminal, ui};
+use std::{
+    io, process,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
-use std::{io, path::PathBuf, time::Duration};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // Create app and run it
-    let mut app = App::new();
-
-    // Get directory argument or use current directory
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        let path = PathBuf::from(&args[1]);
-        if path.is_dir() {
-            app.current_directory = path;
+    let cli = match cli::parse_args(std::env::args().skip(1))
+        .and_then(config::apply_env)
+        .and_then(config::apply_config)
+        .and_then(cli::load_list_files)
+        .and_then(cli::check_directory)
+    {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    // Headless diagnostic mode: explain a single path and exit
+    if let Some(path) = &cli.explain {
+        let app = App::from_cli(&cli);
+        let verdict = explain::explain(&app, path);
+        println!("{}: {}", path.display(), verdict.describe(app.size_units));
+        return Ok(());
+    }
+
+    // Headless report of the largest matches
+    if let Some(n) = cli.top {
+        let mut app = App::from_cli(&cli);
+        let by_size = SortOrder::starting(SortColumn::Size);
+        print!("{}", headless::top(&mut app, n, cli.sort_order(by_size)));
+        print_scan_warnings(&app);
+        return Ok(());
+    }
+
+    // Headless script of the cleanup, to review and run later
+    if let Some(file) = &cli.emit_script {
+        let mut app = App::from_cli(&cli);
+        let outcome = headless::emit_script(&mut app, file);
+        print!("{}", outcome.output);
+        print_scan_warnings(&app);
+        process::exit(outcome.exit_code);
+    }
+
+    // Two-phase cleanup: write a plan to review, then apply exactly what was approved
+    if let Some(file) = &cli.plan {
+        let mut app = App::from_cli(&cli);
+        let outcome = headless::write_plan(&mut app, file);
+        print!("{}", outcome.output);
+        print_scan_warnings(&app);
+        process::exit(outcome.exit_code);
+    }
+    if let Some(file) = &cli.apply {
+        let mut app = App::from_cli(&cli);
+        let outcome = headless::apply_plan(&mut app, file);
+        print!("{}", outcome.output);
+        if let Some(err) = &app.report_error {
+            eprintln!("{}", err);
         }
+        process::exit(outcome.exit_code);
     }
 
+    // Headless cleanup for scripts and CI, reporting the result through the exit code
+    if cli.headless {
+        let mut app = App::from_cli(&cli);
+        let outcome = headless::run(&mut app, cli.delete);
+        let _ = app.record_run();
+        print!("{}", outcome.output);
+        print_scan_warnings(&app);
+        if let Some(err) = &app.report_error {
+            eprintln!("{}", err);
+        }
+        process::exit(outcome.exit_code);
+    }
+
+    // Initialize terminal, restoring it even if we panic
+    terminal::install_panic_hook();
+    let shutdown = terminal::shutdown_flag()?;
+    let mut terminal = terminal::init()?;
+
+    // Create app from the command-line options and run it
+    let mut app = App::from_cli(&cli);
+
     // Start the initial scan
     app.start_scan();
+    app.start_trash_size_query();
+    if cli.watch {
+        app.start_watching();
+    }
+
+    let result = run(&mut terminal, &mut app, &shutdown);
+
+    // Remember the selection, and what was found, for the next run of this directory
+    let _ = app.save_session();
+    let _ = app.record_run();
+
+    // Cleanup, whether or not the loop failed
+    terminal::restore()?;
+    result?;
 
+    Ok(())
+}
+
+// Draw and handle input until the user quits
+fn run(terminal: &mut terminal::Tui, app: &mut App, shutdown: &AtomicBool) -> io::Result<()> {
     loop {
-        terminal.draw(|f| ui::draw(f, &mut app))?;
+        terminal.draw(|f| ui::draw(f, app))?;
 
-        // Check if we should exit
-        if app.should_exit {
+        // Check if we should exit, or were asked to by SIGTERM
+        if app.should_exit || shutdown.load(Ordering::SeqCst) {
             break;
         }
 
         // Handle scan updates
-        if let Some(receiver) = &app.scan_receiver {
-            if let Ok(update) = receiver.try_recv() {
-                match update {
-                    ScanUpdate::Path(path) => {
-                        app.current_scan_path = Some(path);
-                    }
-                    ScanUpdate::Result(dir_info) => {
-                        app.dirs_to_clean.push(dir_info);
-                        app.dirs_to_clean.sort_by_key(|d| d.modified_days_ago);
-
-                        app.scan_results.total_folders = app.dirs_to_clean.len();
-                        app.update_selection_scan_results();
-                        app.scan_results.total_size_gb = app
-                            .dirs_to_clean
-                            .iter()
-                            .map(|d| d.size_bytes as f64)
-                            .sum::<f64>()
-                            / (1024.0 * 1024.0 * 1024.0);
-
-                        if !app.dirs_to_clean.is_empty() && app.dir_list_state.selected().is_none()
-                        {
-                            app.dir_list_state.select(Some(0));
-                        }
-                    }
-                    ScanUpdate::Done => {
-                        app.state = AppState::ScanComplete;
-                        app.scan_receiver = None;
-                        app.current_scan_path = None;
-                    }
-                }
-            }
+        if let Some(receiver) = &app.scan_receiver
+            && let Ok(update) = receiver.try_recv()
+        {
+            app.handle_scan_update(update);
         }
 
+        // Handle deletion progress updates, and the rescans of what a cleanup left behind
+        app.process_deletion_updates();
+        app.poll_subtree_rescans();
+
+        // Pick up the trash size once it has been added up
+        app.poll_trash_size();
+
+        // Rescan after filesystem changes in watch mode
+        app.poll_watch(Instant::now());
+
+        // Drop confirmations left unanswered for too long, and toasts that have had their time
+        app.expire_confirm(Instant::now());
+        app.expire_toast(Instant::now());
+
         // Handle input events
         if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
-                    break;
+            match event::read()? {
+                Event::Key(key) => {
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && key.code == KeyCode::Char('c')
+                    {
+                        break;
+                    }
+                    app.handle_key_event(key);
                 }
-                app.handle_key_event(key);
+                Event::Mouse(MouseEvent {
+                    kind: MouseEventKind::Down(MouseButton::Left),
+                    column,
+                    row,
+                    ..
+                }) => app.handle_click(column, row),
+                _ => {}
             }
         }
 
-        // Update spinner
+        // Fill in the percent-of-parent column while idle
+        app.size_next_parent();
+
+        // Update spinner and scan ETA
         if app.state == AppState::Scanning {
-            // A bit of a hack to access the spinner length
-            const SPINNER_LEN: usize = 8;
-            app.spinner_index = (app.spinner_index + 1) % SPINNER_LEN;
+            app.advance_spinner();
+            app.update_scan_eta();
         }
     }
-
-    // Cleanup
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-
     Ok(())
 }
+
+// Tell headless users which parts of the tree were skipped
+fn print_scan_warnings(app: &App) {
+    for (path, reason) in &app.scan_warnings {
+        eprintln!("Skipped {}: {}", path.display(), reason);
+    }
+}
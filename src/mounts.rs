@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+
+// A mounted filesystem, as listed in /proc/self/mounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub point: PathBuf,
+    pub fstype: String,
+}
+
+// Filesystem types backed by a network server rather than a local disk
+const NETWORK_FSTYPES: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afs",
+    "9p",
+    "ncpfs",
+    "ceph",
+    "glusterfs",
+    "davfs",
+    "fuse.sshfs",
+    "fuse.rclone",
+    "fuse.s3fs",
+];
+
+// Where desktop environments mount USB sticks and other removable media
+const REMOVABLE_PREFIXES: &[&str] = &["/media", "/run/media"];
+
+impl Mount {
+    // Network filesystems and removable media are not local
+    pub fn is_local(&self) -> bool {
+        !NETWORK_FSTYPES.contains(&self.fstype.as_str())
+            && !REMOVABLE_PREFIXES
+                .iter()
+                .any(|prefix| self.point.starts_with(prefix) && self.point != Path::new(prefix))
+    }
+}
+
+// Parse the /proc/self/mounts format: device, mount point, type, options, dump, pass
+pub fn parse_mounts(contents: &str) -> Vec<Mount> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let point = fields.next()?;
+            let fstype = fields.next()?;
+            Some(Mount {
+                point: PathBuf::from(unescape(point)),
+                fstype: fstype.to_string(),
+            })
+        })
+        .collect()
+}
+
+// Mount points are written with octal escapes for spaces, tabs, newlines and backslashes
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if let Ok(code) = u8::from_str_radix(&digits, 8)
+                && digits.len() == 3
+            {
+                out.push(code as char);
+                chars.nth(2);
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+// Mount points of network and removable filesystems; empty where detection isn't supported
+#[cfg(target_os = "linux")]
+pub fn non_local_mount_points() -> Vec<PathBuf> {
+    let contents = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    parse_mounts(&contents)
+        .into_iter()
+        .filter(|mount| !mount.is_local())
+        .map(|mount| mount.point)
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn non_local_mount_points() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+// Total size of the filesystem holding `path`; `None` where it can't be found out
+#[cfg(unix)]
+// The field types vary between platforms, so the casts aren't redundant everywhere
+#[allow(clippy::unnecessary_cast)]
+pub fn disk_total_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs only writes into the zeroed struct we own, and the path is NUL-terminated
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_blocks as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn disk_total_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+server:/export/home /home/me/nfs nfs4 rw,relatime,vers=4.2 0 0
+//nas/share /home/me/My\\040Share cifs rw 0 0
+/dev/sdb1 /run/media/me/USB vfat rw,nosuid 0 0
+tmpfs /run tmpfs rw,nosuid 0 0
+";
+
+    #[test]
+    fn parses_mount_points_and_types() {
+        let mounts = parse_mounts(SAMPLE);
+        assert_eq!(mounts.len(), 5);
+        assert_eq!(mounts[1].point, PathBuf::from("/home/me/nfs"));
+        assert_eq!(mounts[1].fstype, "nfs4");
+        assert_eq!(mounts[2].point, PathBuf::from("/home/me/My Share"));
+    }
+
+    #[test]
+    fn network_and_removable_mounts_are_not_local() {
+        let local: Vec<bool> = parse_mounts(SAMPLE).iter().map(Mount::is_local).collect();
+        assert_eq!(local, vec![true, false, false, false, true]);
+    }
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+// Who owns a matched directory, so shared machines can tell other users' folders apart
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Owner {
+    pub uid: u32,
+    // `None` when the uid has no account, e.g. a file from another machine
+    pub name: Option<String>,
+}
+
+impl Owner {
+    // The user name, or the bare uid when there isn't one
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.uid.to_string(),
+        }
+    }
+}
+
+// Owners looked up during one scan; most matches share a handful of users
+#[derive(Debug, Default)]
+pub struct OwnerCache {
+    names: HashMap<u32, Option<String>>,
+}
+
+impl OwnerCache {
+    pub fn owner(&mut self, metadata: &fs::Metadata) -> Option<Owner> {
+        let uid = uid(metadata)?;
+        let name = self.names.entry(uid).or_insert_with(|| user_name(uid));
+        Some(Owner {
+            uid,
+            name: name.clone(),
+        })
+    }
+}
+
+#[cfg(unix)]
+pub fn uid(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+pub fn uid(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+// The account name for `uid`, from the user database
+#[cfg(unix)]
+pub fn user_name(uid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    // SAFETY: getpwuid_r only writes into the zeroed struct and the buffer we own, and
+    // `pw_name` points into that buffer when it reports a result
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let status = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut entry,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() || entry.pw_name.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(entry.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+pub fn user_name(_uid: u32) -> Option<String> {
+    None
+}
+
+// The uid for `--owner-only`: a number as is, otherwise the named account's
+#[cfg(unix)]
+pub fn parse_user(user: &str) -> Result<u32, String> {
+    if let Ok(uid) = user.parse() {
+        return Ok(uid);
+    }
+    let c_name = std::ffi::CString::new(user).map_err(|_| format!("Unknown user {}", user))?;
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    // SAFETY: as in `user_name`, with a NUL-terminated name
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let status = unsafe {
+        libc::getpwnam_r(
+            c_name.as_ptr(),
+            &mut entry,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() {
+        return Err(format!("Unknown user {}", user));
+    }
+    Ok(entry.pw_uid)
+}
+
+#[cfg(not(unix))]
+pub fn parse_user(_user: &str) -> Result<u32, String> {
+    Err("--owner-only is only supported on Unix".to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_owners_by_uid_and_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let me = unsafe { libc::getuid() };
+        let owner = OwnerCache::default()
+            .owner(&fs::metadata(dir.path()).unwrap())
+            .unwrap();
+        assert_eq!(owner.uid, me);
+
+        assert_eq!(user_name(0).as_deref(), Some("root"));
+        assert_eq!(parse_user("root"), Ok(0));
+        assert_eq!(parse_user("1234"), Ok(1234));
+        assert!(parse_user("no-such-user-here").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use directories::ProjectDirs;
+use std::path::PathBuf;
+
+// The one place that decides where our files live: XDG directories on Linux,
+// ~/Library on macOS and %APPDATA% on Windows. `None` when there's no home directory.
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "disk-cleaner")
+}
+
+// Settings the user edits, such as `config.toml`
+pub fn config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+// Anything that can be rebuilt if deleted, such as the checkpoints of interrupted scans
+pub fn cache_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+// Data worth keeping, such as the history of earlier runs
+pub fn data_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_dir().to_path_buf())
+}
+
+// State carried between runs, such as the last selection. XDG_STATE_HOME on Linux;
+// other platforms have no separate place for it, so it goes with the local data
+pub fn state_dir() -> Option<PathBuf> {
+    let dirs = project_dirs()?;
+    Some(
+        dirs.state_dir()
+            .unwrap_or_else(|| dirs.data_local_dir())
+            .to_path_buf(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[cfg(target_os = "linux")]
+    fn xdg(var: &str, fallback: &str) -> PathBuf {
+        match std::env::var_os(var) {
+            Some(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME").unwrap()).join(fallback),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn follows_xdg_on_linux() {
+        let app = Path::new("disk-cleaner");
+        assert_eq!(
+            config_dir(),
+            Some(xdg("XDG_CONFIG_HOME", ".config").join(app))
+        );
+        assert_eq!(cache_dir(), Some(xdg("XDG_CACHE_HOME", ".cache").join(app)));
+        assert_eq!(
+            data_dir(),
+            Some(xdg("XDG_DATA_HOME", ".local/share").join(app))
+        );
+        assert_eq!(
+            state_dir(),
+            Some(xdg("XDG_STATE_HOME", ".local/state").join(app))
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn uses_library_on_macos() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        assert!(config_dir().unwrap().starts_with(home.join("Library")));
+        assert!(
+            cache_dir()
+                .unwrap()
+                .starts_with(home.join("Library/Caches"))
+        );
+        assert!(data_dir().unwrap().starts_with(home.join("Library")));
+        assert!(state_dir().unwrap().starts_with(home.join("Library")));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn uses_appdata_on_windows() {
+        let roaming = PathBuf::from(std::env::var_os("APPDATA").unwrap());
+        let local = PathBuf::from(std::env::var_os("LOCALAPPDATA").unwrap());
+        assert!(config_dir().unwrap().starts_with(&roaming));
+        assert!(data_dir().unwrap().starts_with(&roaming));
+        assert!(cache_dir().unwrap().starts_with(&local));
+        assert!(state_dir().unwrap().starts_with(&local));
+    }
+
+    #[test]
+    fn every_directory_is_our_own() {
+        for dir in [config_dir(), cache_dir(), data_dir(), state_dir()] {
+            let dir = dir.unwrap();
+            assert!(dir.is_absolute());
+            assert!(dir.to_string_lossy().contains("disk-cleaner"));
+        }
+    }
+}
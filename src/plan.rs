@@ -0,0 +1,205 @@
+use crate::app::DirInfo;
+use crate::scanner::{self, SizeOptions};
+use crate::units::{SizeUnits, human_size};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+// A cleanup to review before it runs: written by `--plan`, carried out by `--apply`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    // The scanned directory; only paths inside it are ever removed
+    pub root: PathBuf,
+    #[serde(default, rename = "candidate")]
+    pub candidates: Vec<Candidate>,
+}
+
+// One match; `delete` starts out as the scan's selection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub delete: bool,
+    // For the reviewer only; sizes are measured again when the plan is applied
+    #[serde(default)]
+    pub size: String,
+    #[serde(default)]
+    pub modified_days_ago: u32,
+}
+
+const HEADER: &str = "# disk-cleaner plan. Set `delete = false` on anything to keep, then run\n\
+                      # `disk-cleaner --apply <this file>`. Only `delete = true` entries are removed.\n\n";
+
+impl Plan {
+    // Every match, marked for deletion when it's selected
+    pub fn from_dirs(root: &Path, dirs: &[DirInfo], units: SizeUnits) -> Self {
+        Plan {
+            root: scanner::absolute_path(root),
+            candidates: dirs
+                .iter()
+                .map(|dir| Candidate {
+                    path: scanner::absolute_path(&dir.path),
+                    delete: dir.selected,
+                    size: human_size(dir.size_bytes, units),
+                    modified_days_ago: dir.modified_days_ago,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        let body = toml::to_string(self).map_err(|err| err.to_string())?;
+        Ok(format!("{}{}", HEADER, body))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|err| err.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+        Self::parse(&contents).map_err(|err| format!("Invalid plan {}: {}", path.display(), err))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_toml().map_err(io::Error::other)?)
+    }
+
+    // The approved candidates that are still safe to remove, measured now, and the
+    // approved ones that aren't, with the reason
+    pub fn approved(&self, options: SizeOptions) -> (Vec<DirInfo>, Vec<(PathBuf, String)>) {
+        let root = scanner::absolute_path(&self.root);
+        let mut ready = Vec::new();
+        let mut skipped = Vec::new();
+        for candidate in self.candidates.iter().filter(|c| c.delete) {
+            match check(&candidate.path, &root) {
+                Ok(modified) => {
+                    let stats = scanner::directory_stats(&candidate.path, options);
+                    ready.push(DirInfo {
+                        path: candidate.path.clone(),
+                        matched_name: candidate
+                            .path
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        system: false,
+                        modified_days_ago: candidate.modified_days_ago,
+                        modified,
+                        selected: true,
+                        size_bytes: stats.size_bytes,
+                        file_count: stats.file_count,
+                        unreadable: stats.unreadable,
+                        owner: None,
+                    })
+                }
+                Err(reason) => skipped.push((candidate.path.clone(), reason.to_string())),
+            }
+        }
+        (ready, skipped)
+    }
+}
+
+// A plan may have been edited, or the tree changed since it was written: only real
+// directories under the plan's root, never the root itself, are removed
+fn check(path: &Path, root: &Path) -> Result<SystemTime, &'static str> {
+    let escapes = path.components().any(|c| c == Component::ParentDir);
+    let absolute = scanner::absolute_path(path);
+    if escapes || absolute == root || !absolute.starts_with(root) {
+        return Err("outside the plan's root");
+    }
+    let metadata = fs::symlink_metadata(path).map_err(|_| "no longer exists")?;
+    if !metadata.is_dir() {
+        return Err("not a directory");
+    }
+    Ok(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir_info(path: &Path, selected: bool) -> DirInfo {
+        DirInfo {
+            modified_days_ago: 40,
+            selected,
+            size_bytes: 2048,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    #[test]
+    fn plan_round_trips_through_toml() {
+        let plan = Plan::from_dirs(
+            Path::new("/projects"),
+            &[
+                dir_info(Path::new("/projects/a/target"), true),
+                dir_info(Path::new("/projects/it's here/target"), false),
+            ],
+            SizeUnits::Binary,
+        );
+        let written = plan.to_toml().unwrap();
+        assert!(written.starts_with("# disk-cleaner plan."));
+        assert!(written.contains("[[candidate]]"));
+        assert!(written.contains("size = \"2 KiB\""));
+        assert_eq!(Plan::parse(&written).unwrap(), plan);
+
+        // Only path and delete are needed, and typos are rejected
+        let minimal = "root = \"/p\"\n[[candidate]]\npath = \"/p/a\"\ndelete = true\n";
+        assert_eq!(Plan::parse(minimal).unwrap().candidates.len(), 1);
+        assert!(
+            Plan::parse("root = \"/p\"\n[[candidate]]\npath = \"/p/a\"\ndelet = true\n").is_err()
+        );
+    }
+
+    #[test]
+    fn only_approved_directories_under_the_root_are_ready() {
+        let root = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let approved = root.path().join("a/target");
+        let kept = root.path().join("b/target");
+        let file = root.path().join("c/target");
+        fs::create_dir_all(&approved).unwrap();
+        fs::write(approved.join("bin"), vec![0; 100]).unwrap();
+        fs::create_dir_all(&kept).unwrap();
+        fs::create_dir_all(root.path().join("c")).unwrap();
+        fs::write(&file, "").unwrap();
+
+        let candidate = |path: &Path, delete: bool| Candidate {
+            path: path.to_path_buf(),
+            delete,
+            size: String::new(),
+            modified_days_ago: 0,
+        };
+        let plan = Plan {
+            root: root.path().to_path_buf(),
+            candidates: vec![
+                candidate(&approved, true),
+                candidate(&kept, false),
+                candidate(&file, true),
+                candidate(&root.path().join("gone/target"), true),
+                candidate(elsewhere.path(), true),
+                candidate(&root.path().join("a/../../escaped"), true),
+                candidate(root.path(), true),
+            ],
+        };
+        let (ready, skipped) = plan.approved(SizeOptions::default());
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].path, approved);
+        assert!(ready[0].size_bytes >= 100);
+        let reasons: Vec<&str> = skipped.iter().map(|(_, r)| r.as_str()).collect();
+        assert_eq!(
+            reasons,
+            [
+                "not a directory",
+                "no longer exists",
+                "outside the plan's root",
+                "outside the plan's root",
+                "outside the plan's root"
+            ]
+        );
+    }
+}
@@ -1,22 +1,1935 @@
+use crate::app::{DirInfo, ScanUpdate};
+use crate::checkpoint::{self, Checkpoint, Frontier};
+use crate::mounts;
+use crate::owners::OwnerCache;
+use glob::Pattern;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
 
-pub fn calculate_directory_size(path: &PathBuf) -> u64 {
-    let mut total_size = 0u64;
+// Options controlling how directory sizes are measured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeOptions {
+    // Count a hardlinked file once per link instead of once per inode
+    pub count_hard_links: bool,
+    // Count allocated blocks (like `du`) instead of apparent file length
+    pub disk_usage: bool,
+}
+
+// Matches older than this many days are pre-selected
+pub const DEFAULT_SELECT_AGE_DAYS: u32 = 30;
+
+// Ignore pattern for hidden (dot) directories, on unless hidden trees are included
+pub const HIDDEN_PATTERN: &str = ".*";
+
+// Ignore patterns every scan starts with
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[HIDDEN_PATTERN];
+
+// Everything the scan thread needs to decide which directories to report
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanConfig {
+    pub root: PathBuf,
+    // The root as given, when it was a symlink resolved into `root`
+    pub root_link: Option<PathBuf>,
+    // Walk the directory a symlinked root points to; when off, such a root isn't scanned
+    pub follow_root_symlink: bool,
+    // Directory names to look for
+    pub folders_to_clean: Vec<String>,
+    // Glob patterns matched against directory names, in addition to the exact names
+    pub target_globs: Vec<String>,
+    // Glob patterns for directories to skip
+    pub ignore_patterns: Vec<String>,
+    pub match_full_path: bool,
+    // Absolute paths that are never walked
+    pub exclude_paths: Vec<PathBuf>,
+    // Absolute paths whose matches are walked past but never reported
+    pub protected_paths: Vec<PathBuf>,
+    pub min_size_bytes: u64,
+    // Skip matches holding fewer files than this
+    pub min_files: u64,
+    // Skip matches that are empty, as far as could be read
+    pub hide_empty: bool,
+    pub min_age_days: u32,
+    pub modified_before: Option<SystemTime>,
+    // Only report matches owned by this uid
+    pub owner_only: Option<u32>,
+    // Only report matches this many levels below the root, or more; the root's children
+    // are at depth 1
+    pub min_depth: usize,
+    // Only report matches at most this many levels below the root
+    pub max_depth: Option<usize>,
+    pub max_threads: usize,
+    pub size_options: SizeOptions,
+    // Don't descend into network or removable mounts
+    pub local_only: bool,
+    // Pre-select matches older than `select_older_than_days`
+    pub auto_select: bool,
+    pub select_older_than_days: u32,
+    // Stop the scan once this many matches have been reported
+    pub max_matches: Option<usize>,
+    // Report matches as soon as they're found and measure them in the background,
+    // instead of holding up the walk until each one is sized
+    pub concurrent_sizes: bool,
+    // Where to save progress while walking, so an interrupted scan can be resumed
+    pub checkpoint: Option<PathBuf>,
+    // Start from the saved checkpoint, skipping what it already walked
+    pub resume: bool,
+    // More directories to scan the same way as `root`, reported as one scan
+    pub extra_roots: Vec<PathBuf>,
+    // Whether `root` and `extra_roots` are walked one after another or several at once
+    pub root_concurrency: RootConcurrency,
+}
+
+// How a scan over several roots goes through them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RootConcurrency {
+    // One after another, so events arrive root by root and only one walk runs at a time
+    #[default]
+    Sequential,
+    // Up to this many roots at once, their events interleaved
+    Parallel(usize),
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        ScanConfig {
+            root: PathBuf::from("."),
+            root_link: None,
+            follow_root_symlink: true,
+            folders_to_clean: vec!["node_modules".to_string(), "target".to_string()],
+            target_globs: Vec::new(),
+            ignore_patterns: DEFAULT_IGNORE_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            match_full_path: false,
+            exclude_paths: Vec::new(),
+            protected_paths: Vec::new(),
+            min_size_bytes: 0,
+            min_files: 0,
+            hide_empty: false,
+            min_age_days: 0,
+            modified_before: None,
+            owner_only: None,
+            min_depth: 0,
+            max_depth: None,
+            max_threads: default_thread_count(),
+            size_options: SizeOptions::default(),
+            local_only: false,
+            auto_select: true,
+            select_older_than_days: DEFAULT_SELECT_AGE_DAYS,
+            max_matches: None,
+            concurrent_sizes: false,
+            checkpoint: None,
+            resume: false,
+            extra_roots: Vec::new(),
+            root_concurrency: RootConcurrency::Sequential,
+        }
+    }
+}
+
+impl ScanConfig {
+    pub fn builder() -> ScanConfigBuilder {
+        ScanConfigBuilder::default()
+    }
+
+    // Resolve a symlinked root up front, so the header and every reported path show the
+    // directory that is really scanned
+    pub fn resolve_root(&mut self) {
+        self.root_link = None;
+        if self.follow_root_symlink
+            && is_symlink(&self.root)
+            && let Ok(resolved) = fs::canonicalize(&self.root)
+        {
+            self.root_link = Some(std::mem::replace(&mut self.root, resolved));
+        }
+    }
+
+    // Whether a match at `depth` below the root is inside the depth range
+    pub fn in_depth_range(&self, depth: usize) -> bool {
+        depth >= self.min_depth && self.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    // Whether a measured match is big enough, by bytes and by files, to report
+    pub fn keeps(&self, stats: DirStats) -> bool {
+        stats.size_bytes >= self.min_size_bytes
+            && stats.file_count >= self.min_files
+            && !(self.hide_empty && stats.is_empty())
+    }
+}
+
+// Builds a `ScanConfig`, starting from the defaults
+#[derive(Debug, Clone, Default)]
+pub struct ScanConfigBuilder {
+    config: ScanConfig,
+}
+
+impl ScanConfigBuilder {
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.config.root = root.into();
+        self
+    }
+
+    // Replace the default target names
+    pub fn targets(mut self, targets: Vec<String>) -> Self {
+        self.config.folders_to_clean = targets;
+        self
+    }
+
+    pub fn target_globs(mut self, globs: Vec<String>) -> Self {
+        self.config.target_globs = globs;
+        self
+    }
+
+    // Walk into hidden directories by dropping the default `.*` ignore pattern
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        if include_hidden {
+            self.config.ignore_patterns.retain(|p| p != HIDDEN_PATTERN);
+        }
+        self
+    }
+
+    // Add ignore patterns on top of the defaults, skipping duplicates
+    pub fn ignore(mut self, patterns: &[String]) -> Self {
+        for pattern in patterns {
+            if !self.config.ignore_patterns.contains(pattern) {
+                self.config.ignore_patterns.push(pattern.clone());
+            }
+        }
+        self
+    }
+
+    pub fn match_full_path(mut self, match_full_path: bool) -> Self {
+        self.config.match_full_path = match_full_path;
+        self
+    }
+
+    // Paths are made absolute so they compare against walked paths
+    pub fn exclude(mut self, paths: &[PathBuf]) -> Self {
+        self.config.exclude_paths = paths.iter().map(|p| absolute_path(p)).collect();
+        self
+    }
+
+    pub fn protect(mut self, paths: &[PathBuf]) -> Self {
+        self.config.protected_paths = paths.iter().map(|p| absolute_path(p)).collect();
+        self
+    }
+
+    pub fn min_size_bytes(mut self, bytes: u64) -> Self {
+        self.config.min_size_bytes = bytes;
+        self
+    }
+
+    pub fn min_files(mut self, files: u64) -> Self {
+        self.config.min_files = files;
+        self
+    }
+
+    pub fn hide_empty(mut self, hide: bool) -> Self {
+        self.config.hide_empty = hide;
+        self
+    }
+
+    pub fn min_age_days(mut self, days: u32) -> Self {
+        self.config.min_age_days = days;
+        self
+    }
+
+    pub fn owner_only(mut self, uid: Option<u32>) -> Self {
+        self.config.owner_only = uid;
+        self
+    }
+
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.config.min_depth = depth;
+        self
+    }
+
+    pub fn max_depth(mut self, depth: Option<usize>) -> Self {
+        self.config.max_depth = depth;
+        self
+    }
+
+    pub fn modified_before(mut self, cutoff: Option<SystemTime>) -> Self {
+        self.config.modified_before = cutoff;
+        self
+    }
+
+    pub fn max_threads(mut self, threads: usize) -> Self {
+        self.config.max_threads = threads.max(1);
+        self
+    }
+
+    pub fn size_options(mut self, options: SizeOptions) -> Self {
+        self.config.size_options = options;
+        self
+    }
+
+    pub fn local_only(mut self, local_only: bool) -> Self {
+        self.config.local_only = local_only;
+        self
+    }
+
+    pub fn auto_select(mut self, auto_select: bool) -> Self {
+        self.config.auto_select = auto_select;
+        self
+    }
+
+    pub fn select_older_than_days(mut self, days: u32) -> Self {
+        self.config.select_older_than_days = days;
+        self
+    }
+
+    pub fn max_matches(mut self, limit: Option<usize>) -> Self {
+        self.config.max_matches = limit;
+        self
+    }
+
+    pub fn concurrent_sizes(mut self, enabled: bool) -> Self {
+        self.config.concurrent_sizes = enabled;
+        self
+    }
+
+    pub fn checkpoint(mut self, file: Option<PathBuf>) -> Self {
+        self.config.checkpoint = file;
+        self
+    }
+
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.config.resume = resume;
+        self
+    }
+
+    pub fn extra_roots(mut self, roots: Vec<PathBuf>) -> Self {
+        self.config.extra_roots = roots;
+        self
+    }
+
+    pub fn root_concurrency(mut self, concurrency: RootConcurrency) -> Self {
+        self.config.root_concurrency = concurrency;
+        self
+    }
+
+    pub fn follow_root_symlink(mut self, follow: bool) -> Self {
+        self.config.follow_root_symlink = follow;
+        self
+    }
+
+    pub fn build(mut self) -> ScanConfig {
+        self.config.resolve_root();
+        self.config
+    }
+}
+
+// Default number of threads used for size calculation
+pub fn default_thread_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+// Build the thread pool that `calculate_directory_size` runs on
+pub fn build_thread_pool(threads: usize) -> Result<rayon::ThreadPool, rayon::ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.max(1))
+        .thread_name(|i| format!("size-worker-{}", i))
+        .build()
+}
+
+// Compile ignore patterns, failing on the first invalid one
+pub fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, glob::PatternError> {
+    patterns.iter().map(|p| Pattern::new(p)).collect()
+}
+
+// The target a directory name matches: an exact name first, since that's the common case,
+// then the glob patterns
+pub fn matching_target<'a>(
+    name: &str,
+    targets: &'a [String],
+    globs: &'a [Pattern],
+) -> Option<&'a str> {
+    if let Some(target) = targets.iter().find(|t| *t == name) {
+        return Some(target);
+    }
+    globs
+        .iter()
+        .find(|glob| glob.matches(name))
+        .map(|glob| glob.as_str())
+}
+
+// Find the first ignore pattern matching a directory, given its path relative to the
+// scan root. By default only the basename is matched; `full_path` matches the whole
+// relative path so patterns like `**/cache` or `build/**` work.
+pub fn matching_pattern<'a>(
+    relative: &Path,
+    patterns: &'a [Pattern],
+    full_path: bool,
+) -> Option<&'a Pattern> {
+    if full_path {
+        patterns.iter().find(|p| p.matches_path(relative))
+    } else {
+        let name = relative.file_name().unwrap_or_default().to_string_lossy();
+        patterns.iter().find(|p| p.matches(&name))
+    }
+}
+
+// How the walk treats an error reading part of the tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkErrorAction {
+    // Likely to clear up, e.g. a busy or briefly unreachable network path
+    Retry,
+    // Won't get better by itself, e.g. permission denied; skip it but tell the user
+    Warn,
+    // Nothing worth reporting, e.g. the entry was deleted mid-walk
+    Ignore,
+}
+
+// How many times, and how soon, a transient walk error is retried; the delay doubles
+pub const WALK_RETRIES: u32 = 3;
+pub const WALK_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+pub fn classify_io_error(kind: io::ErrorKind) -> WalkErrorAction {
+    use io::ErrorKind::*;
+
+    match kind {
+        Interrupted
+        | WouldBlock
+        | TimedOut
+        | ResourceBusy
+        | ConnectionReset
+        | ConnectionAborted
+        | NetworkDown
+        | StaleNetworkFileHandle => WalkErrorAction::Retry,
+        NotFound => WalkErrorAction::Ignore,
+        _ => WalkErrorAction::Warn,
+    }
+}
+
+// Errors without an underlying I/O error, like symlink loops, are warned about
+pub fn classify_walk_error(err: &walkdir::Error) -> WalkErrorAction {
+    err.io_error().map_or(WalkErrorAction::Warn, |io_err| {
+        classify_io_error(io_err.kind())
+    })
+}
+
+// Run `op`, retrying transient failures up to `retries` times with a doubling delay
+pub fn retry_transient<T>(
+    retries: u32,
+    backoff: Duration,
+    mut op: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut delay = backoff;
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(err)
+                if attempt < retries && classify_io_error(err.kind()) == WalkErrorAction::Retry =>
+            {
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Make a path absolute without touching the filesystem, so rules compare lexically
+pub fn absolute_path(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Levels from `root` down to `path`; the root's children are at depth 1
+pub fn depth_below(root: &Path, path: &Path) -> usize {
+    path.strip_prefix(root)
+        .map_or(0, |relative| relative.components().count())
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+// Prefix long paths with `\\?\` so Win32 file APIs don't fail past MAX_PATH (260 chars)
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+
+    const MAX_PATH: usize = 260;
+    let absolute = absolute_path(path);
+    let text = absolute.as_os_str().to_string_lossy();
+    if text.len() < MAX_PATH || text.starts_with(r"\\?\") {
+        return path.to_path_buf();
+    }
+    // UNC paths (`\\server\share\...`) take the `\\?\UNC\` form
+    let prefixed = match text.strip_prefix(r"\\") {
+        Some(unc) => OsString::from(format!(r"\\?\UNC\{}", unc)),
+        None => {
+            let mut prefixed = OsString::from(r"\\?\");
+            prefixed.push(absolute.as_os_str());
+            prefixed
+        }
+    };
+    PathBuf::from(prefixed)
+}
+
+// Only Windows limits path length this way
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+// Whether `path` is itself one of the given mount points
+pub fn is_mount_point(path: &Path, mount_points: &[PathBuf]) -> bool {
+    !mount_points.is_empty() && mount_points.contains(&absolute_path(path))
+}
+
+// Find the rule path (already absolute) that contains `path`, if any
+pub fn containing_rule<'a>(path: &Path, rules: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    if rules.is_empty() {
+        return None;
+    }
+    let path = absolute_path(path);
+    rules.iter().find(|rule| path.starts_with(rule))
+}
+
+// Prefixes managed by the OS or a package manager, where deleting a match can break things
+#[cfg(unix)]
+const SYSTEM_PREFIXES: &[&str] = &[
+    "/bin",
+    "/boot",
+    "/etc",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/opt",
+    "/sbin",
+    "/usr",
+    "/var/lib",
+    "/System",
+    "/Library",
+    "/Applications",
+];
+#[cfg(windows)]
+const SYSTEM_PREFIXES: &[&str] = &[
+    r"C:\Windows",
+    r"C:\Program Files",
+    r"C:\Program Files (x86)",
+    r"C:\ProgramData",
+];
+#[cfg(not(any(unix, windows)))]
+const SYSTEM_PREFIXES: &[&str] = &[];
 
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.flatten() {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_dir() {
-                    // Recursive call for subdirectories
-                    total_size += calculate_directory_size(&entry.path());
+// Whether the path lies under a system prefix
+pub fn is_system_path(path: &Path) -> bool {
+    let path = absolute_path(path);
+    SYSTEM_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+// A match that probably isn't the user's to delete: under a system prefix, or owned by root
+// when the user (judged by who owns their home directory) isn't root
+pub fn is_system_dir(path: &Path, metadata: &fs::Metadata) -> bool {
+    is_system_path(path) || owned_by_root_for_regular_user(metadata)
+}
+
+#[cfg(unix)]
+fn owned_by_root_for_regular_user(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let home_uid = std::env::var_os("HOME")
+        .and_then(|home| fs::metadata(home).ok())
+        .map(|home| home.uid());
+    metadata.uid() == 0 && home_uid.is_some_and(|uid| uid != 0)
+}
+
+#[cfg(not(unix))]
+fn owned_by_root_for_regular_user(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+// Whole days since the directory was last modified
+pub fn days_since_modified(metadata: &fs::Metadata) -> u32 {
+    days_between(metadata.modified().unwrap_or(UNIX_EPOCH), SystemTime::now())
+}
+
+// Whole days from `modified` to `now`; pre-epoch and future timestamps never panic
+fn days_between(modified: SystemTime, now: SystemTime) -> u32 {
+    let elapsed = now.duration_since(modified).unwrap_or(Duration::ZERO);
+    (elapsed.as_secs() / (24 * 60 * 60)).min(u32::MAX as u64) as u32
+}
+
+// Whether the directory was last modified before `cutoff`
+pub fn modified_before(metadata: &fs::Metadata, cutoff: SystemTime) -> bool {
+    metadata.modified().unwrap_or(UNIX_EPOCH) < cutoff
+}
+
+// Walk `config.root` and report what it finds to `on_event`: every directory visited,
+// each match, unreadable parts of the tree and, last, `Done`. Sizes are measured on a
+// pool of `config.max_threads`; with `concurrent_sizes` they arrive later as `SizeReady`,
+// still before `Done`. Setting `stop` ends the walk early; reaching `max_matches` sets it.
+// With a `checkpoint` file, progress is saved as the walk goes and kept if it's stopped;
+// `resume` reports the saved matches first and walks only what's left. With
+// `extra_roots`, each root is walked in turn or alongside the others, as `scan_roots` does.
+pub fn scan(config: &ScanConfig, on_event: impl FnMut(ScanUpdate), stop: &AtomicBool) {
+    let size_pool =
+        build_thread_pool(config.max_threads).expect("Failed to build size thread pool");
+    if config.extra_roots.is_empty() {
+        scan_root(config, &size_pool, on_event, stop);
+    } else {
+        scan_roots(config, &size_pool, on_event, stop);
+    }
+}
+
+// How often `scan_roots` passes a stop on to the walks while no events arrive
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// `root` and every one of `extra_roots` as one scan: their events, then a single `Done`.
+// Up to `root_concurrency` walks run at once on worker threads, sharing `size_pool`, and
+// their events reach `on_event` on this thread. Each walk has its own stop flag, so one
+// root reaching `max_matches` doesn't cut the others short; `stop` ends them all.
+fn scan_roots(
+    config: &ScanConfig,
+    size_pool: &rayon::ThreadPool,
+    mut on_event: impl FnMut(ScanUpdate),
+    stop: &AtomicBool,
+) {
+    // A checkpoint belongs to a single root, so it isn't kept for several
+    let configs: Vec<ScanConfig> = std::iter::once(&config.root)
+        .chain(&config.extra_roots)
+        .map(|root| {
+            let mut root_config = ScanConfig {
+                root: root.clone(),
+                extra_roots: Vec::new(),
+                checkpoint: None,
+                resume: false,
+                ..config.clone()
+            };
+            root_config.resolve_root();
+            root_config
+        })
+        .collect();
+    let stopped = stop.load(Ordering::SeqCst);
+    let stops: Vec<AtomicBool> = configs.iter().map(|_| AtomicBool::new(stopped)).collect();
+    let workers = match config.root_concurrency {
+        RootConcurrency::Sequential => 1,
+        RootConcurrency::Parallel(limit) => limit.clamp(1, configs.len()),
+    };
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|walkers| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (configs, stops, next) = (&configs, &stops, &next);
+            walkers.spawn(move || {
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(root_config) = configs.get(index) else {
+                        break;
+                    };
+                    let send = |update| {
+                        if !matches!(update, ScanUpdate::Done) {
+                            let _ = tx.send(update);
+                        }
+                    };
+                    scan_root(root_config, size_pool, send, &stops[index]);
+                }
+            });
+        }
+        drop(tx);
+        loop {
+            match rx.recv_timeout(STOP_POLL_INTERVAL) {
+                Ok(update) => on_event(update),
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+            if stop.load(Ordering::SeqCst) {
+                for root_stop in &stops {
+                    root_stop.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    });
+    on_event(ScanUpdate::Done);
+}
+
+// One root's walk, measuring sizes on `size_pool`; see `scan`
+fn scan_root(
+    config: &ScanConfig,
+    size_pool: &rayon::ThreadPool,
+    mut on_event: impl FnMut(ScanUpdate),
+    stop: &AtomicBool,
+) {
+    let ignore_patterns =
+        compile_patterns(&config.ignore_patterns).expect("Failed to compile glob pattern");
+    let target_globs =
+        compile_patterns(&config.target_globs).expect("Failed to compile glob pattern");
+    let non_local_mounts = if config.local_only {
+        mounts::non_local_mount_points()
+    } else {
+        Vec::new()
+    };
+    // Left unresolved by `resolve_root`: following it was turned off, or it leads nowhere
+    if is_symlink(&config.root) {
+        let reason = if config.follow_root_symlink {
+            "the scan root is a symlink to a directory that doesn't exist"
+        } else {
+            "the scan root is a symlink, and following it is turned off"
+        };
+        on_event(ScanUpdate::Skipped(config.root.clone(), reason.to_string()));
+        on_event(ScanUpdate::Done);
+        return;
+    }
+    // The root, plus directories that failed to list and came good on retry
+    let mut pending = vec![config.root.clone()];
+    let mut reported = 0;
+    let mut limited = false;
+    // Background sizes come back here, so `on_event` is only ever called on this thread
+    let (size_tx, size_rx) = mpsc::channel();
+
+    let saved = match &config.checkpoint {
+        Some(file) if config.resume => checkpoint::load(file, &config.root),
+        _ => None,
+    }
+    .unwrap_or_default();
+    // Directories the saved scan already walked, or reported as matches
+    let resumed: HashSet<PathBuf> = saved
+        .done
+        .iter()
+        .cloned()
+        .chain(saved.matches.iter().map(|dir| dir.path.clone()))
+        .collect();
+    let mut frontier = Frontier::new(saved.done);
+    // Everything reported so far, for the next checkpoint
+    let mut found: BTreeMap<PathBuf, DirInfo> = BTreeMap::new();
+    let mut last_save = Instant::now();
+    let mut owners = OwnerCache::default();
+
+    // Background sizes finish before `Done`, so none of them get lost
+    size_pool.in_place_scope(|sizes| {
+        let measure_later = |path: PathBuf| {
+            let size_tx = size_tx.clone();
+            let options = config.size_options;
+            sizes.spawn(move |_| {
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                let stats = directory_stats(&path, options);
+                if stats.unreadable {
+                    let _ = size_tx.send(unreadable_warning(&path));
+                }
+                let _ = size_tx.send(ScanUpdate::SizeReady(path, stats));
+            });
+        };
+
+        for dir in saved.matches {
+            let stats = DirStats {
+                size_bytes: dir.size_bytes,
+                file_count: dir.file_count,
+                unreadable: dir.unreadable,
+            };
+            found.insert(dir.path.clone(), dir.clone());
+            on_event(ScanUpdate::Result(dir.clone()));
+            if config.concurrent_sizes {
+                // Stopped before its size came in, most likely
+                if stats == DirStats::default() {
+                    measure_later(dir.path);
                 } else {
-                    // Add file size
-                    total_size += metadata.len();
+                    on_event(ScanUpdate::SizeReady(dir.path, stats));
                 }
             }
+            reported += 1;
         }
+
+        'walk: while let Some(start) = pending.pop() {
+            let retried = start != config.root;
+            let mut it = WalkDir::new(&start).into_iter();
+
+            loop {
+                for update in size_rx.try_iter() {
+                    record_size(&mut found, &update, config);
+                    on_event(update);
+                }
+                if stop.load(Ordering::SeqCst) {
+                    break 'walk;
+                }
+                if let Some(file) = &config.checkpoint
+                    && last_save.elapsed() >= checkpoint::SAVE_INTERVAL
+                {
+                    save_checkpoint(file, config, &frontier, &found);
+                    last_save = Instant::now();
+                }
+                let entry = match it.next() {
+                    Some(Ok(entry)) => entry,
+                    Some(Err(err)) => {
+                        let path = err.path().map(Path::to_path_buf).unwrap_or_default();
+                        let action = classify_walk_error(&err);
+                        if action == WalkErrorAction::Retry
+                            && retry_transient(WALK_RETRIES, WALK_RETRY_BACKOFF, || {
+                                fs::read_dir(&path)
+                            })
+                            .is_ok()
+                        {
+                            frontier.abandon(&path);
+                            pending.push(path);
+                        } else if action != WalkErrorAction::Ignore {
+                            on_event(ScanUpdate::Skipped(path, err.to_string()));
+                        }
+                        continue;
+                    }
+                    None => {
+                        frontier.finish();
+                        break;
+                    }
+                };
+                let path = entry.path();
+                frontier.visit(path, entry.file_type().is_dir());
+                // A retried directory itself was already reported and checked
+                if retried && entry.depth() == 0 {
+                    continue;
+                }
+
+                if entry.file_type().is_dir() {
+                    if resumed.contains(path) {
+                        it.skip_current_dir();
+                        continue;
+                    }
+                    on_event(ScanUpdate::Path(path.to_path_buf()));
+
+                    // The scan root itself is always walked
+                    if entry.depth() > 0 {
+                        // Check against excluded paths, ignore patterns and non-local mounts
+                        let relative = path.strip_prefix(&config.root).unwrap_or(path);
+                        if containing_rule(path, &config.exclude_paths).is_some()
+                            || is_mount_point(path, &non_local_mounts)
+                            || matching_pattern(relative, &ignore_patterns, config.match_full_path)
+                                .is_some()
+                        {
+                            it.skip_current_dir();
+                            continue;
+                        }
+                    }
+                }
+
+                let is_dir = entry.file_type().is_dir();
+                let dir_name = entry.file_name().to_string_lossy();
+
+                let matched = matching_target(&dir_name, &config.folders_to_clean, &target_globs);
+                // Counted from the root, since retried directories start walks of their own
+                let depth = depth_below(&config.root, path);
+
+                if is_dir && let Some(matched_name) = matched {
+                    // Matches are never descended into, whether or not they're reported
+                    it.skip_current_dir();
+
+                    if !config.in_depth_range(depth) {
+                        continue;
+                    }
+                    if containing_rule(path, &config.protected_paths).is_some() {
+                        continue;
+                    }
+                    if let Ok(metadata) = entry.metadata() {
+                        let days_ago = days_since_modified(&metadata);
+                        if days_ago < config.min_age_days {
+                            continue;
+                        }
+                        if let Some(cutoff) = config.modified_before
+                            && !modified_before(&metadata, cutoff)
+                        {
+                            continue;
+                        }
+                        let owner = owners.owner(&metadata);
+                        if let Some(uid) = config.owner_only
+                            && owner.as_ref().is_none_or(|owner| owner.uid != uid)
+                        {
+                            continue;
+                        }
+
+                        // Sized now, or reported as 0 and patched in by `SizeReady`
+                        let dir_stats = if config.concurrent_sizes {
+                            measure_later(path.to_path_buf());
+                            DirStats::default()
+                        } else {
+                            size_pool.install(|| directory_stats(path, config.size_options))
+                        };
+                        if !config.concurrent_sizes && !config.keeps(dir_stats) {
+                            continue;
+                        }
+
+                        if dir_stats.unreadable {
+                            on_event(unreadable_warning(path));
+                        }
+                        let system = is_system_dir(path, &metadata);
+                        let dir_info = DirInfo {
+                            path: path.to_path_buf(),
+                            matched_name: matched_name.to_string(),
+                            modified_days_ago: days_ago,
+                            modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+                            // Auto-select old directories, unless opted out or they look
+                            // like they belong to the system
+                            selected: config.auto_select
+                                && days_ago > config.select_older_than_days
+                                && !system,
+                            size_bytes: dir_stats.size_bytes,
+                            file_count: dir_stats.file_count,
+                            unreadable: dir_stats.unreadable,
+                            system,
+                            owner,
+                        };
+                        found.insert(dir_info.path.clone(), dir_info.clone());
+                        on_event(ScanUpdate::Result(dir_info));
+
+                        reported += 1;
+                        if config.max_matches.is_some_and(|limit| reported >= limit) {
+                            limited = true;
+                            stop.store(true, Ordering::SeqCst);
+                            on_event(ScanUpdate::LimitReached(reported));
+                            break 'walk;
+                        }
+                    }
+                } else if is_dir && config.max_depth.is_some_and(|max| depth >= max) {
+                    // Nothing below the deepest level can be reported
+                    it.skip_current_dir();
+                }
+            }
+        }
+    });
+    drop(size_tx);
+    for update in size_rx {
+        record_size(&mut found, &update, config);
+        on_event(update);
+    }
+    // A scan cut short by `max_matches` is as finished as it's going to get
+    if let Some(file) = &config.checkpoint {
+        if stop.load(Ordering::SeqCst) && !limited {
+            save_checkpoint(file, config, &frontier, &found);
+        } else {
+            checkpoint::clear(file);
+        }
+    }
+    on_event(ScanUpdate::Done);
+}
+
+// Keep a background size for the checkpoint, dropping matches it filters out
+fn record_size(found: &mut BTreeMap<PathBuf, DirInfo>, update: &ScanUpdate, config: &ScanConfig) {
+    if let ScanUpdate::SizeReady(path, stats) = update {
+        if !config.keeps(*stats) {
+            found.remove(path);
+        } else if let Some(dir) = found.get_mut(path) {
+            dir.size_bytes = stats.size_bytes;
+            dir.file_count = stats.file_count;
+            dir.unreadable = stats.unreadable;
+        }
+    }
+}
+
+// A match whose size is a guess, because part of it couldn't be read
+fn unreadable_warning(path: &Path) -> ScanUpdate {
+    ScanUpdate::Skipped(
+        path.to_path_buf(),
+        "could not read all of it, so its size may be too small".to_string(),
+    )
+}
+
+// Saving is best effort: a scan that can't checkpoint still runs
+fn save_checkpoint(
+    file: &Path,
+    config: &ScanConfig,
+    frontier: &Frontier,
+    found: &BTreeMap<PathBuf, DirInfo>,
+) {
+    let _ = checkpoint::save(
+        file,
+        &Checkpoint {
+            root: absolute_path(&config.root),
+            done: frontier.done(),
+            matches: found.values().cloned().collect(),
+        },
+    );
+}
+
+pub fn calculate_directory_size(path: &Path, options: SizeOptions) -> u64 {
+    directory_stats(path, options).size_bytes
+}
+
+// Total size of a directory and the number of files in it, from one walk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirStats {
+    pub size_bytes: u64,
+    // Every file entry, hardlinks included, since each one costs a backup or a copy
+    pub file_count: u64,
+    // Part of the tree couldn't be listed or looked at, so the totals may be short
+    pub unreadable: bool,
+}
+
+impl DirStats {
+    const UNREADABLE: DirStats = DirStats {
+        size_bytes: 0,
+        file_count: 0,
+        unreadable: true,
+    };
+
+    // Nothing to reclaim, and known for sure rather than for lack of access
+    pub fn is_empty(&self) -> bool {
+        self.size_bytes == 0 && !self.unreadable
+    }
+}
+
+impl std::ops::Add for DirStats {
+    type Output = DirStats;
+
+    fn add(self, other: DirStats) -> DirStats {
+        DirStats {
+            size_bytes: self.size_bytes + other.size_bytes,
+            file_count: self.file_count + other.file_count,
+            unreadable: self.unreadable || other.unreadable,
+        }
+    }
+}
+
+pub fn directory_stats(path: &Path, options: SizeOptions) -> DirStats {
+    let seen_inodes = Mutex::new(HashSet::new());
+    // Children of a prefixed path stay prefixed, so deep `node_modules` trees stay readable
+    walk_stats(&long_path(path), options, &seen_inodes)
+}
+
+fn walk_stats(
+    path: &Path,
+    options: SizeOptions,
+    seen_inodes: &Mutex<HashSet<(u64, u64)>>,
+) -> DirStats {
+    let entries: Vec<io::Result<fs::DirEntry>> = match fs::read_dir(path) {
+        Ok(entries) => entries.collect(),
+        Err(_) => return DirStats::UNREADABLE,
+    };
+    let mut unreadable = false;
+    let entries: Vec<fs::DirEntry> = entries
+        .into_iter()
+        .filter_map(|entry| entry.inspect_err(|_| unreadable = true).ok())
+        .collect();
+    let listed = DirStats {
+        unreadable,
+        ..DirStats::default()
+    };
+
+    listed
+        + entries
+            .par_iter()
+            .map(|entry| match entry.metadata() {
+                // Recursive call for subdirectories
+                Ok(metadata) if metadata.is_dir() => {
+                    walk_stats(&entry.path(), options, seen_inodes)
+                }
+                // Add file size, once per inode unless hardlinks are counted separately
+                Ok(metadata) => DirStats {
+                    size_bytes: if options.count_hard_links || is_first_link(&metadata, seen_inodes)
+                    {
+                        file_size(&metadata, options)
+                    } else {
+                        0
+                    },
+                    file_count: 1,
+                    unreadable: false,
+                },
+                Err(_) => DirStats::UNREADABLE,
+            })
+            .reduce(DirStats::default, |a, b| a + b)
+}
+
+// Immediate children of `path` with their sizes, largest first
+pub fn child_sizes(path: &Path, options: SizeOptions) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(long_path(path)) else {
+        return Vec::new();
+    };
+    let mut children: Vec<(PathBuf, u64)> = entries
+        .flatten()
+        .map(|entry| {
+            let size = match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => {
+                    calculate_directory_size(&entry.path(), options)
+                }
+                Ok(metadata) => file_size(&metadata, options),
+                Err(_) => 0,
+            };
+            (entry.path(), size)
+        })
+        .collect();
+    children.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    children
+}
+
+// Bytes under `path` per file extension, the `top` largest first. Extensions are compared
+// without case, and files without one are tallied as `(none)`.
+pub fn extension_sizes(path: &Path, options: SizeOptions, top: usize) -> Vec<(String, u64)> {
+    let seen_inodes = Mutex::new(HashSet::new());
+    let mut sizes: BTreeMap<String, u64> = BTreeMap::new();
+    for entry in WalkDir::new(long_path(path)).into_iter().flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir()
+            || !(options.count_hard_links || is_first_link(&metadata, &seen_inodes))
+        {
+            continue;
+        }
+        let extension = match entry.path().extension() {
+            Some(extension) => format!(".{}", extension.to_string_lossy().to_lowercase()),
+            None => "(none)".to_string(),
+        };
+        *sizes.entry(extension).or_default() += file_size(&metadata, options);
+    }
+    let mut sizes: Vec<(String, u64)> = sizes.into_iter().collect();
+    // Stable, so equal sizes stay in name order
+    sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    sizes.truncate(top);
+    sizes
+}
+
+// Space the file takes: its length, or what's allocated for it on disk when asked,
+// which is less for sparse and compressed files
+#[cfg(unix)]
+fn file_size(metadata: &fs::Metadata, options: SizeOptions) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    if options.disk_usage {
+        // `blocks` is always in 512-byte units, whatever the filesystem's block size
+        metadata.blocks() * 512
+    } else {
+        metadata.len()
+    }
+}
+
+#[cfg(not(unix))]
+fn file_size(metadata: &fs::Metadata, _options: SizeOptions) -> u64 {
+    metadata.len()
+}
+
+// Record the file's inode, returning false if another link to it was already counted
+#[cfg(unix)]
+fn is_first_link(metadata: &fs::Metadata, seen_inodes: &Mutex<HashSet<(u64, u64)>>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.nlink() <= 1 {
+        return true;
+    }
+    seen_inodes
+        .lock()
+        .map(|mut seen| seen.insert((metadata.dev(), metadata.ino())))
+        .unwrap_or(true)
+}
+
+#[cfg(not(unix))]
+fn is_first_link(_metadata: &fs::Metadata, _seen_inodes: &Mutex<HashSet<(u64, u64)>>) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_pool_respects_configured_size() {
+        let pool = build_thread_pool(3).unwrap();
+        assert_eq!(pool.current_num_threads(), 3);
+        assert_eq!(pool.install(rayon::current_num_threads), 3);
+    }
+
+    #[test]
+    fn calculates_nested_size_on_pool() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("one.txt"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("a/two.txt"), vec![0u8; 200]).unwrap();
+        fs::write(dir.path().join("a/b/three.txt"), vec![0u8; 300]).unwrap();
+
+        let pool = build_thread_pool(2).unwrap();
+        let size = pool.install(|| calculate_directory_size(dir.path(), SizeOptions::default()));
+        assert_eq!(size, 600);
+    }
+
+    #[test]
+    fn basename_mode_matches_only_the_final_component() {
+        let patterns = compile_patterns(&["**/tmp".to_string(), "build/**".to_string()]).unwrap();
+        let matched = |path: &str| {
+            matching_pattern(Path::new(path), &patterns, false).map(|p| p.as_str().to_string())
+        };
+        // `**/` also matches zero directories, so the basename `tmp` still matches
+        assert_eq!(matched("a/b/tmp").as_deref(), Some("**/tmp"));
+        // Patterns spanning several components never match a single name
+        assert_eq!(matched("build/cache"), None);
+    }
+
+    #[test]
+    fn full_path_mode_matches_relative_paths() {
+        let patterns = compile_patterns(&["**/tmp".to_string(), "build/**".to_string()]).unwrap();
+        let matched = |path: &str| {
+            matching_pattern(Path::new(path), &patterns, true).map(|p| p.as_str().to_string())
+        };
+        assert_eq!(matched("a/b/tmp").as_deref(), Some("**/tmp"));
+        assert_eq!(matched("tmp").as_deref(), Some("**/tmp"));
+        assert_eq!(matched("build/cache").as_deref(), Some("build/**"));
+        assert_eq!(matched("src/tmpfiles"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hardlinked_files_are_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("file.bin"), vec![0u8; 1000]).unwrap();
+        fs::hard_link(dir.path().join("file.bin"), dir.path().join("sub/link.bin")).unwrap();
+
+        let deduplicated = calculate_directory_size(dir.path(), SizeOptions::default());
+        assert_eq!(deduplicated, 1000);
+
+        let options = SizeOptions {
+            count_hard_links: true,
+            ..SizeOptions::default()
+        };
+        assert_eq!(calculate_directory_size(dir.path(), options), 2000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_counts_allocated_blocks_of_sparse_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = fs::File::create(dir.path().join("sparse.img")).unwrap();
+        file.set_len(64 * 1024 * 1024).unwrap();
+        drop(file);
+
+        let apparent = calculate_directory_size(dir.path(), SizeOptions::default());
+        assert_eq!(apparent, 64 * 1024 * 1024);
+        let on_disk = calculate_directory_size(
+            dir.path(),
+            SizeOptions {
+                disk_usage: true,
+                ..SizeOptions::default()
+            },
+        );
+        assert!(on_disk < apparent);
+    }
+
+    #[test]
+    fn walk_errors_are_classified_by_kind() {
+        use io::ErrorKind;
+
+        for kind in [
+            ErrorKind::Interrupted,
+            ErrorKind::TimedOut,
+            ErrorKind::ResourceBusy,
+            ErrorKind::StaleNetworkFileHandle,
+        ] {
+            assert_eq!(
+                classify_io_error(kind),
+                WalkErrorAction::Retry,
+                "{:?}",
+                kind
+            );
+        }
+        assert_eq!(
+            classify_io_error(ErrorKind::PermissionDenied),
+            WalkErrorAction::Warn
+        );
+        assert_eq!(
+            classify_io_error(ErrorKind::InvalidData),
+            WalkErrorAction::Warn
+        );
+        assert_eq!(
+            classify_io_error(ErrorKind::NotFound),
+            WalkErrorAction::Ignore
+        );
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let mut calls = 0;
+        let result = retry_transient(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::from(io::ErrorKind::ResourceBusy))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: io::Result<()> = retry_transient(3, Duration::ZERO, || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::TimedOut))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+
+        let mut calls = 0;
+        let result: io::Result<()> = retry_transient(3, Duration::ZERO, || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn days_between_handles_pre_epoch_and_future_times() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = UNIX_EPOCH + day * 10;
+        assert_eq!(days_between(UNIX_EPOCH - day * 5, now), 15);
+        assert_eq!(days_between(now + day, now), 0);
+        assert_eq!(days_between(now - day * 3, now), 3);
     }
 
-    total_size
+    #[test]
+    fn builder_starts_from_defaults() {
+        let config = ScanConfig::builder().build();
+        assert_eq!(config, ScanConfig::default());
+        assert_eq!(config.folders_to_clean, vec!["node_modules", "target"]);
+        assert_eq!(config.ignore_patterns, vec![".*"]);
+    }
+
+    #[test]
+    fn include_hidden_drops_only_the_default_pattern() {
+        let config = ScanConfig::builder()
+            .include_hidden(true)
+            .ignore(&["vendor".to_string()])
+            .build();
+        assert_eq!(config.ignore_patterns, vec!["vendor"]);
+        let config = ScanConfig::builder().include_hidden(false).build();
+        assert_eq!(config.ignore_patterns, vec![HIDDEN_PATTERN]);
+    }
+
+    #[test]
+    fn builder_sets_every_option() {
+        let cutoff = UNIX_EPOCH + Duration::from_secs(1_000);
+        let config = ScanConfig::builder()
+            .root("/projects")
+            .targets(vec![".venv".to_string()])
+            .target_globs(vec!["*.egg-info".to_string()])
+            .ignore(&[".*".to_string(), "vendor".to_string()])
+            .match_full_path(true)
+            .exclude(&[PathBuf::from("/projects/archive")])
+            .protect(&[PathBuf::from("/projects/keep")])
+            .min_size_bytes(1024)
+            .min_files(500)
+            .min_age_days(7)
+            .modified_before(Some(cutoff))
+            .max_threads(0)
+            .size_options(SizeOptions {
+                count_hard_links: true,
+                disk_usage: true,
+            })
+            .local_only(true)
+            .auto_select(false)
+            .select_older_than_days(90)
+            .max_matches(Some(1000))
+            .checkpoint(Some(PathBuf::from("/cache/scan.json")))
+            .resume(true)
+            .build();
+
+        assert_eq!(config.root, PathBuf::from("/projects"));
+        assert_eq!(config.folders_to_clean, vec![".venv"]);
+        assert_eq!(config.target_globs, vec!["*.egg-info"]);
+        assert_eq!(config.ignore_patterns, vec![".*", "vendor"]);
+        assert!(config.match_full_path);
+        assert_eq!(
+            config.exclude_paths,
+            vec![PathBuf::from("/projects/archive")]
+        );
+        assert_eq!(
+            config.protected_paths,
+            vec![PathBuf::from("/projects/keep")]
+        );
+        assert_eq!(config.min_size_bytes, 1024);
+        assert_eq!(config.min_files, 500);
+        assert_eq!(config.min_age_days, 7);
+        assert_eq!(config.modified_before, Some(cutoff));
+        assert_eq!(config.max_threads, 1);
+        assert!(config.size_options.count_hard_links);
+        assert!(config.size_options.disk_usage);
+        assert!(config.local_only);
+        assert!(!config.auto_select);
+        assert_eq!(config.select_older_than_days, 90);
+        assert_eq!(config.max_matches, Some(1000));
+        assert_eq!(config.checkpoint, Some(PathBuf::from("/cache/scan.json")));
+        assert!(config.resume);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_are_prefixed_and_measured() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut deep = dir.path().join("node_modules");
+        while deep.as_os_str().len() <= 300 {
+            deep.push("a-rather-long-package-name");
+        }
+        fs::create_dir_all(long_path(&deep)).unwrap();
+        fs::write(long_path(&deep.join("index.js")), b"0123456789").unwrap();
+
+        assert!(long_path(&deep).to_string_lossy().starts_with(r"\\?\"));
+        assert_eq!(long_path(dir.path()), dir.path().to_path_buf());
+        let options = SizeOptions::default();
+        assert_eq!(
+            calculate_directory_size(&dir.path().join("node_modules"), options),
+            10
+        );
+    }
+
+    #[test]
+    fn child_sizes_are_sorted_largest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("small")).unwrap();
+        fs::create_dir_all(dir.path().join("large/nested")).unwrap();
+        fs::write(dir.path().join("small/a"), vec![0u8; 10]).unwrap();
+        fs::write(dir.path().join("large/nested/b"), vec![0u8; 300]).unwrap();
+        fs::write(dir.path().join("medium.bin"), vec![0u8; 50]).unwrap();
+
+        let children = child_sizes(dir.path(), SizeOptions::default());
+        let summary: Vec<(String, u64)> = children
+            .iter()
+            .map(|(path, size)| {
+                (
+                    path.file_name().unwrap().to_string_lossy().to_string(),
+                    *size,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("large".to_string(), 300),
+                ("medium.bin".to_string(), 50),
+                ("small".to_string(), 10)
+            ]
+        );
+        assert!(child_sizes(&dir.path().join("missing"), SizeOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn extension_sizes_tally_bytes_per_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("lib/nested")).unwrap();
+        fs::write(dir.path().join("index.js"), vec![0u8; 400]).unwrap();
+        fs::write(dir.path().join("lib/nested/util.JS"), vec![0u8; 100]).unwrap();
+        fs::write(dir.path().join("lib/index.js.map"), vec![0u8; 120]).unwrap();
+        fs::write(dir.path().join("lib/types.d.ts"), vec![0u8; 30]).unwrap();
+        fs::write(dir.path().join("LICENSE"), vec![0u8; 30]).unwrap();
+
+        let sizes = extension_sizes(dir.path(), SizeOptions::default(), 10);
+        assert_eq!(
+            sizes,
+            vec![
+                (".js".to_string(), 500),
+                (".map".to_string(), 120),
+                ("(none)".to_string(), 30),
+                (".ts".to_string(), 30),
+            ]
+        );
+        assert_eq!(
+            extension_sizes(dir.path(), SizeOptions::default(), 2).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn glob_targets_match_several_directory_names() {
+        let targets = vec!["node_modules".to_string()];
+        let globs = compile_patterns(&["*.egg-info".to_string(), "build-*".to_string()]).unwrap();
+
+        assert_eq!(
+            matching_target("node_modules", &targets, &globs),
+            Some("node_modules")
+        );
+        assert_eq!(
+            matching_target("mypkg.egg-info", &targets, &globs),
+            Some("*.egg-info")
+        );
+        assert_eq!(
+            matching_target("other.egg-info", &targets, &globs),
+            Some("*.egg-info")
+        );
+        assert_eq!(
+            matching_target("build-x86", &targets, &globs),
+            Some("build-*")
+        );
+        assert_eq!(matching_target("build", &targets, &globs), None);
+        assert_eq!(matching_target("src", &targets, &globs), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_under_system_prefixes_are_flagged() {
+        assert!(is_system_path(Path::new("/usr/lib/node_modules")));
+        assert!(is_system_path(Path::new("/usr/local/lib/node_modules")));
+        assert!(!is_system_path(Path::new("/home/me/usr/node_modules")));
+        assert!(!is_system_path(Path::new("/usrlocal/node_modules")));
+
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = fs::metadata(dir.path()).unwrap();
+        let usr_metadata = fs::metadata("/usr").unwrap();
+        assert!(is_system_dir(Path::new("/usr"), &usr_metadata));
+        // A temp dir is owned by whoever runs the tests, which is also who owns $HOME
+        let home_owner = std::env::var_os("HOME").and_then(|h| fs::metadata(h).ok());
+        if home_owner.is_some_and(|h| {
+            use std::os::unix::fs::MetadataExt;
+            h.uid() == metadata.uid()
+        }) {
+            assert!(!is_system_dir(dir.path(), &metadata));
+        }
+    }
+
+    // Run `scan` to the end, collecting every event it reports
+    fn scan_events(config: &ScanConfig) -> Vec<ScanUpdate> {
+        let mut events = Vec::new();
+        scan(
+            config,
+            |update| events.push(update),
+            &AtomicBool::new(false),
+        );
+        events
+    }
+
+    fn matches(events: &[ScanUpdate]) -> Vec<PathBuf> {
+        let mut found: Vec<PathBuf> = events
+            .iter()
+            .filter_map(|event| match event {
+                ScanUpdate::Result(dir) => Some(dir.path.clone()),
+                _ => None,
+            })
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn scan_reports_matches_outside_ignored_directories() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in [
+            "a/target",
+            "a/target/nested/target",
+            "skip/target",
+            ".hidden/target",
+        ] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        fs::write(root.path().join("a/target/bin"), vec![0; 100]).unwrap();
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .ignore(&["skip".to_string()])
+            .build();
+        let events = scan_events(&config);
+        assert_eq!(matches(&events), [root.path().join("a/target")]);
+        assert!(matches!(events.last(), Some(ScanUpdate::Done)));
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, ScanUpdate::Path(p) if p == root.path()))
+        );
+        let size = events.iter().find_map(|event| match event {
+            ScanUpdate::Result(dir) => Some(dir.size_bytes),
+            _ => None,
+        });
+        assert!(size.unwrap() >= 100);
+    }
+
+    #[test]
+    fn scan_sends_background_sizes_before_done() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("a/target")).unwrap();
+        fs::write(root.path().join("a/target/bin"), vec![0; 100]).unwrap();
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .concurrent_sizes(true)
+            .build();
+        let events = scan_events(&config);
+        let size_at = events
+            .iter()
+            .position(|e| matches!(e, ScanUpdate::SizeReady(_, stats) if stats.size_bytes >= 100))
+            .unwrap();
+        let result_at = events
+            .iter()
+            .position(|e| matches!(e, ScanUpdate::Result(dir) if dir.size_bytes == 0))
+            .unwrap();
+        assert!(result_at < size_at);
+        assert!(matches!(events.last(), Some(ScanUpdate::Done)));
+    }
+
+    #[test]
+    fn scan_stops_at_the_match_limit_or_when_asked() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["a/target", "b/target", "c/target"] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .max_matches(Some(2))
+            .build();
+        let stop = AtomicBool::new(false);
+        let mut events = Vec::new();
+        scan(&config, |update| events.push(update), &stop);
+        assert_eq!(matches(&events).len(), 2);
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, ScanUpdate::LimitReached(2)))
+        );
+        assert!(stop.load(Ordering::SeqCst));
+
+        // Already stopped: nothing is walked, but the scan still finishes
+        let events = {
+            let mut events = Vec::new();
+            scan(&config, |update| events.push(update), &stop);
+            events
+        };
+        assert!(matches!(events[..], [ScanUpdate::Done]));
+    }
+
+    #[test]
+    fn directory_stats_counts_files_in_the_same_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        fs::create_dir_all(dir.path().join("empty")).unwrap();
+        fs::write(dir.path().join("top"), vec![0; 100]).unwrap();
+        fs::write(dir.path().join("a/one"), vec![0; 200]).unwrap();
+        fs::write(dir.path().join("a/b/c/two"), vec![0; 300]).unwrap();
+        fs::write(dir.path().join("a/b/c/three"), "").unwrap();
+
+        let stats = directory_stats(dir.path(), SizeOptions::default());
+        assert_eq!(
+            stats,
+            DirStats {
+                size_bytes: 600,
+                file_count: 4,
+                unreadable: false
+            }
+        );
+        assert_eq!(
+            calculate_directory_size(dir.path(), SizeOptions::default()),
+            600
+        );
+        assert_eq!(
+            directory_stats(&dir.path().join("empty"), SizeOptions::default()),
+            DirStats::default()
+        );
+
+        // Matches carry their count from the scan
+        fs::create_dir_all(dir.path().join("p/target")).unwrap();
+        fs::write(dir.path().join("p/target/x"), "x").unwrap();
+        fs::write(dir.path().join("p/target/y"), "y").unwrap();
+        let events = scan_events(&ScanConfig::builder().root(dir.path()).build());
+        let counts: Vec<u64> = events
+            .iter()
+            .filter_map(|event| match event {
+                ScanUpdate::Result(dir) => Some(dir.file_count),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(counts, [2]);
+    }
+
+    #[test]
+    fn min_files_keeps_matches_with_exactly_that_many() {
+        let root = tempfile::tempdir().unwrap();
+        for (project, files) in [("three", 3), ("two", 2)] {
+            let target = root.path().join(project).join("target");
+            fs::create_dir_all(&target).unwrap();
+            for file in 0..files {
+                fs::write(target.join(file.to_string()), "").unwrap();
+            }
+        }
+        let config = ScanConfig::builder().root(root.path()).min_files(3).build();
+        assert_eq!(
+            matches(&scan_events(&config)),
+            [root.path().join("three/target")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn owner_only_reports_matches_owned_by_that_user() {
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("project/target");
+        fs::create_dir_all(&target).unwrap();
+        // Created by this process, so owned by its uid
+        let me = unsafe { libc::getuid() };
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .owner_only(Some(me))
+            .build();
+        let events = scan_events(&config);
+        assert_eq!(matches(&events), [target]);
+        let owner = events.iter().find_map(|event| match event {
+            ScanUpdate::Result(dir) => dir.owner.clone(),
+            _ => None,
+        });
+        assert_eq!(owner.map(|owner| owner.uid), Some(me));
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .owner_only(Some(me.wrapping_add(1)))
+            .build();
+        assert!(matches(&scan_events(&config)).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_root_is_resolved_or_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("real");
+        fs::create_dir_all(real.join("project/target")).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let real = fs::canonicalize(&real).unwrap();
+
+        let config = ScanConfig::builder().root(&link).build();
+        assert_eq!(config.root, real);
+        assert_eq!(config.root_link.as_deref(), Some(link.as_path()));
+        assert_eq!(
+            matches(&scan_events(&config)),
+            [real.join("project/target")]
+        );
+
+        let config = ScanConfig::builder()
+            .root(&link)
+            .follow_root_symlink(false)
+            .build();
+        assert_eq!(config.root, link);
+        let events = scan_events(&config);
+        assert!(matches(&events).is_empty());
+        assert!(
+            events
+                .iter()
+                .any(|event| matches!(event, ScanUpdate::Skipped(path, _) if *path == link))
+        );
+
+        // A dangling link can't be followed, and says so rather than blaming the setting
+        let dangling = dir.path().join("dangling");
+        std::os::unix::fs::symlink(dir.path().join("gone"), &dangling).unwrap();
+        let config = ScanConfig::builder().root(&dangling).build();
+        assert_eq!(config.root, dangling);
+        let events = scan_events(&config);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ScanUpdate::Skipped(path, reason)
+                if *path == dangling && reason.contains("doesn't exist")
+        )));
+    }
+
+    #[test]
+    fn empty_matches_are_told_apart_from_unreadable_ones() {
+        let root = tempfile::tempdir().unwrap();
+        let empty = root.path().join("empty/target");
+        fs::create_dir_all(&empty).unwrap();
+        let full = root.path().join("full/target");
+        fs::create_dir_all(&full).unwrap();
+        fs::write(full.join("out"), "built").unwrap();
+
+        let config = ScanConfig::builder().root(root.path()).build();
+        let events = scan_events(&config);
+        let reported: Vec<(PathBuf, u64, bool)> = events
+            .iter()
+            .filter_map(|event| match event {
+                ScanUpdate::Result(dir) => Some((dir.path.clone(), dir.size_bytes, dir.unreadable)),
+                _ => None,
+            })
+            .collect();
+        assert!(reported.contains(&(empty.clone(), 0, false)));
+        assert!(reported.contains(&(full.clone(), 5, false)));
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .hide_empty(true)
+            .build();
+        assert_eq!(matches(&scan_events(&config)), [full]);
+
+        // Gone before it could be measured: no size, but not known to be empty
+        let stats = directory_stats(&root.path().join("gone/target"), SizeOptions::default());
+        assert!(stats.unreadable);
+        assert!(!stats.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_match_is_reported_with_a_warning() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Root reads everything regardless of permissions
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let root = tempfile::tempdir().unwrap();
+        let target = root.path().join("project/target");
+        fs::create_dir_all(&target).unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .hide_empty(true)
+            .build();
+        let events = scan_events(&config);
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(matches(&events), std::slice::from_ref(&target));
+        assert!(events.iter().any(
+            |event| matches!(event, ScanUpdate::Skipped(path, reason) if *path == target && reason.contains("size"))
+        ));
+    }
+
+    #[test]
+    fn depth_range_reports_only_matches_in_the_band() {
+        let root = tempfile::tempdir().unwrap();
+        for dir in ["node_modules", "a/node_modules", "a/b/node_modules"] {
+            fs::create_dir_all(root.path().join(dir)).unwrap();
+        }
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .min_depth(2)
+            .max_depth(Some(2))
+            .build();
+        assert_eq!(
+            matches(&scan_events(&config)),
+            [root.path().join("a/node_modules")]
+        );
+
+        let config = ScanConfig::builder().root(root.path()).min_depth(2).build();
+        assert_eq!(matches(&scan_events(&config)).len(), 2);
+        let config = ScanConfig::builder()
+            .root(root.path())
+            .max_depth(Some(1))
+            .build();
+        assert_eq!(
+            matches(&scan_events(&config)),
+            [root.path().join("node_modules")]
+        );
+    }
+
+    #[test]
+    fn resumed_scan_does_not_report_seen_matches_again() {
+        let root = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        for project in ["a", "b", "c"] {
+            let target = root.path().join(project).join("target");
+            fs::create_dir_all(&target).unwrap();
+            fs::write(target.join("out"), "built").unwrap();
+        }
+        let mut config = ScanConfig::builder()
+            .root(root.path())
+            .checkpoint(Some(cache.path().join("scan.json")))
+            .build();
+
+        // Interrupted right after the first match
+        let stop = AtomicBool::new(false);
+        let mut first = Vec::new();
+        scan(
+            &config,
+            |update| {
+                if let ScanUpdate::Result(dir) = &update {
+                    first.push(dir.path.clone());
+                    stop.store(true, Ordering::SeqCst);
+                }
+            },
+            &stop,
+        );
+        assert_eq!(first.len(), 1);
+        assert!(cache.path().join("scan.json").exists());
+
+        config.resume = true;
+        let events = scan_events(&config);
+        let all: Vec<PathBuf> = ["a", "b", "c"]
+            .iter()
+            .map(|project| root.path().join(project).join("target"))
+            .collect();
+        // The saved match comes back once, from the checkpoint, without walking it again
+        assert_eq!(matches(&events), all);
+        assert!(
+            !events.iter().any(
+                |event| matches!(event, ScanUpdate::Path(path) if path.starts_with(&first[0]))
+            )
+        );
+        assert!(!cache.path().join("scan.json").exists());
+    }
+
+    #[test]
+    fn sequential_and_parallel_roots_find_the_same_matches() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        for project in ["a", "b"] {
+            fs::create_dir_all(first.path().join(project).join("target")).unwrap();
+        }
+        fs::create_dir_all(second.path().join("c/node_modules")).unwrap();
+
+        let run = |concurrency| {
+            let config = ScanConfig::builder()
+                .root(first.path())
+                .extra_roots(vec![second.path().to_path_buf()])
+                .root_concurrency(concurrency)
+                .build();
+            let mut events = Vec::new();
+            scan(
+                &config,
+                |update| events.push(update),
+                &AtomicBool::new(false),
+            );
+            events
+        };
+        let sequential = run(RootConcurrency::Sequential);
+        let parallel = run(RootConcurrency::Parallel(2));
+        let mut expected = vec![
+            first.path().join("a/target"),
+            first.path().join("b/target"),
+            second.path().join("c/node_modules"),
+        ];
+        expected.sort();
+        assert_eq!(matches(&sequential), expected);
+        assert_eq!(matches(&sequential), matches(&parallel));
+        for events in [&sequential, &parallel] {
+            let done = events
+                .iter()
+                .filter(|event| matches!(event, ScanUpdate::Done))
+                .count();
+            assert_eq!(done, 1);
+            assert!(matches!(events.last(), Some(ScanUpdate::Done)));
+        }
+
+        // One after another, every event from the first root comes before the second's
+        let roots: Vec<bool> = sequential
+            .iter()
+            .filter_map(|event| match event {
+                ScanUpdate::Result(dir) => Some(dir.path.starts_with(second.path())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(roots, [false, false, true]);
+    }
+
+    #[test]
+    fn stopping_a_multi_root_scan_stops_every_walk() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::create_dir_all(first.path().join("a/target")).unwrap();
+        fs::create_dir_all(second.path().join("b/target")).unwrap();
+        let config = ScanConfig::builder()
+            .root(first.path())
+            .extra_roots(vec![second.path().to_path_buf()])
+            .root_concurrency(RootConcurrency::Parallel(2))
+            .build();
+        let mut events = Vec::new();
+        scan(
+            &config,
+            |update| events.push(update),
+            &AtomicBool::new(true),
+        );
+        assert!(matches(&events).is_empty());
+        assert!(matches!(events.last(), Some(ScanUpdate::Done)));
+    }
 }
@@ -0,0 +1,95 @@
+use crate::app::DirInfo;
+use crate::paths;
+use crate::scanner;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Selected paths remembered per scan root
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionFile {
+    sessions: BTreeMap<PathBuf, Vec<PathBuf>>,
+}
+
+// Location of the session file, in the platform's state directory
+pub fn default_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("session.json"))
+}
+
+fn read(file: &Path) -> SessionFile {
+    fs::read_to_string(file)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+// Remember the selected paths for `root`; an empty selection forgets it
+pub fn save(file: &Path, root: &Path, selected: &[PathBuf]) -> io::Result<()> {
+    let mut session = read(file);
+    let root = scanner::absolute_path(root);
+    if selected.is_empty() {
+        if session.sessions.remove(&root).is_none() {
+            return Ok(());
+        }
+    } else {
+        session.sessions.insert(root, selected.to_vec());
+    }
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(&session).map_err(io::Error::other)?;
+    fs::write(file, contents)
+}
+
+// Load the paths previously selected under `root`
+pub fn load(file: &Path, root: &Path) -> Vec<PathBuf> {
+    read(file)
+        .sessions
+        .remove(&scanner::absolute_path(root))
+        .unwrap_or_default()
+}
+
+// Keep only saved paths that still exist and were found again by the fresh scan
+pub fn reconcile(saved: &[PathBuf], dirs: &[DirInfo]) -> Vec<PathBuf> {
+    let found: HashSet<PathBuf> = dirs
+        .iter()
+        .map(|d| scanner::absolute_path(&d.path))
+        .collect();
+    saved
+        .iter()
+        .filter(|path| path.exists() && found.contains(*path))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_round_trips_and_reconciles() {
+        let root = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        let file = state.path().join("nested/session.json");
+
+        let kept = root.path().join("a/node_modules");
+        let removed = root.path().join("b/target");
+        fs::create_dir_all(&kept).unwrap();
+        fs::create_dir_all(&removed).unwrap();
+
+        save(&file, root.path(), &[kept.clone(), removed.clone()]).unwrap();
+        let loaded = load(&file, root.path());
+        assert_eq!(loaded, vec![kept.clone(), removed.clone()]);
+        assert!(load(&file, state.path()).is_empty());
+
+        fs::remove_dir_all(&removed).unwrap();
+        let dirs = vec![DirInfo::for_test(&kept)];
+        assert_eq!(reconcile(&loaded, &dirs), vec![kept]);
+
+        save(&file, root.path(), &[]).unwrap();
+        assert!(load(&file, root.path()).is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::app::DirInfo;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// Matched directories whose last modification falls in an age range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgeBucket {
+    pub label: &'static str,
+    pub count: usize,
+    pub size_bytes: u64,
+}
+
+// Upper bound (inclusive, in days) and label of each bucket; the last one is open-ended
+const AGE_BUCKETS: [(u32, &str); 3] = [
+    (30, "0-30 days"),
+    (90, "31-90 days"),
+    (u32::MAX, "over 90 days"),
+];
+
+// Count and total size of the directories in each age bucket, youngest first
+pub fn age_buckets(dirs: &[DirInfo]) -> Vec<AgeBucket> {
+    let mut buckets: Vec<AgeBucket> = AGE_BUCKETS
+        .iter()
+        .map(|(_, label)| AgeBucket {
+            label,
+            count: 0,
+            size_bytes: 0,
+        })
+        .collect();
+    for dir in dirs {
+        let index = AGE_BUCKETS
+            .iter()
+            .position(|(max_days, _)| dir.modified_days_ago <= *max_days)
+            .unwrap_or(AGE_BUCKETS.len() - 1);
+        buckets[index].count += 1;
+        buckets[index].size_bytes += dir.size_bytes;
+    }
+    buckets
+}
+
+// Space freed by removing `dirs`. A match inside another one is already part of its
+// size, so only the outermost of a nested pair is counted.
+pub fn reclaimable_bytes<'a>(dirs: impl IntoIterator<Item = &'a DirInfo>) -> u64 {
+    let mut dirs: Vec<&DirInfo> = dirs.into_iter().collect();
+    // Component-wise order puts each directory right before everything under it
+    dirs.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut outer: Option<&Path> = None;
+    let mut total = 0;
+    for dir in dirs {
+        if outer.is_some_and(|outer| dir.path.starts_with(outer)) {
+            continue;
+        }
+        outer = Some(&dir.path);
+        total += dir.size_bytes;
+    }
+    total
+}
+
+// Matches sharing the directory that directly contains them, e.g. one project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParentTotal {
+    pub parent: PathBuf,
+    pub count: usize,
+    pub size_bytes: u64,
+}
+
+// Count and reclaimable size of the matches under each immediate parent, largest first
+pub fn totals_by_parent(dirs: &[DirInfo]) -> Vec<ParentTotal> {
+    let mut members: HashMap<&Path, Vec<&DirInfo>> = HashMap::new();
+    for dir in dirs {
+        let parent = dir.path.parent().unwrap_or(&dir.path);
+        members.entry(parent).or_default().push(dir);
+    }
+    let mut totals: Vec<ParentTotal> = members
+        .into_iter()
+        .map(|(parent, dirs)| ParentTotal {
+            parent: parent.to_path_buf(),
+            count: dirs.len(),
+            size_bytes: reclaimable_bytes(dirs),
+        })
+        .collect();
+    totals.sort_by(|a, b| {
+        b.size_bytes
+            .cmp(&a.size_bytes)
+            .then_with(|| a.parent.cmp(&b.parent))
+    });
+    totals
+}
+
+// Select every match except the `keep` most recently modified per target name. System
+// directories and the `skip`ped ones, e.g. in use, are never selected, but still count
+// towards what's kept.
+pub fn keep_recent(dirs: &mut [DirInfo], keep: usize, skip: &HashSet<PathBuf>) {
+    let mut newest_first: Vec<usize> = (0..dirs.len()).collect();
+    newest_first.sort_by(|&a, &b| {
+        dirs[b]
+            .modified
+            .cmp(&dirs[a].modified)
+            .then_with(|| dirs[a].path.cmp(&dirs[b].path))
+    });
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    for index in newest_first {
+        let seen = kept.entry(dirs[index].matched_name.clone()).or_default();
+        let dir = &mut dirs[index];
+        dir.selected = *seen >= keep && !dir.system && !skip.contains(&dir.path);
+        *seen += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(days_ago: u32, size_bytes: u64) -> DirInfo {
+        DirInfo {
+            modified_days_ago: days_ago,
+            modified: std::time::UNIX_EPOCH,
+            size_bytes,
+            ..DirInfo::for_test("/missing/target")
+        }
+    }
+
+    #[test]
+    fn buckets_split_at_30_and_90_days() {
+        let dirs = vec![
+            dir(0, 1),
+            dir(30, 2),
+            dir(31, 4),
+            dir(90, 8),
+            dir(91, 16),
+            dir(u32::MAX, 32),
+        ];
+        let buckets = age_buckets(&dirs);
+        let summary: Vec<(&str, usize, u64)> = buckets
+            .iter()
+            .map(|b| (b.label, b.count, b.size_bytes))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("0-30 days", 2, 3),
+                ("31-90 days", 2, 12),
+                ("over 90 days", 2, 48)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_results_give_empty_buckets() {
+        let buckets = age_buckets(&[]);
+        assert_eq!(buckets.len(), 3);
+        assert!(buckets.iter().all(|b| b.count == 0 && b.size_bytes == 0));
+    }
+
+    #[test]
+    fn keep_recent_selects_all_but_the_newest_per_name() {
+        let build = |name: &str, project: &str, secs: u64| DirInfo {
+            modified: std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs),
+            size_bytes: 1,
+            ..DirInfo::for_test(format!("/missing/{}/{}", project, name))
+        };
+        let mut dirs = vec![
+            build("target", "a", 100),
+            build("target", "b", 400),
+            build("target", "c", 300),
+            build("target", "d", 200),
+            build("node_modules", "e", 50),
+        ];
+        keep_recent(&mut dirs, 2, &HashSet::new());
+        let selected: Vec<bool> = dirs.iter().map(|d| d.selected).collect();
+        assert_eq!(selected, vec![true, false, false, true, false]);
+
+        keep_recent(&mut dirs, 0, &HashSet::new());
+        assert!(dirs.iter().all(|d| d.selected));
+
+        // System and skipped matches are never selected, however old
+        dirs[0].system = true;
+        let in_use = HashSet::from([dirs[3].path.clone()]);
+        keep_recent(&mut dirs, 0, &in_use);
+        let selected: Vec<bool> = dirs.iter().map(|d| d.selected).collect();
+        assert_eq!(selected, vec![false, true, true, false, true]);
+    }
+
+    #[test]
+    fn totals_by_parent_adds_up_each_projects_matches() {
+        let build = |path: &str, size_bytes: u64| DirInfo {
+            size_bytes,
+            ..DirInfo::for_test(path)
+        };
+        let dirs = vec![
+            build("/src/a/target", 100),
+            build("/src/a/node_modules", 50),
+            build("/src/b/target", 400),
+            build("/src/c/node_modules", 20),
+            build("/src/a/.venv", 5),
+        ];
+        let totals: Vec<(String, usize, u64)> = totals_by_parent(&dirs)
+            .into_iter()
+            .map(|t| (t.parent.display().to_string(), t.count, t.size_bytes))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                ("/src/b".to_string(), 1, 400),
+                ("/src/a".to_string(), 3, 155),
+                ("/src/c".to_string(), 1, 20),
+            ]
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crossterm::{
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::{Terminal, backend::CrosstermBackend};
+use std::io::{self, Stdout, Write};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+pub type Tui = Terminal<CrosstermBackend<Stdout>>;
+
+// Switch to raw mode and the alternate screen; undone by `restore`
+pub fn init() -> io::Result<Tui> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    if let Err(err) = enter_screen(&mut stdout) {
+        let _ = disable_raw_mode();
+        return Err(err);
+    }
+    Terminal::new(CrosstermBackend::new(stdout))
+}
+
+// Put the terminal back the way we found it. Safe to call more than once, or without
+// `init`, so the panic hook can always call it
+pub fn restore() -> io::Result<()> {
+    let raw = disable_raw_mode();
+    leave_screen(&mut io::stdout())?;
+    raw
+}
+
+// Restore the terminal before a panic message is printed, so it stays readable
+pub fn install_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+}
+
+// Flag set when a process manager asks us to stop (SIGTERM), so the main loop can exit
+// through the normal restore instead of leaving the terminal in raw mode
+pub fn shutdown_flag() -> io::Result<Arc<AtomicBool>> {
+    let flag = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, flag.clone())?;
+    Ok(flag)
+}
+
+fn enter_screen(out: &mut impl Write) -> io::Result<()> {
+    execute!(out, EnterAlternateScreen, EnableMouseCapture)
+}
+
+fn leave_screen(out: &mut impl Write) -> io::Result<()> {
+    execute!(out, DisableMouseCapture, LeaveAlternateScreen, Show)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn sigterm_sets_the_shutdown_flag_instead_of_killing_us() {
+        use std::sync::atomic::Ordering;
+
+        let flag = shutdown_flag().unwrap();
+        assert!(!flag.load(Ordering::SeqCst));
+        signal_hook::low_level::raise(signal_hook::consts::SIGTERM).unwrap();
+        assert!(flag.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn leaving_undoes_entering_the_alternate_screen() {
+        let mut out = Vec::new();
+        enter_screen(&mut out).unwrap();
+        let entered = String::from_utf8(out.clone()).unwrap();
+        assert!(entered.starts_with("\x1b[?1049h"));
+        // Mouse reporting, for clicks on the column headers
+        assert!(entered.contains("\x1b[?1000h"));
+
+        let mut out = Vec::new();
+        leave_screen(&mut out).unwrap();
+        let left = String::from_utf8(out).unwrap();
+        assert!(left.contains("\x1b[?1000l"));
+        assert!(left.ends_with("\x1b[?1049l\x1b[?25h"));
+    }
+}
@@ -0,0 +1,119 @@
+use serde::Deserialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// A build tool's own clean command, for `--use-tooling`: run in a project that has
+// `marker` at its root, it takes care of the project's `target` directory
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CleanCommand {
+    // Matched directory name, e.g. `target`
+    pub target: String,
+    // File next to it that says which tool owns it, e.g. `Cargo.toml`
+    pub marker: String,
+    // Program and arguments, e.g. `["cargo", "clean"]`
+    pub command: Vec<String>,
+}
+
+// The tools known out of the box; a config file's `[[tooling]]` entries are tried first
+pub fn default_commands() -> Vec<CleanCommand> {
+    [
+        ("target", "Cargo.toml", &["cargo", "clean"][..]),
+        ("target", "pom.xml", &["mvn", "-q", "clean"]),
+        ("build", "build.gradle", &["gradle", "-q", "clean"]),
+        ("build", "build.gradle.kts", &["gradle", "-q", "clean"]),
+        (".build", "Package.swift", &["swift", "package", "clean"]),
+        ("dist-newstyle", "cabal.project", &["cabal", "clean"]),
+    ]
+    .iter()
+    .map(|(target, marker, command)| CleanCommand {
+        target: target.to_string(),
+        marker: marker.to_string(),
+        command: command.iter().map(|arg| arg.to_string()).collect(),
+    })
+    .collect()
+}
+
+// The first command that owns `dir`: one for its name whose marker is in its parent
+pub fn command_for<'a>(dir: &Path, commands: &'a [CleanCommand]) -> Option<&'a CleanCommand> {
+    let name = dir.file_name()?.to_string_lossy();
+    let project = dir.parent()?;
+    commands.iter().find(|command| {
+        command.target == name
+            && !command.command.is_empty()
+            && project.join(&command.marker).is_file()
+    })
+}
+
+// Run `command` in the project containing `dir`, quietly; `Err` says why it failed
+pub fn run(command: &CleanCommand, dir: &Path) -> Result<(), String> {
+    let (program, args) = command
+        .command
+        .split_first()
+        .ok_or_else(|| "empty clean command".to_string())?;
+    let project = dir.parent().unwrap_or(dir);
+    let status = Command::new(program)
+        .args(args)
+        .current_dir(project)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|err| format!("{}: {}", program, err))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!(
+            "{} exited with {}",
+            command.command.join(" "),
+            status
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn projects_map_to_their_tools_clean_command() {
+        let root = tempfile::tempdir().unwrap();
+        for (project, marker) in [
+            ("rust", "Cargo.toml"),
+            ("java", "pom.xml"),
+            ("android", "build.gradle.kts"),
+            ("loose", "README.md"),
+        ] {
+            fs::create_dir_all(root.path().join(project)).unwrap();
+            fs::write(root.path().join(project).join(marker), "").unwrap();
+        }
+        let commands = default_commands();
+        let command = |path: &str| {
+            command_for(&root.path().join(path), &commands).map(|c| c.command.join(" "))
+        };
+        assert_eq!(command("rust/target").as_deref(), Some("cargo clean"));
+        assert_eq!(command("java/target").as_deref(), Some("mvn -q clean"));
+        assert_eq!(command("android/build").as_deref(), Some("gradle -q clean"));
+        // Right marker, wrong directory; or no tool at all
+        assert_eq!(command("rust/build"), None);
+        assert_eq!(command("loose/target"), None);
+
+        // Configured commands come first
+        let custom = CleanCommand {
+            target: "target".to_string(),
+            marker: "Cargo.toml".to_string(),
+            command: vec![
+                "cargo".to_string(),
+                "clean".to_string(),
+                "--release".to_string(),
+            ],
+        };
+        let commands = [vec![custom], default_commands()].concat();
+        assert_eq!(
+            command_for(&root.path().join("rust/target"), &commands)
+                .map(|c| c.command.join(" "))
+                .as_deref(),
+            Some("cargo clean --release")
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::app::DirInfo;
+use crate::units::{SizeUnits, human_size};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Matches arranged by where they are under the scan root, for `--format tree`. Each node
+// is one path component (or a run of them, once compacted) with the total size of the
+// matches at or under it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub size_bytes: u64,
+    // Keyed by name, so siblings come out in A-Z order
+    pub children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, components: &[String], size_bytes: u64) {
+        self.size_bytes += size_bytes;
+        if let Some((first, rest)) = components.split_first() {
+            self.children
+                .entry(first.clone())
+                .or_insert_with(|| Node {
+                    name: first.clone(),
+                    ..Node::default()
+                })
+                .insert(rest, size_bytes);
+        }
+    }
+
+    // Fold chains of directories with one child into a single `a/b/c` line: nothing is
+    // lost, and the tree gets much shorter
+    fn compact(mut self) -> Node {
+        while self.children.len() == 1 {
+            let (_, only) = self.children.pop_first().expect("one child");
+            // A match that itself contains matches keeps its own line
+            if only.size_bytes != self.size_bytes {
+                self.children.insert(only.name.clone(), only);
+                break;
+            }
+            self.name = format!("{}/{}", self.name, only.name);
+            self.children = only.children;
+        }
+        self.children = compact_all(self.children);
+        self
+    }
+}
+
+// Compact each node, re-keying it by its possibly longer name
+fn compact_all(children: BTreeMap<String, Node>) -> BTreeMap<String, Node> {
+    children
+        .into_values()
+        .map(|child| {
+            let child = child.compact();
+            (child.name.clone(), child)
+        })
+        .collect()
+}
+
+// The matches as a tree under `root`. Paths outside it keep all their components.
+pub fn build(dirs: &[DirInfo], root: &Path) -> Node {
+    let mut tree = Node::default();
+    for dir in dirs {
+        let relative = dir.path.strip_prefix(root).unwrap_or(&dir.path);
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        tree.insert(&components, dir.size_bytes);
+    }
+    Node {
+        name: root.display().to_string(),
+        size_bytes: tree.size_bytes,
+        children: compact_all(tree.children),
+    }
+}
+
+// One line per node: its size, then its name drawn into the tree, like `tree --du`
+pub fn render(tree: &Node, units: SizeUnits) -> String {
+    let mut output = format!(
+        "{:>10}  {}\n",
+        human_size(tree.size_bytes, units),
+        tree.name
+    );
+    render_children(tree, "", units, &mut output);
+    output
+}
+
+fn render_children(node: &Node, indent: &str, units: SizeUnits, output: &mut String) {
+    let count = node.children.len();
+    for (i, child) in node.children.values().enumerate() {
+        let last = i + 1 == count;
+        let (branch, next_indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        output.push_str(&format!(
+            "{:>10}  {}{}{}\n",
+            human_size(child.size_bytes, units),
+            indent,
+            branch,
+            child.name
+        ));
+        render_children(child, &format!("{}{}", indent, next_indent), units, output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(path: &str, size_bytes: u64) -> DirInfo {
+        DirInfo {
+            selected: true,
+            size_bytes,
+            ..DirInfo::for_test(path)
+        }
+    }
+
+    #[test]
+    fn nested_matches_are_indented_under_their_common_prefixes() {
+        let dirs = [
+            dir("/p/app/target", 3072),
+            dir("/p/app/web/node_modules", 1024),
+            dir("/p/lib/deep/nested/target", 2048),
+            dir("/p/app/web/node_modules/pkg/node_modules", 512),
+        ];
+        let tree = build(&dirs, Path::new("/p"));
+        let expected: String = [
+            ("6 KiB", "/p"),
+            ("4 KiB", "├── app"),
+            ("3 KiB", "│   ├── target"),
+            ("1 KiB", "│   └── web/node_modules"),
+            ("512 B", "│       └── pkg/node_modules"),
+            ("2 KiB", "└── lib/deep/nested/target"),
+        ]
+        .iter()
+        .map(|(size, line)| format!("{:>10}  {}\n", size, line))
+        .collect();
+        assert_eq!(render(&tree, SizeUnits::Binary), expected);
+    }
+}
@@ -1,16 +1,339 @@
-use crate::app::{App, AppState};
+use crate::app::{App, AppState, DirInfo, SortColumn, SpinnerStyle, ViewMode};
+use crate::groups::{Group, GroupMode, Row};
+use crate::keymap::{self, Action};
+use crate::stats;
+use crate::units::{human_count, human_duration, human_size, reclaimable_summary};
 use ratatui::{
     Frame,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Cell, Clear, Gauge, List, ListItem, Paragraph, Row as TableRow, Table, Wrap,
+    },
 };
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-const SPINNER_CHARS: [char; 8] = ['⠁', '⠂', '⠄', '⡀', '⢀', '⠠', '⠐', '⠈'];
+// Failed paths listed in the deletion summary before it just says how many more
+const SUMMARY_FAILURES_SHOWN: usize = 3;
+
+// The scan root, the symlink it was reached through and how many more roots there are
+fn root_label(app: &App) -> String {
+    let root = match &app.scan_config.root_link {
+        Some(link) => format!(
+            "{} (via {})",
+            app.scan_config.root.display(),
+            link.display()
+        ),
+        None => app.scan_config.root.display().to_string(),
+    };
+    match app.scan_config.extra_roots.len() {
+        0 => root,
+        more => format!("{} and {} more", root, more),
+    }
+}
+
+// Columns of the results table in this view; the compact view leaves out the date
+fn columns(app: &App) -> Vec<(SortColumn, Constraint)> {
+    let mut columns = vec![
+        (SortColumn::Selected, Constraint::Length(7)),
+        (SortColumn::Size, Constraint::Length(12)),
+    ];
+    if app.show_file_counts {
+        // Room for "1023 MiB, 999k files" and the like
+        columns[1].1 = Constraint::Length(22);
+    }
+    if app.view_mode == ViewMode::Detailed {
+        columns.push((SortColumn::Age, Constraint::Length(12)));
+    }
+    columns.push((SortColumn::Path, Constraint::Fill(1)));
+    columns
+}
+
+// Header cell: the key that sorts by the column, its name, and the direction when it's
+// the sort column
+fn header_cell<'a>(app: &App, column: SortColumn) -> Cell<'a> {
+    let (action, name) = match column {
+        SortColumn::Selected => (Action::SortSelected, "Sel"),
+        SortColumn::Size => (Action::SortSize, "Size"),
+        SortColumn::Age => (Action::SortAge, "Modified"),
+        SortColumn::Path | SortColumn::Name => (Action::SortPath, "Path"),
+    };
+    let mut label = match app.key_bindings.keys_for(action).first() {
+        Some(key) => format!("{} {}", keymap::key_name(*key), name),
+        None => name.to_string(),
+    };
+    // `--sort name` orders by the project name, which is part of the path
+    let sorted = match app.sort_order.column {
+        SortColumn::Name => column == SortColumn::Path,
+        sort_column => sort_column == column,
+    };
+    if sorted {
+        let arrow = match (app.spinner_style, app.sort_order.descending) {
+            (SpinnerStyle::Ascii, false) => " ^",
+            (SpinnerStyle::Ascii, true) => " v",
+            (SpinnerStyle::Braille, false) => " ▲",
+            (SpinnerStyle::Braille, true) => " ▼",
+        };
+        label.push_str(arrow);
+    }
+    Cell::from(label)
+}
+
+// Cells for the columns in view, in order
+fn table_row<'a>(
+    app: &App,
+    selected: Cell<'a>,
+    size: Cell<'a>,
+    age: Cell<'a>,
+    path: Cell<'a>,
+) -> TableRow<'a> {
+    let mut cells = vec![selected, size, age, path];
+    if app.view_mode == ViewMode::Compact {
+        cells.remove(2);
+    }
+    TableRow::new(cells)
+}
+
+// One match in the results table
+fn dir_row<'a>(app: &App, dir: &DirInfo) -> TableRow<'a> {
+    let checked = if dir.selected { "[x]" } else { "[ ]" };
+    // Members are indented under their group header
+    let indent = match app.group_mode {
+        GroupMode::Flat => "",
+        GroupMode::ByProject => "  ",
+    };
+
+    // Format directory size for display
+    let size_text = if app.sizes_pending.contains(&dir.path) {
+        "measuring…".to_string()
+    } else if app.show_file_counts {
+        format!(
+            "{}, {} files",
+            human_size(dir.size_bytes, app.size_units),
+            human_count(dir.file_count)
+        )
+    } else {
+        human_size(dir.size_bytes, app.size_units)
+    };
+
+    let name = match app.view_mode {
+        // Short name, for narrow terminals
+        ViewMode::Compact => short_name(&dir.path),
+        ViewMode::Detailed => app.path_display.render(&dir.path, &app.scan_config.root),
+    };
+    let mut spans = vec![Span::styled(name, target_style(app, &dir.matched_name))];
+    if let Some(percent) = app.percent_of_parent(dir) {
+        spans.push(Span::styled(
+            format!(" {}% of parent", percent),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if app.show_owners
+        && let Some(owner) = &dir.owner
+    {
+        spans.push(Span::styled(
+            format!(" ({})", owner.label()),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    // Say why a match frees nothing, or why its size can't be trusted
+    if dir.unreadable {
+        let marker = match app.spinner_style {
+            SpinnerStyle::Ascii => " ! unreadable",
+            SpinnerStyle::Braille => " ⚠ unreadable",
+        };
+        spans.push(Span::styled(marker, Style::default().fg(Color::Yellow)));
+    } else if dir.size_bytes == 0 && !app.sizes_pending.contains(&dir.path) {
+        spans.push(Span::styled(" empty", Style::default().fg(Color::DarkGray)));
+    }
+    if dir.system {
+        let marker = match app.spinner_style {
+            SpinnerStyle::Ascii => " ! system",
+            SpinnerStyle::Braille => " ⚠ system",
+        };
+        spans.push(Span::styled(marker, Style::default().fg(Color::Red)));
+    }
+    if app.in_use.contains(&dir.path) {
+        let marker = match app.spinner_style {
+            SpinnerStyle::Ascii => " ! in use",
+            SpinnerStyle::Braille => " ⚠ in use",
+        };
+        spans.push(Span::styled(marker, Style::default().fg(Color::Yellow)));
+    }
+    table_row(
+        app,
+        Cell::from(format!("{}{}", indent, checked)),
+        Cell::from(size_text),
+        Cell::from(modified_date(dir.modified_days_ago)),
+        Cell::from(Line::from(spans)),
+    )
+}
+
+// A subdirectory of an expanded match, indented one level below it
+fn child_row<'a>(app: &App, path: &Path, size_bytes: u64) -> TableRow<'a> {
+    let branch = match app.spinner_style {
+        SpinnerStyle::Ascii => "  `-",
+        SpinnerStyle::Braille => "  └",
+    };
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let dim = Style::default().fg(Color::DarkGray);
+    table_row(
+        app,
+        Cell::from(""),
+        Cell::from(human_size(size_bytes, app.size_units)).style(dim),
+        Cell::from(""),
+        Cell::from(Line::from(vec![
+            Span::styled(format!("{} ", branch), dim),
+            Span::raw(name),
+        ])),
+    )
+}
+
+// Header row for a project's matches: fold marker, selection state, size, path and count
+fn group_row<'a>(app: &App, group: &Group) -> TableRow<'a> {
+    let collapsed = app.collapsed_groups.contains(&group.project);
+    let marker = match (app.spinner_style, collapsed) {
+        (SpinnerStyle::Ascii, true) => ">",
+        (SpinnerStyle::Ascii, false) => "v",
+        (SpinnerStyle::Braille, true) => "▸",
+        (SpinnerStyle::Braille, false) => "▾",
+    };
+    let selected = group
+        .members
+        .iter()
+        .filter(|&&i| app.dirs_to_clean[i].selected)
+        .count();
+    let checked = match selected {
+        0 => "[ ]",
+        n if n == group.members.len() => "[x]",
+        _ => "[-]",
+    };
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    table_row(
+        app,
+        Cell::from(format!("{} {}", marker, checked)),
+        Cell::from(human_size(
+            group.size_bytes(&app.dirs_to_clean),
+            app.size_units,
+        ))
+        .style(bold),
+        Cell::from(""),
+        Cell::from(Line::from(vec![
+            Span::styled(group.project.display().to_string(), bold),
+            Span::raw(format!(" ({} matches)", group.members.len())),
+        ])),
+    )
+}
+
+// A note in place of the results, in the path column
+fn message_row<'a>(app: &App, message: &'a str) -> TableRow<'a> {
+    table_row(
+        app,
+        Cell::from(""),
+        Cell::from(""),
+        Cell::from(""),
+        Cell::from(message),
+    )
+}
+
+// The match's name with its parent folder, since the name alone is always a target name
+fn short_name(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    match path.parent().and_then(|p| p.file_name()) {
+        Some(parent) => format!("{}/{}", parent.to_string_lossy(), name),
+        None => name.to_string(),
+    }
+}
+
+// Basic ANSI colours, so rows stay distinguishable on 16-colour terminals
+const TARGET_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Green,
+    Color::LightBlue,
+    Color::LightRed,
+];
+
+// Colour for a target name or glob, assigned by its position in the target list
+fn target_style(app: &App, matched_name: &str) -> Style {
+    if !app.use_color {
+        return Style::default();
+    }
+    let config = &app.scan_config;
+    match config
+        .folders_to_clean
+        .iter()
+        .chain(&config.target_globs)
+        .position(|t| t == matched_name)
+    {
+        Some(index) => Style::default().fg(TARGET_COLORS[index % TARGET_COLORS.len()]),
+        None => Style::default(),
+    }
+}
+
+// Calendar date a directory was last modified, from its age in days
+fn modified_date(days_ago: u32) -> String {
+    (chrono::Local::now() - chrono::Duration::days(days_ago as i64))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+// Below this size the panels can't be laid out, so only a notice is drawn
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 12;
+
+// Below this width the folder and ignore panels leave the results too little room beside
+// them, so they go above the list instead
+const NARROW_WIDTH: u16 = 80;
+// The list needs this much height to also fit the panels above it; any less, they're hidden
+const STACKED_MIN_HEIGHT: u16 = 16;
+// On wide terminals the panels stop growing at this width and the list gets the rest
+const PANEL_MAX_WIDTH: u16 = 40;
+
+// Where the folder and ignore panels go, and what's left for the results list
+fn content_layout(area: Rect, panel_rows: u16) -> (Option<[Rect; 2]>, Rect) {
+    if area.width < NARROW_WIDTH {
+        if area.height < STACKED_MIN_HEIGHT {
+            return (None, area);
+        }
+        // Side by side in a strip over the list, tall enough for their longest list
+        let [panels, list] = Layout::vertical([
+            Constraint::Length((panel_rows + 2).min(area.height / 3)),
+            Constraint::Min(0),
+        ])
+        .areas(area);
+        let [folders, ignore] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(panels);
+        return (Some([folders, ignore]), list);
+    }
+    let panel_width = (area.width * 3 / 10).min(PANEL_MAX_WIDTH);
+    let [panels, list] =
+        Layout::horizontal([Constraint::Length(panel_width), Constraint::Min(0)]).areas(area);
+    let [folders, ignore] =
+        Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(panels);
+    (Some([folders, ignore]), list)
+}
 
 pub fn draw(f: &mut Frame<'_>, app: &mut App) {
     let area = f.area();
 
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        let notice = Paragraph::new(format!(
+            "Terminal too small ({}x{}, need {}x{})",
+            area.width, area.height, MIN_WIDTH, MIN_HEIGHT
+        ))
+        .wrap(Wrap { trim: true });
+        f.render_widget(notice, area);
+        return;
+    }
+
     // Main layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -22,54 +345,129 @@ pub fn draw(f: &mut Frame<'_>, app: &mut App) {
         .split(area);
 
     // Top bar with directory info and scan results
+    let root = root_label(app);
     let dir_info = match app.state {
-        AppState::Scanning => format!("Scanning: {}", app.current_directory.display()),
-        AppState::Stopping => format!("Stopping: {}", app.current_directory.display()),
-        AppState::ScanComplete | AppState::DeletionComplete => {
-            format!("Scanned: {}", app.current_directory.display())
+        AppState::Scanning => format!("Scanning: {}", root),
+        AppState::Stopping => format!("Stopping: {}", root),
+        AppState::Deleting => format!("Deleting in: {}", root),
+        AppState::ScanComplete | AppState::ConfirmPreview | AppState::DeletionComplete
+            if app.scan_results.stopped =>
+        {
+            format!("Scan stopped (partial results): {}", root)
+        }
+        AppState::ScanComplete | AppState::ConfirmPreview | AppState::DeletionComplete => {
+            format!("Scan completed: {}", root)
         }
     };
+    // Watch mode status
+    let dir_info = match (&app.watcher, &app.watch_error) {
+        (Some(_), _) => format!("{} (watching)", dir_info),
+        (None, Some(err)) => format!("{} (watch stopped: {})", dir_info, err),
+        (None, None) => dir_info,
+    };
+    // The count `[`/`]` adjust
+    let dir_info = match app.keep_recent {
+        Some(keep) => format!("{} (keeping {} most recent per target)", dir_info, keep),
+        None => dir_info,
+    };
+    let dir_info = if app.targets_unconfirmed {
+        format!("{} (read-only: no targets given)", dir_info)
+    } else if app.read_only {
+        format!("{} (read-only)", dir_info)
+    } else {
+        dir_info
+    };
     let scan_results_text = match app.state {
         AppState::Scanning => {
-            let spinner = SPINNER_CHARS[app.spinner_index];
+            let spinner = app.spinner_style.chars()[app.spinner_index];
             let path_str = app
                 .current_scan_path
                 .as_ref()
                 .map(|p| p.to_string_lossy())
                 .unwrap_or_default();
-            format!("{} {}", spinner, path_str)
+            match app.scan_eta {
+                Some(eta) => format!("{} ETA ~{} {}", spinner, human_duration(eta), path_str),
+                None => format!("{} {}", spinner, path_str),
+            }
         }
         AppState::Stopping => "Please wait...".to_string(),
-        AppState::ScanComplete | AppState::DeletionComplete => format!(
-            "Scan completed {} folders, found {} folders",
-            app.scan_results.total_folders, app.scan_results.found_folders
-        ),
+        AppState::Deleting => {
+            let (done, total) = app.deletion_progress;
+            format!(
+                "{} {}/{} folders...",
+                app.delete_mode.progress_label(),
+                done,
+                total
+            )
+        }
+        AppState::ScanComplete | AppState::ConfirmPreview | AppState::DeletionComplete => {
+            let summary = format!(
+                "Scanned {} folders. Found {} folders ({}), {} selected ({})",
+                app.scan_results.total_folders,
+                app.scan_results.found_folders,
+                human_size(app.scan_results.total_size_bytes, app.size_units),
+                app.scan_results.selected_folders_count,
+                human_size(app.scan_results.selected_size_bytes, app.size_units)
+            );
+            let summary = format!(
+                "{}. {}",
+                summary,
+                reclaimable_summary(
+                    app.scan_results.total_size_bytes,
+                    app.disk_total,
+                    app.size_units
+                )
+            );
+            // Unreadable directories were skipped, so the totals may be low
+            let summary = match app.scan_warnings.len() {
+                0 => summary,
+                skipped => format!("{}, {} unreadable skipped", summary, skipped),
+            };
+            // `--since-last-run` leaves out what the last run already showed
+            let summary = match app.scan_results.seen_before {
+                0 => summary,
+                seen => format!("{}, {} unchanged since last run hidden", summary, seen),
+            };
+            match app.scan_results.limit_reached {
+                Some(limit) => format!(
+                    "{} (stopped early, showing first {} matches)",
+                    summary, limit
+                ),
+                None => summary,
+            }
+        }
     };
-    let top_paragraph = Paragraph::new(scan_results_text)
-        .block(Block::default().title(dir_info).borders(Borders::ALL));
+    let mut top_block = Block::default().title(dir_info).borders(Borders::ALL);
+    // Cleanups can't work without a trash, so say so up front
+    if let Some(banner) = app.trash_unavailable_banner() {
+        top_block = top_block.title(
+            Line::from(format!(" {} ", banner))
+                .right_aligned()
+                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        );
+    }
+    let top_paragraph = Paragraph::new(scan_results_text).block(top_block);
     f.render_widget(top_paragraph, chunks[0]);
 
-    // Content area
-    let content_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-        .split(chunks[1]);
-
-    // Left panel area
-    let left_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(content_chunks[0]);
+    // Content area, laid out for the terminal's width
+    let panel_rows = (app.scan_config.folders_to_clean.len() + app.scan_config.target_globs.len())
+        .max(app.scan_config.ignore_patterns.len()) as u16;
+    let (panel_areas, list_area) = content_layout(chunks[1], panel_rows);
 
-    // Top-left panel - folders to clean
+    // Folders to clean
     let mut folder_items = Vec::new();
-    for (i, folder) in app.folders_to_clean.iter().enumerate() {
-        let checked = if app.selected_folders[i] {
-            "[x]"
-        } else {
-            "[ ]"
-        };
-        folder_items.push(ListItem::new(format!("{} {}", checked, folder)));
+    for (i, folder) in app.scan_config.folders_to_clean.iter().enumerate() {
+        let checked = if app.folder_selected(i) { "[x]" } else { "[ ]" };
+        folder_items.push(ListItem::new(Line::from(vec![
+            Span::raw(format!("{} ", checked)),
+            Span::styled(folder.as_str(), target_style(app, folder)),
+        ])));
+    }
+    for glob in &app.scan_config.target_globs {
+        folder_items.push(ListItem::new(Line::from(vec![
+            Span::raw("[x] "),
+            Span::styled(glob.as_str(), target_style(app, glob)),
+        ])));
     }
 
     let folders_list = List::new(folder_items)
@@ -80,10 +478,9 @@ pub fn draw(f: &mut Frame<'_>, app: &mut App) {
         )
         .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
-    f.render_widget(folders_list, left_chunks[0]);
-
-    // Bottom-left panel - ignore patterns
+    // Ignore patterns
     let ignore_items: Vec<ListItem> = app
+        .scan_config
         .ignore_patterns
         .iter()
         .map(|p| ListItem::new(p.as_str()))
@@ -94,80 +491,133 @@ pub fn draw(f: &mut Frame<'_>, app: &mut App) {
             .title("Ignore Patterns")
             .borders(Borders::ALL),
     );
-    f.render_widget(ignore_list, left_chunks[1]);
+    if let Some([folders_area, ignore_area]) = panel_areas {
+        f.render_widget(folders_list, folders_area);
+        f.render_widget(ignore_list, ignore_area);
+    }
 
-    // Right panel - files to clean
-    let mut file_items = Vec::new();
+    // Results list - files to clean
+    let mut file_rows = Vec::new();
 
     if app.dirs_to_clean.is_empty() {
         if matches!(app.state, AppState::ScanComplete) {
-            file_items.push(ListItem::new("No matching directories found"));
+            file_rows.push(message_row(app, "No matching directories found"));
         }
         // else: show nothing while scanning
     } else {
-        for dir in app.dirs_to_clean.iter() {
-            let checked = if dir.selected { "[x]" } else { "[ ]" };
-
-            // Format directory size for display
-            let size_text = if dir.size_bytes < 1024 {
-                format!("{} B", dir.size_bytes)
-            } else if dir.size_bytes < 1024 * 1024 {
-                format!("{} KB", dir.size_bytes / 1024)
-            } else if dir.size_bytes < 1024 * 1024 * 1024 {
-                format!("{} MB", dir.size_bytes / (1024 * 1024))
+        let layout = app.layout();
+        let visual_range = app.visual_range();
+        for (i, row) in layout.rows.iter().enumerate() {
+            let table_row = match *row {
+                Row::Dir(index) => dir_row(app, &app.dirs_to_clean[index]),
+                Row::Group(index) => group_row(app, &layout.groups[index]),
+                Row::Child { dir, child } => {
+                    let path = &app.dirs_to_clean[dir].path;
+                    let (child, size) = &app.expanded_dirs[path][child];
+                    child_row(app, child, *size)
+                }
+            };
+            // Rows a `V` range covers
+            let table_row = if visual_range.as_ref().is_some_and(|rows| rows.contains(&i)) {
+                table_row.style(Style::default().bg(Color::DarkGray))
             } else {
-                format!(
-                    "{:.1} GB",
-                    dir.size_bytes as f64 / (1024.0 * 1024.0 * 1024.0)
-                )
+                table_row
             };
-
-            // Show size and full path instead of just folder name
-            let item_text = format!("{} {} → {}", checked, size_text, dir.path.display());
-
-            let item = ListItem::new(item_text);
-            file_items.push(item);
+            file_rows.push(table_row);
+        }
+        if layout.rows.is_empty() && app.hide_unselected {
+            file_rows.push(message_row(app, "Nothing selected (press h to show all)"));
         }
     }
 
-    // Create list widget for directories
-    let title = if app.scan_results.selected_size_gb > 0.0 {
+    // Create table widget for directories
+    let title = if app.scan_results.selected_size_bytes > 0 {
         format!(
-            "Directories to clean: {:.2} GB selected",
-            app.scan_results.selected_size_gb
+            "Directories to clean: {} selected",
+            human_size(app.scan_results.selected_size_bytes, app.size_units)
         )
     } else {
         "Directories to clean".to_string()
     };
-    let dirs_list = List::new(file_items)
-        .block(Block::default().title(title).borders(Borders::ALL))
-        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    // The age threshold `+`/`-` adjust
+    let title = format!(
+        "{} (auto-select > {} days)",
+        title, app.scan_config.select_older_than_days
+    );
+    let title = if app.hide_unselected {
+        format!("{} (unselected hidden)", title)
+    } else {
+        title
+    };
+    let title = match app.visual_range() {
+        Some(rows) => format!("{} (range: {} rows, Space toggles)", title, rows.count()),
+        None => title,
+    };
+    let columns = columns(app);
+    let header = TableRow::new(
+        columns
+            .iter()
+            .map(|(column, _)| header_cell(app, *column))
+            .collect::<Vec<_>>(),
+    )
+    .style(Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED));
+    let dirs_block = Block::default().title(title).borders(Borders::ALL);
+    // Remember where the headers are, laid out the way the table lays out its columns
+    let header_area = dirs_block.inner(list_area);
+    let header_cells = Layout::horizontal(columns.iter().map(|(_, width)| *width))
+        .flex(Flex::Start)
+        .spacing(1)
+        .split(Rect {
+            height: header_area.height.min(1),
+            ..header_area
+        });
+    app.sort_headers = columns
+        .iter()
+        .zip(header_cells.iter())
+        .map(|((column, _), area)| (*area, *column))
+        .collect();
+    let dirs_table = Table::new(file_rows, columns.iter().map(|(_, width)| *width))
+        .header(header)
+        .block(dirs_block)
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
-    f.render_stateful_widget(dirs_list, content_chunks[1], &mut app.dir_list_state);
+    f.render_stateful_widget(dirs_table, list_area, &mut app.dir_list_state);
 
     // Bottom panel - instructions
-    let help_text = "ESC: cancel/quit | ↑/↓: up/down | Space: toggle selection \na/d: select/deselect all | c: clean selected";
-    let help_block = Block::default()
+    let help_text = Line::from(keymap::hint_line(app.read_only, &app.key_bindings));
+    let mut help_block = Block::default()
         .title("Instructions")
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Blue));
-    let help_paragraph = Paragraph::new(help_text).block(help_block);
+    if let Some(reminder) = app.trash_reminder() {
+        help_block = help_block.title(Line::from(format!(" {} ", reminder)).right_aligned());
+    }
+    let help_paragraph = Paragraph::new(help_text)
+        .block(help_block)
+        .wrap(Wrap { trim: true });
 
     f.render_widget(help_paragraph, chunks[2]);
 
     // Handle confirmation
     if let Some(ref action) = app.confirm_action {
-        let confirm_text = format!("{}? (Y/n)", action);
+        let confirm_text = if app.pending_single_delete.is_some() && app.delete_mode.is_permanent()
+        {
+            format!("{}? This cannot be undone (Y/n)", action)
+        } else {
+            format!("{}? (Y/n)", action)
+        };
         let confirm_block = Block::default()
             .title("Confirm Action")
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Red));
+        let text_width = confirm_text.chars().count() as u16 + 1;
         let confirm_paragraph = Paragraph::new(confirm_text)
             .block(confirm_block)
-            .style(Style::default().bg(Color::DarkGray));
+            .style(Style::default().bg(Color::DarkGray))
+            .wrap(Wrap { trim: true });
 
         // Calculate position to center the confirmation message
-        let text_width = action.len() as u16 + 8; // approx width for action + "? (Y/n)"
+
         let area_width = area.width;
         let area_height = area.height;
         let popup_width = std::cmp::min(text_width + 4, area_width.saturating_sub(4));
@@ -184,37 +634,613 @@ pub fn draw(f: &mut Frame<'_>, app: &mut App) {
         f.render_widget(confirm_paragraph, confirm_area);
     }
 
-    // Handle Deletion Summary
-    if let AppState::DeletionComplete = app.state {
-        if let Some((count, size)) = app.deletion_summary {
-            let size_gb = size as f64 / (1024.0 * 1024.0 * 1024.0);
-            let summary_text = format!(
-                "Cleaned {} folders, freeing {:.2} GB.\n\nPress 'y' or 'enter' to exit.",
-                count, size_gb
-            );
-            let summary_block = Block::default()
-                .title("Deletion Complete")
+    // Handle deletion preview: every selected path with its size
+    if let AppState::ConfirmPreview = app.state {
+        let preview_lines: Vec<Line> = app
+            .dirs_to_clean
+            .iter()
+            .filter(|dir| dir.selected)
+            .map(|dir| {
+                let line = format!(
+                    "{:>10}  {}",
+                    human_size(dir.size_bytes, app.size_units),
+                    dir.path.display()
+                );
+                // A process still has files open in it
+                if app.in_use.contains(&dir.path) {
+                    Line::from(vec![
+                        Span::raw(line),
+                        Span::styled(" (in use)", Style::default().fg(Color::Red)),
+                    ])
+                } else {
+                    Line::from(line)
+                }
+            })
+            .collect();
+        let prompt = match &app.typed_confirmation {
+            Some(typed) => format!(
+                "Type {} and press Enter to confirm: {}_",
+                typed.expected, typed.input
+            ),
+            None => "y: confirm | n/Esc: cancel | ↑/↓: scroll".to_string(),
+        };
+        // Only worth mentioning once it's more than a moment
+        let estimate = app.deletion_estimate();
+        let prompt = if estimate >= Duration::from_secs(1) {
+            format!(
+                "Estimated deletion: ~{} | {}",
+                human_duration(estimate),
+                prompt
+            )
+        } else {
+            prompt
+        };
+
+        let popup_width = area.width.saturating_sub(4) * 4 / 5;
+        let popup_height = std::cmp::min(
+            preview_lines.len() as u16 + 3,
+            area.height.saturating_sub(4),
+        );
+        let preview_area = Rect {
+            x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+            y: area.y + (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+        // Red for a cleanup that can't be undone
+        let preview_color = if app.delete_mode.is_permanent() {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+        let preview_block = Block::default()
+            .title(Span::styled(
+                app.preview_question(),
+                Style::default()
+                    .fg(preview_color)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(preview_color));
+        let inner = preview_block.inner(preview_area);
+        let preview_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        f.render_widget(Clear, preview_area);
+        f.render_widget(preview_block, preview_area);
+        f.render_widget(
+            Paragraph::new(preview_lines).scroll((app.preview_scroll, 0)),
+            preview_chunks[0],
+        );
+        f.render_widget(
+            Paragraph::new(prompt).style(Style::default().add_modifier(Modifier::BOLD)),
+            preview_chunks[1],
+        );
+    }
+
+    // Handle deletion progress
+    if let AppState::Deleting = app.state {
+        let (done, total) = app.deletion_progress;
+        let ratio = if total > 0 {
+            done as f64 / total as f64
+        } else {
+            0.0
+        };
+        let gauge = Gauge::default()
+            .block(
+                Block::default()
+                    .title(app.delete_mode.progress_label())
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .gauge_style(Style::default().fg(Color::Green).bg(Color::DarkGray))
+            .label(format!("{}/{}", done, total))
+            .ratio(ratio);
+
+        let popup_width = std::cmp::min(50, area.width.saturating_sub(4));
+        let popup_height = 3;
+        let gauge_area = Rect {
+            x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+            y: area.y + (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, gauge_area);
+        f.render_widget(gauge, gauge_area);
+    }
+
+    // Handle Deletion Summary, unless the empty-trash question is on top of it
+    if let AppState::DeletionComplete = app.state
+        && let Some(report) = &app.deletion_report
+        && !app.pending_empty_trash
+    {
+        let report_note = app
+            .report_error
+            .as_ref()
+            .map(|err| format!("\n{}", err))
+            .unwrap_or_default();
+        // Trash failures are never silently turned into permanent deletes, so say what was left
+        let failures = report.failure_lines();
+        let failed_note = match failures.len() {
+            0 => String::new(),
+            failed => {
+                let shown: Vec<&str> = failures
+                    .iter()
+                    .take(SUMMARY_FAILURES_SHOWN)
+                    .map(String::as_str)
+                    .collect();
+                let more = match failed.saturating_sub(SUMMARY_FAILURES_SHOWN) {
+                    0 => String::new(),
+                    more => format!("\n…and {} more", more),
+                };
+                format!(
+                    "\n{} could not be removed and were left in place:\n{}{}",
+                    failed,
+                    shown.join("\n"),
+                    more
+                )
+            }
+        };
+        let gone_note = report
+            .already_gone_note()
+            .map(|note| format!("\nSkipped {}.", note))
+            .unwrap_or_default();
+        // Trashed folders only free space once the trash is emptied
+        let trash_note = match &app.empty_trash_result {
+            Some(Ok(())) => "\nEmptied the trash.".to_string(),
+            Some(Err(err)) => format!("\nCould not empty the trash: {}", err),
+            None if app.can_empty_trash() => "\nPress 'e' to empty the trash.".to_string(),
+            None => String::new(),
+        };
+        let summary_text = format!(
+            "Cleaned {} folders, freeing {}.{}{}{}{}\n\nPress 'y' or 'enter' to exit.",
+            report.succeeded.len(),
+            human_size(report.bytes_freed, app.size_units),
+            gone_note,
+            failed_note,
+            report_note,
+            trash_note
+        );
+        let summary_block = Block::default()
+            .title("Deletion Complete")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green));
+        let text_lines = summary_text.lines().count() as u16;
+        let summary_paragraph = Paragraph::new(summary_text)
+            .block(summary_block)
+            .style(Style::default().bg(Color::DarkGray))
+            .alignment(ratatui::layout::Alignment::Center);
+
+        let area_width = area.width;
+        let area_height = area.height;
+        let popup_width = if failures.is_empty() {
+            50
+        } else {
+            area_width.saturating_sub(4).clamp(50, 100)
+        };
+        let popup_height = (7 + text_lines.saturating_sub(3)).min(area_height);
+
+        let summary_area = Rect {
+            x: area.x + (area_width.saturating_sub(popup_width)) / 2,
+            y: area.y + (area_height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, summary_area);
+        f.render_widget(summary_paragraph, summary_area);
+    }
+
+    // Handle directory contents popup, on the right over the results list
+    if let Some(contents) = &app.contents {
+        let mut lines: Vec<Line> = if contents.children.is_empty() {
+            vec![Line::from("(empty or unreadable)")]
+        } else {
+            contents
+                .children
+                .iter()
+                .map(|(path, size)| {
+                    Line::from(format!(
+                        "{:>10}  {}",
+                        human_size(*size, app.size_units),
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ))
+                })
+                .collect()
+        };
+        // What kinds of files the space goes to
+        if !contents.extensions.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                "By extension",
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            lines.extend(contents.extensions.iter().map(|(extension, size)| {
+                Line::from(format!(
+                    "{:>10}  {}",
+                    human_size(*size, app.size_units),
+                    extension
+                ))
+            }));
+        }
+        let contents_paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title(format!(
+                        "Inside {} (p/Esc close)",
+                        short_name(&contents.path)
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Blue)),
+            )
+            .scroll((contents.scroll, 0));
+
+        let contents_area = list_area;
+        f.render_widget(Clear, contents_area);
+        f.render_widget(contents_paragraph, contents_area);
+    }
+
+    // Handle age statistics popup
+    if app.show_stats {
+        let mut stats_lines: Vec<Line> = stats::age_buckets(&app.dirs_to_clean)
+            .iter()
+            .map(|bucket| {
+                Line::from(format!(
+                    "{:<14}{:>6} folders {:>10}",
+                    bucket.label,
+                    bucket.count,
+                    human_size(bucket.size_bytes, app.size_units)
+                ))
+            })
+            .collect();
+        stats_lines.push(Line::from(Span::styled(
+            format!(
+                "{:<14}{:>6} folders {:>10}",
+                "total",
+                app.scan_results.found_folders,
+                human_size(app.scan_results.total_size_bytes, app.size_units)
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        let stats_paragraph = Paragraph::new(stats_lines).block(
+            Block::default()
+                .title("Matches by age (s/Esc close)")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Green));
-            let summary_paragraph = Paragraph::new(summary_text)
-                .block(summary_block)
-                .style(Style::default().bg(Color::DarkGray))
-                .alignment(ratatui::layout::Alignment::Center);
-
-            let area_width = area.width;
-            let area_height = area.height;
-            let popup_width = 50;
-            let popup_height = 7;
-
-            let summary_area = Rect {
-                x: area.x + (area_width.saturating_sub(popup_width)) / 2,
-                y: area.y + (area_height.saturating_sub(popup_height)) / 2,
-                width: popup_width,
-                height: popup_height,
-            };
+                .border_style(Style::default().fg(Color::Blue)),
+        );
+
+        let popup_width = std::cmp::min(42, area.width.saturating_sub(4));
+        let popup_height = std::cmp::min(6, area.height.saturating_sub(2));
+        let stats_area = Rect {
+            x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+            y: area.y + (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, stats_area);
+        f.render_widget(stats_paragraph, stats_area);
+    }
+
+    // Handle directory browser
+    if let Some(browser) = &mut app.browser {
+        let items: Vec<ListItem> = browser
+            .entries
+            .iter()
+            .map(|(label, _)| ListItem::new(label.as_str()))
+            .collect();
+        let footer = browser.error.clone().unwrap_or_else(|| {
+            "Enter: open | Backspace: up | s: scan here | Esc: close".to_string()
+        });
+
+        let popup_width = area.width.saturating_sub(4) * 3 / 5;
+        let popup_height = area.height.saturating_sub(4) * 4 / 5;
+        let browser_area = Rect {
+            x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+            y: area.y + (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+        let browser_block = Block::default()
+            .title(format!("Browse: {}", browser.path.display()))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Blue));
+        let inner = browser_block.inner(browser_area);
+        let browser_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+        let footer_style = if browser.error.is_some() {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+
+        f.render_widget(Clear, browser_area);
+        f.render_widget(browser_block, browser_area);
+        f.render_stateful_widget(
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            browser_chunks[0],
+            &mut browser.list_state,
+        );
+        f.render_widget(
+            Paragraph::new(footer).style(footer_style),
+            browser_chunks[1],
+        );
+    }
+
+    // Handle help popup
+    if app.show_help {
+        let key_width = keymap::bindings(app.read_only)
+            .map(|binding| binding.keys_label(&app.key_bindings, false).chars().count())
+            .max()
+            .unwrap_or(0);
+        let help_lines: Vec<Line> = keymap::bindings(app.read_only)
+            .map(|binding| {
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "{:<width$}  ",
+                            binding.keys_label(&app.key_bindings, false),
+                            width = key_width
+                        ),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(binding.description),
+                ])
+            })
+            .collect();
+        let help_height = help_lines.len() as u16;
+        let help_paragraph = Paragraph::new(help_lines)
+            .block(
+                Block::default()
+                    .title("Help (↑/↓ scroll, ?/Esc close)")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Blue)),
+            )
+            .scroll((app.help_scroll, 0));
+
+        let popup_width = std::cmp::min(72, area.width.saturating_sub(4));
+        let popup_height = std::cmp::min(help_height + 2, area.height.saturating_sub(2));
+        let help_area = Rect {
+            x: area.x + (area.width.saturating_sub(popup_width)) / 2,
+            y: area.y + (area.height.saturating_sub(popup_height)) / 2,
+            width: popup_width,
+            height: popup_height,
+        };
+
+        f.render_widget(Clear, help_area);
+        f.render_widget(help_paragraph, help_area);
+    }
+
+    // Handle toast, in the bottom right corner just above the instructions
+    if let Some(toast) = app.current_toast(Instant::now()) {
+        let toast_width = std::cmp::min(
+            toast.chars().count() as u16 + 4,
+            area.width.saturating_sub(2),
+        );
+        let toast_height = 3;
+        let toast_area = Rect {
+            x: area.x + area.width.saturating_sub(toast_width + 1),
+            y: chunks[2].y.saturating_sub(toast_height),
+            width: toast_width,
+            height: toast_height,
+        };
+        let toast_paragraph = Paragraph::new(toast).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+
+        f.render_widget(Clear, toast_area);
+        f.render_widget(toast_paragraph, toast_area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::TOAST_DURATION;
+    use ratatui::{Terminal, backend::TestBackend};
 
-            f.render_widget(Clear, summary_area);
-            f.render_widget(summary_paragraph, summary_area);
+    fn render(width: u16, height: u16) -> String {
+        render_app(&mut App::new(), width, height)
+    }
+
+    fn render_app(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn tiny_terminal_shows_notice_instead_of_layout() {
+        let screen = render(10, 3);
+        assert!(screen.contains("Terminal"));
+        assert!(!screen.contains("Instructions"));
+    }
+
+    #[test]
+    fn normal_terminal_draws_the_panels() {
+        let screen = render(80, 24);
+        assert!(screen.contains("Instructions"));
+        assert!(!screen.contains("too small"));
+    }
+
+    #[test]
+    fn header_tells_a_stopped_scan_from_a_finished_one() {
+        let mut app = App::new();
+        app.state = AppState::ScanComplete;
+        let finished = render_app(&mut app, 100, 24);
+        assert!(finished.contains("Scan completed:"));
+        assert!(!finished.contains("partial results"));
+
+        app.scan_results.stopped = true;
+        let stopped = render_app(&mut app, 100, 24);
+        assert!(stopped.contains("Scan stopped (partial results):"));
+        assert!(!stopped.contains("Scan completed"));
+    }
+
+    #[test]
+    fn footer_reminds_about_a_full_trash() {
+        let mut app = App::new();
+        app.state = AppState::ScanComplete;
+        assert!(!render_app(&mut app, 100, 24).contains("Trash holds"));
+
+        app.trash_size = Some(3 * 1024 * 1024);
+        let screen = render_app(&mut app, 100, 24);
+        assert!(screen.contains("Trash holds ~3 MiB from prior cleanups"));
+        assert!(screen.contains("Instructions"));
+    }
+
+    #[test]
+    fn toast_is_drawn_over_the_screen_until_it_expires() {
+        let mut app = App::new();
+        app.state = AppState::ScanComplete;
+        app.set_toast("Copied /projects/app/target");
+        let screen = render_app(&mut app, 100, 24);
+        assert!(screen.contains("Copied /projects/app/target"));
+        assert!(screen.contains("Instructions"));
+
+        app.toast = Some(("Copied".to_string(), Instant::now() - TOAST_DURATION));
+        assert!(!render_app(&mut app, 100, 24).contains("Copied"));
+    }
+
+    #[test]
+    fn clicking_a_column_header_sorts_by_it() {
+        let mut app = App::new();
+        app.session_file = None;
+        app.state = AppState::ScanComplete;
+        for (path, size, age) in [("/p/a/target", 3000, 5), ("/p/b/target", 100, 50)] {
+            app.handle_scan_update(crate::app::ScanUpdate::Result(DirInfo {
+                modified_days_ago: age,
+                selected: true,
+                size_bytes: size,
+                ..DirInfo::for_test(path)
+            }));
         }
+        let screen = render_app(&mut app, 100, 16);
+        assert!(screen.contains("1 Sel"));
+        assert!(screen.contains("3 Modified ▲"));
+
+        let (size_header, _) = app.sort_headers[1];
+        app.handle_click(size_header.x + 1, size_header.y);
+        let screen = render_app(&mut app, 100, 16);
+        assert!(screen.contains("2 Size ▼"));
+        assert_eq!(app.dirs_to_clean[0].size_bytes, 3000);
+
+        // Clicks below the header leave the order alone
+        app.handle_click(size_header.x + 1, size_header.y + 1);
+        assert!(app.sort_order.descending);
+
+        // The compact view drops the date column from the table and its headers
+        app.view_mode = ViewMode::Compact;
+        let screen = render_app(&mut app, 100, 16);
+        assert!(!screen.contains("Modified"));
+        assert_eq!(app.sort_headers.len(), 3);
+    }
+
+    #[test]
+    fn folders_panel_survives_out_of_step_selection_flags() {
+        let mut app = App::new();
+        app.scan_config.folders_to_clean =
+            vec!["target".into(), "node_modules".into(), ".venv".into()];
+        app.selected_folders = vec![false];
+        let screen = render_app(&mut app, 100, 24);
+        assert!(screen.contains("[ ] target"));
+        assert!(screen.contains("[x] node_modules"));
+        assert!(screen.contains("[x] .venv"));
+
+        // More flags than folders is harmless too
+        app.scan_config.folders_to_clean = vec!["target".into()];
+        app.selected_folders = vec![true; 5];
+        assert!(render_app(&mut app, 100, 24).contains("[x] target"));
+    }
+
+    #[test]
+    fn permanent_cleanup_preview_is_red_and_says_so() {
+        let mut app = App::new();
+        app.handle_scan_update(crate::app::ScanUpdate::Result(DirInfo {
+            modified_days_ago: 40,
+            selected: true,
+            size_bytes: 1024,
+            ..DirInfo::for_test("/missing/a/target")
+        }));
+        app.handle_scan_update(crate::app::ScanUpdate::Done);
+        app.state = AppState::ConfirmPreview;
+        let screen = render_app(&mut app, 120, 30);
+        assert!(screen.contains("Move 1 selected items (1 KiB) to trash?"));
+
+        app.delete_mode = crate::delete::DeleteMode::Permanent;
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|f| draw(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let cells = buffer.content();
+        let screen: String = cells.iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("This cannot be undone"));
+        let title = "PERMANENTLY DELETE";
+        let at = (0..cells.len() - title.len())
+            .find(|&i| {
+                cells[i..i + title.len()]
+                    .iter()
+                    .map(|cell| cell.symbol())
+                    .eq(title.split("").filter(|c| !c.is_empty()))
+            })
+            .unwrap();
+        assert_eq!(cells[at].fg, Color::Red);
+    }
+
+    // Row and column of the first cell where `needle` starts
+    fn locate(app: &mut App, width: u16, height: u16, needle: &str) -> Option<(usize, usize)> {
+        let screen: Vec<char> = render_app(app, width, height).chars().collect();
+        screen
+            .chunks(width as usize)
+            .enumerate()
+            .find_map(|(row, line)| {
+                let line: String = line.iter().collect();
+                line.find(needle)
+                    .map(|byte| (row, line[..byte].chars().count()))
+            })
+    }
+
+    #[test]
+    fn panels_sit_beside_the_list_and_stop_growing_on_wide_terminals() {
+        let mut app = App::new();
+        let (folders_row, _) = locate(&mut app, 100, 30, "Folders to clean").unwrap();
+        let (list_row, list_col) = locate(&mut app, 100, 30, "Directories to clean").unwrap();
+        assert_eq!(list_row, folders_row);
+        assert_eq!(list_col, 31);
+
+        let (list_row, list_col) = locate(&mut app, 200, 30, "Directories to clean").unwrap();
+        assert_eq!(list_row, folders_row);
+        assert_eq!(list_col, PANEL_MAX_WIDTH as usize + 1);
+    }
+
+    #[test]
+    fn narrow_terminals_stack_the_panels_or_hide_them() {
+        let mut app = App::new();
+        let (folders_row, _) = locate(&mut app, 60, 30, "Folders to clean").unwrap();
+        let (ignore_row, _) = locate(&mut app, 60, 30, "Ignore Patterns").unwrap();
+        let (list_row, list_col) = locate(&mut app, 60, 30, "Directories to clean").unwrap();
+        assert_eq!(ignore_row, folders_row);
+        assert!(list_row > folders_row);
+        // The list gets the full width
+        assert_eq!(list_col, 1);
+
+        // Too short to stack them as well
+        assert_eq!(locate(&mut app, 60, 14, "Folders to clean"), None);
+        assert_eq!(
+            locate(&mut app, 60, 14, "Directories to clean"),
+            Some((3, 1))
+        );
     }
 }
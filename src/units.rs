@@ -0,0 +1,153 @@
+// Unit system used when displaying sizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeUnits {
+    // Powers of 1024: KiB, MiB, GiB
+    #[default]
+    Binary,
+    // Powers of 1000: kB, MB, GB (what `df -H` and most vendors report)
+    Si,
+}
+
+impl SizeUnits {
+    fn base(self) -> u64 {
+        match self {
+            SizeUnits::Binary => 1024,
+            SizeUnits::Si => 1000,
+        }
+    }
+
+    fn labels(self) -> [&'static str; 3] {
+        match self {
+            SizeUnits::Binary => ["KiB", "MiB", "GiB"],
+            SizeUnits::Si => ["kB", "MB", "GB"],
+        }
+    }
+}
+
+// Format a byte count for display
+pub fn human_size(bytes: u64, units: SizeUnits) -> String {
+    let base = units.base();
+    let [kilo, mega, giga] = units.labels();
+    if bytes < base {
+        format!("{} B", bytes)
+    } else if bytes < base * base {
+        format!("{} {}", bytes / base, kilo)
+    } else if bytes < base * base * base {
+        format!("{} {}", bytes / (base * base), mega)
+    } else {
+        format!("{:.1} {}", bytes as f64 / (base * base * base) as f64, giga)
+    }
+}
+
+// `part` as a percentage of `whole`, or `None` when there's no whole to compare with
+pub fn percent_of(part: u64, whole: u64) -> Option<f64> {
+    match whole {
+        0 => None,
+        whole => Some(part as f64 * 100.0 / whole as f64),
+    }
+}
+
+// e.g. "Reclaimable: 14.2 GiB (6.1% of 234.0 GiB disk)", without the share when the disk
+// size is unknown
+pub fn reclaimable_summary(reclaimable: u64, disk_total: Option<u64>, units: SizeUnits) -> String {
+    let amount = human_size(reclaimable, units);
+    match disk_total.and_then(|total| Some((total, percent_of(reclaimable, total)?))) {
+        Some((total, percent)) => format!(
+            "Reclaimable: {} ({:.1}% of {} disk)",
+            amount,
+            percent,
+            human_size(total, units)
+        ),
+        None => format!("Reclaimable: {}", amount),
+    }
+}
+
+// Format a count for display: `950`, `48k`, `1.2M`
+pub fn human_count(count: u64) -> String {
+    if count < 1000 {
+        count.to_string()
+    } else if count < 1_000_000 {
+        format!("{}k", count / 1000)
+    } else {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    }
+}
+
+// Format a duration as `12s`, `3m 05s` or `1h 02m`
+pub fn human_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_units_at_boundaries() {
+        let units = SizeUnits::Binary;
+        assert_eq!(human_size(1023, units), "1023 B");
+        assert_eq!(human_size(1024, units), "1 KiB");
+        assert_eq!(human_size(1024 * 1024 - 1, units), "1023 KiB");
+        assert_eq!(human_size(1024 * 1024, units), "1 MiB");
+        assert_eq!(human_size(1024 * 1024 * 1024 - 1, units), "1023 MiB");
+        assert_eq!(human_size(1024 * 1024 * 1024, units), "1.0 GiB");
+        assert_eq!(human_size(1_000_000_000, units), "953 MiB");
+    }
+
+    #[test]
+    fn si_units_at_boundaries() {
+        let units = SizeUnits::Si;
+        assert_eq!(human_size(999, units), "999 B");
+        assert_eq!(human_size(1000, units), "1 kB");
+        assert_eq!(human_size(999_999, units), "999 kB");
+        assert_eq!(human_size(1_000_000, units), "1 MB");
+        assert_eq!(human_size(999_999_999, units), "999 MB");
+        assert_eq!(human_size(1_000_000_000, units), "1.0 GB");
+        assert_eq!(human_size(1024 * 1024 * 1024, units), "1.1 GB");
+    }
+
+    #[test]
+    fn durations_at_boundaries() {
+        let secs = std::time::Duration::from_secs;
+        assert_eq!(human_duration(secs(12)), "12s");
+        assert_eq!(human_duration(secs(59)), "59s");
+        assert_eq!(human_duration(secs(185)), "3m 05s");
+        assert_eq!(human_duration(secs(3720)), "1h 02m");
+    }
+
+    #[test]
+    fn reclaimable_share_of_the_disk() {
+        assert_eq!(percent_of(61, 1000), Some(6.1));
+        assert_eq!(percent_of(5, 0), None);
+
+        let gib = 1024 * 1024 * 1024;
+        assert_eq!(
+            reclaimable_summary(gib, Some(16 * gib), SizeUnits::Binary),
+            "Reclaimable: 1.0 GiB (6.2% of 16.0 GiB disk)"
+        );
+        // A zero or unknown disk size leaves the share out instead of dividing by zero
+        assert_eq!(
+            reclaimable_summary(gib, Some(0), SizeUnits::Binary),
+            "Reclaimable: 1.0 GiB"
+        );
+        assert_eq!(
+            reclaimable_summary(gib, None, SizeUnits::Binary),
+            "Reclaimable: 1.0 GiB"
+        );
+    }
+
+    #[test]
+    fn counts_are_shortened_by_thousands() {
+        assert_eq!(human_count(999), "999");
+        assert_eq!(human_count(1000), "1k");
+        assert_eq!(human_count(48_213), "48k");
+        assert_eq!(human_count(1_250_000), "1.2M");
+    }
+}
@@ -0,0 +1,128 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+// How long the tree has to stay quiet before a change triggers a rescan
+pub const DEBOUNCE: Duration = Duration::from_secs(2);
+
+// Collapses a burst of changes into a single rescan once things settle down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Debouncer {
+    delay: Duration,
+    last_change: Option<Instant>,
+}
+
+impl Debouncer {
+    pub fn new(delay: Duration) -> Self {
+        Debouncer {
+            delay,
+            last_change: None,
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    // Whether a change is waiting and has been quiet long enough; clears it if so
+    pub fn take_due(&mut self, now: Instant) -> bool {
+        match self.last_change {
+            Some(last) if now.duration_since(last) >= self.delay => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Recursively watches the scan root for changes
+pub struct TreeWatcher {
+    // Kept alive for as long as events should arrive; `None` when fed by hand in tests
+    _watcher: Option<RecommendedWatcher>,
+    events: mpsc::Receiver<notify::Result<Event>>,
+    pub debouncer: Debouncer,
+}
+
+impl TreeWatcher {
+    pub fn start(root: &Path) -> notify::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        })?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        Ok(TreeWatcher {
+            _watcher: Some(watcher),
+            events: rx,
+            debouncer: Debouncer::new(DEBOUNCE),
+        })
+    }
+
+    // A watcher fed from `events` instead of the filesystem
+    #[cfg(test)]
+    pub fn from_channel(events: mpsc::Receiver<notify::Result<Event>>, delay: Duration) -> Self {
+        TreeWatcher {
+            _watcher: None,
+            events,
+            debouncer: Debouncer::new(delay),
+        }
+    }
+
+    // Drain pending events into the debouncer. Fails once the watcher reports an error
+    // or goes away, after which no more changes will be seen.
+    pub fn poll(&mut self, now: Instant) -> Result<(), String> {
+        loop {
+            match self.events.try_recv() {
+                // Reads (including our own scan's) aren't changes
+                Ok(Ok(event)) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(Ok(_)) => self.debouncer.record(now),
+                Ok(Err(err)) => return Err(err.to_string()),
+                Err(mpsc::TryRecvError::Empty) => return Ok(()),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Err("the watcher stopped".to_string());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debouncer_waits_for_quiet_period() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_secs(2));
+        assert!(!debouncer.take_due(start));
+
+        debouncer.record(start);
+        debouncer.record(start + Duration::from_secs(1));
+        assert!(!debouncer.take_due(start + Duration::from_secs(2)));
+        assert!(debouncer.take_due(start + Duration::from_secs(3)));
+        // Only fires once per burst
+        assert!(!debouncer.take_due(start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn poll_ignores_reads_and_reports_errors() {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = TreeWatcher::from_channel(rx, Duration::ZERO);
+        let now = Instant::now();
+
+        tx.send(Ok(Event::new(EventKind::Access(
+            notify::event::AccessKind::Any,
+        ))))
+        .unwrap();
+        watcher.poll(now).unwrap();
+        assert!(!watcher.debouncer.take_due(now));
+
+        tx.send(Err(notify::Error::generic("inotify limit reached")))
+            .unwrap();
+        assert_eq!(watcher.poll(now), Err("inotify limit reached".to_string()));
+
+        drop(tx);
+        assert!(watcher.poll(now).is_err());
+    }
+}